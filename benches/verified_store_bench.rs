@@ -48,6 +48,20 @@ fn key_length_benchmark(c: &mut Criterion) {
     group.finish();
 }
 
+fn integer_key_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("integer_keys");
+
+    let data: HashMap<u64, String> = (0..1000u64).map(|i| (i, format!("value_{}", i))).collect();
+    let store = VerifiedKvStore::new(data).unwrap();
+    let test_key = 500u64;
+
+    group.bench_function("1k_u64_keys", |b| {
+        b.iter(|| black_box(store.get(black_box(&test_key)).unwrap()))
+    });
+
+    group.finish();
+}
+
 fn construction_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("construction");
 
@@ -66,6 +80,7 @@ criterion_group!(
     benches,
     lookup_benchmark,
     key_length_benchmark,
+    integer_key_benchmark,
     construction_benchmark
 );
 criterion_main!(benches);
@@ -0,0 +1,11 @@
+//! Sets `cfg(gxhash)` when GxHash is compiled in: the `gxhash` feature is on
+//! and the target is one the `gxhash` crate supports.
+
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(gxhash)");
+    let arch = std::env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let supported = matches!(arch.as_str(), "x86" | "x86_64" | "aarch64");
+    if supported && std::env::var_os("CARGO_FEATURE_GXHASH").is_some() {
+        println!("cargo::rustc-cfg=gxhash");
+    }
+}
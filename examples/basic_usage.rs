@@ -70,7 +70,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Serialization example
     println!("\nSerialization example:");
     colors.save_to_file("colors.bin")?;
-    let loaded_colors: VerifiedKvStore<String> = VerifiedKvStore::load_from_file("colors.bin")?;
+    let loaded_colors: VerifiedKvStore<String, String> =
+        VerifiedKvStore::load_from_file("colors.bin")?;
     println!(
         "  Successfully saved and loaded {} color codes",
         loaded_colors.len()
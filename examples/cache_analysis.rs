@@ -92,7 +92,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Random access (cache unfriendly)
     let random_indices: Vec<usize> = (0..iterations.min(all_keys.len() * 100))
-        .map(|i: usize| (i.wrapping_mul(314159) % all_keys.len()))
+        .map(|i: usize| i.wrapping_mul(314159) % all_keys.len())
         .collect();
 
     let start = Instant::now();
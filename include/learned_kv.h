@@ -0,0 +1,68 @@
+/*
+ * learned_kv.h - C API for learned-kv store files.
+ *
+ * Opens files written by VerifiedKvStore::save_to_file with String keys and
+ * String or Vec<u8> values, and looks up keys as byte strings. Link against
+ * the learned_kv shared library (liblearned_kv.so / .dylib / learned_kv.dll).
+ *
+ * An open store is immutable: lookups may run concurrently from any number of
+ * threads. Value pointers returned by lkv_get stay valid until lkv_close.
+ *
+ *     lkv_store *store;
+ *     int rc = lkv_open("users.kv", &store);
+ *     if (rc != LKV_OK) { fprintf(stderr, "%s\n", lkv_strerror(rc)); return 1; }
+ *
+ *     const uint8_t *val;
+ *     size_t val_len;
+ *     if (lkv_get(store, (const uint8_t *)"user:1", 6, &val, &val_len) == LKV_OK)
+ *         fwrite(val, 1, val_len, stdout);
+ *     lkv_close(store);
+ */
+
+#ifndef LEARNED_KV_H
+#define LEARNED_KV_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes. */
+#define LKV_OK                      0
+#define LKV_ERR_NOT_FOUND           1 /* key is not in the store */
+#define LKV_ERR_INVALID_ARGUMENT    2 /* null pointer or unusable path */
+#define LKV_ERR_IO                  3 /* file could not be read */
+#define LKV_ERR_INVALID_FILE        4 /* corrupt file, wrong format or unsupported key type */
+#define LKV_ERR_INCOMPATIBLE_HASHER 5 /* hasher not available on this platform */
+#define LKV_ERR_OTHER               6
+
+typedef struct lkv_store lkv_store;
+
+/* Open the store file at `path`. On success writes the handle to `*out`,
+ * otherwise sets `*out` to NULL. */
+int lkv_open(const char *path, lkv_store **out);
+
+/* Look up `key_len` bytes at `key_ptr` (may be NULL when `key_len` is 0).
+ * On success points `*val_ptr`/`*val_len` at the value. */
+int lkv_get(const lkv_store *store, const uint8_t *key_ptr, size_t key_len,
+            const uint8_t **val_ptr, size_t *val_len);
+
+/* 1 if the key is present, 0 otherwise. */
+int lkv_contains(const lkv_store *store, const uint8_t *key_ptr, size_t key_len);
+
+/* Number of entries; 0 for NULL. */
+size_t lkv_len(const lkv_store *store);
+
+/* Free a store. NULL is ignored. */
+void lkv_close(lkv_store *store);
+
+/* Static, NUL-terminated description of a status code. */
+const char *lkv_strerror(int code);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LEARNED_KV_H */
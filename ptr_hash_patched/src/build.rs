@@ -125,7 +125,9 @@ impl<Key: KeyT + ?Sized, BF: BucketFn, F: Packed, Hx: KeyHasher<Key>>
         let mut recent = [BucketIdx::NONE; 16];
         let mut total_evictions = 0;
 
-        let mut rng = fastrand::Rng::new();
+        // Seeded per part, so that the pilots only depend on the global seed and
+        // not on the order in which threads process parts.
+        let mut rng = fastrand::Rng::with_seed(hash::C.wrapping_mul(self.seed ^ part as u64));
 
         // let mut eviction_counts: Vec<usize> = vec![];
 
@@ -10,6 +10,8 @@
 //! - [`GxInt`]: GxHash, but with the type 'inlined' so that it optimized better.
 //! - [`Xxh3Int`]: Xxh3, but with the type 'inlined' so that it optimized better.
 //!
+//! [`StrongerIntHash`] also accepts `(u32, u32)` and `(u64, u64)` pairs.
+//!
 //! In practice, prefer [`FastIntHash`] if it's good enough.
 //! Otherwise, fall back to [`StrongerIntHash`].
 //! If that still fails (which probably shouldn't happen) fall back to one of the two remaining
@@ -28,6 +30,10 @@
 //! call specialized functions rather than going through the generic `Hasher`
 //! interface.
 //!
+#[cfg(all(
+    feature = "gxhash",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
 use gxhash::GxBuildHasher;
 
 use crate::KeyT;
@@ -47,6 +53,8 @@ pub trait Hash: Copy + Debug + Default + Send + Sync + Eq + rdst::RadixKey {
     fn low(&self) -> u64;
     /// Returns the high 64bits of the hash.
     fn high(&self) -> u64;
+    /// Remixes the hash with `seed`. Bijective, so distinct hashes stay distinct.
+    fn reseed(self, seed: u64) -> Self;
 }
 
 impl Hash for u64 {
@@ -56,6 +64,10 @@ impl Hash for u64 {
     fn high(&self) -> u64 {
         *self
     }
+    #[inline(always)]
+    fn reseed(self, seed: u64) -> Self {
+        (self ^ seed).wrapping_mul(C)
+    }
 }
 
 impl Hash for u128 {
@@ -65,6 +77,12 @@ impl Hash for u128 {
     fn high(&self) -> u64 {
         (*self >> 64) as u64
     }
+    #[inline(always)]
+    fn reseed(self, seed: u64) -> Self {
+        let low = self.low().reseed(seed) as u128;
+        let high = self.high().reseed(seed) as u128;
+        (high << 64) | low
+    }
 }
 
 /// Wrapper trait for various hash functions.
@@ -93,11 +111,23 @@ pub type FxHash = fxhash::FxHasher64;
 ///
 /// Prefer [`Xxh3Int`] for integers, which avoids some overhead of the default hasher.
 pub type Xxh3 = xxhash_rust::xxh3::Xxh3Default;
+#[cfg(all(
+    feature = "gxhash",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
 pub type Gx = gxhash::GxHasher;
 
 /// Use gxhash for 64-bit string hashing.
+#[cfg(all(
+    feature = "gxhash",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
 pub type StringHash = Gx;
 /// Use gxhash for 128-bit string hashing.
+#[cfg(all(
+    feature = "gxhash",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
 pub type StringHash128 = Gx128;
 
 // Implementations
@@ -116,10 +146,18 @@ impl<Key: KeyT + ?Sized> KeyHasher<Key> for Xxh3_128 {
     }
 }
 
-/// 128-bit version of XXH3.
+/// 128-bit version of gxhash.
+#[cfg(all(
+    feature = "gxhash",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
 #[cfg_attr(feature = "epserde", derive(epserde::prelude::Epserde))]
 #[derive(Clone)]
 pub struct Gx128;
+#[cfg(all(
+    feature = "gxhash",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
 impl<Key: KeyT + ?Sized> KeyHasher<Key> for Gx128 {
     type H = u128;
     #[inline(always)]
@@ -155,7 +193,11 @@ pub struct NoHash;
 #[derive(Clone)]
 pub struct Xxh3Int;
 
-/// Inlined version of Xxh3 for integer keys.
+/// Inlined version of gxhash for integer keys.
+#[cfg(all(
+    feature = "gxhash",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
 #[cfg_attr(feature = "epserde", derive(epserde::prelude::Epserde))]
 #[derive(Clone)]
 pub struct GxInt;
@@ -191,6 +233,7 @@ macro_rules! int_hashers {
                 }
             }
 
+            #[cfg(all(feature = "gxhash", any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
             impl KeyHasher<$t> for GxInt {
                 type H = u64;
                 #[inline(always)]
@@ -202,3 +245,22 @@ macro_rules! int_hashers {
     };
 }
 int_hashers!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+// Pairs of integers, e.g. composite `(u32, u32)` ids.
+impl KeyHasher<(u32, u32)> for StrongerIntHash {
+    type H = u64;
+    #[inline(always)]
+    fn hash(x: &(u32, u32), seed: u64) -> u64 {
+        let packed = (x.0 as u64) << 32 | x.1 as u64;
+        <Self as KeyHasher<u64>>::hash(&packed, seed)
+    }
+}
+
+impl KeyHasher<(u64, u64)> for StrongerIntHash {
+    type H = u64;
+    #[inline(always)]
+    fn hash(x: &(u64, u64), seed: u64) -> u64 {
+        let first = <Self as KeyHasher<u64>>::hash(&x.0, seed);
+        <Self as KeyHasher<u64>>::hash(&x.1, first)
+    }
+}
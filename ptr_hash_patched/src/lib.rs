@@ -302,6 +302,33 @@ type RemSlots = FM32;
 type Pilot = u64;
 type PilotHash = u64;
 
+/// Maximum number of global seeds tried before construction fails.
+const MAX_TRIES: usize = 10;
+
+/// Seed of the RNG drawing global seeds, unless given explicitly.
+const DEFAULT_RNG_SEED: u64 = 31415;
+
+/// Reason why [`PtrHash::try_new_keyed()`] failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// Two keys hashed to the same value under every global seed tried.
+    /// Usually means the input contains duplicate keys.
+    DuplicateHashes,
+    /// No global seed led to a valid pilot assignment.
+    PilotSearchFailed,
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::DuplicateHashes => write!(f, "duplicate key hashes under every seed"),
+            BuildError::PilotSearchFailed => write!(f, "no seed led to a valid pilot assignment"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
 /// PtrHash datastructure.
 /// It is recommended to use PtrHash with default types.
 ///
@@ -357,6 +384,11 @@ pub struct PtrHash<
     // Computed state.
     /// The global seed.
     seed: u64,
+    /// Secret mixed into every key hash; 0 unless built with [`PtrHash::try_new_keyed()`].
+    hash_seed: u64,
+    /// Whether key hashes use `hash_seed` alone, with the global seed mixed
+    /// in afterwards, instead of the global seed.
+    keyed: bool,
     /// The pilots.
     pilots: V,
     /// Remap the out-of-bound slots to free slots.
@@ -389,6 +421,8 @@ where
             rem_buckets_total: FastReduce::new(0),
             rem_slots: RemSlots::new(0),
             seed: 0,
+            hash_seed: 0,
+            keyed: false,
             pilots: vec![],
             remap: F::default(),
             _key: PhantomData,
@@ -432,9 +466,54 @@ impl<Key: KeyT, BF: BucketFn, F: MutPacked, Hx: KeyHasher<Key>> PtrHash<Key, BF,
     /// parameters) and the eviction chains become too long.
     pub fn try_new(keys: &[Key], params: PtrHashParams<BF>) -> Option<Self> {
         let mut ptr_hash = Self::init(keys.len(), params);
-        ptr_hash.compute_pilots(keys.par_iter())?;
+        ptr_hash.compute_pilots(keys.par_iter()).ok()?;
         Some(ptr_hash)
     }
+
+    /// Fallible version of `new` that hashes keys with the secret `hash_seed`,
+    /// and draws global seeds from an RNG seeded with `rng_seed`.
+    ///
+    /// Without a secret seed, key hashes are predictable and an adversary can
+    /// craft keys whose hashes collide for every global seed tried. With one,
+    /// collisions are only possible for keys that are actually equal.
+    /// Returns a [`BuildError`] describing why construction failed.
+    ///
+    /// Key hashes do not depend on the global seed, so tables built with the
+    /// same hasher and `hash_seed` agree on [`hash`](Self::hash) for every key;
+    /// the global seed is mixed in afterwards, by
+    /// [`index_from_hash`](Self::index_from_hash). Duplicate hashes therefore
+    /// fail construction without trying other global seeds.
+    ///
+    /// The result only depends on the keys, `params` and the two seeds, not on
+    /// the number of threads. Use [`PtrHash::global_seed()`] to get the seed
+    /// that was settled on.
+    pub fn try_new_keyed(
+        keys: &[Key],
+        params: PtrHashParams<BF>,
+        hash_seed: u64,
+        rng_seed: u64,
+    ) -> Result<Self, BuildError> {
+        let mut ptr_hash = Self::init(keys.len(), params);
+        ptr_hash.hash_seed = hash_seed;
+        ptr_hash.keyed = true;
+        ptr_hash.compute_pilots_seeded(keys.par_iter(), rng_seed, None)?;
+        Ok(ptr_hash)
+    }
+
+    /// Rebuild with a known global seed, e.g. one recorded from
+    /// [`PtrHash::global_seed()`]. Only that seed is tried.
+    pub fn try_with_global_seed(
+        keys: &[Key],
+        params: PtrHashParams<BF>,
+        hash_seed: u64,
+        global_seed: u64,
+    ) -> Result<Self, BuildError> {
+        let mut ptr_hash = Self::init(keys.len(), params);
+        ptr_hash.hash_seed = hash_seed;
+        ptr_hash.keyed = true;
+        ptr_hash.compute_pilots_seeded(keys.par_iter(), 0, Some(global_seed))?;
+        Ok(ptr_hash)
+    }
 }
 
 /// Construction (helper) methods working with unsized keys.
@@ -452,7 +531,9 @@ impl<Key: KeyT + ?Sized, BF: BucketFn, F: MutPacked, Hx: KeyHasher<Key>>
         params: PtrHashParams<BF>,
     ) -> Self {
         let mut ptr_hash = Self::init(n, params);
-        ptr_hash.compute_pilots(keys);
+        ptr_hash
+            .compute_pilots(keys)
+            .expect("Unable to construct PtrHash after 10 tries. Try using a better hash or decreasing lambda.");
         ptr_hash
     }
 
@@ -553,6 +634,8 @@ impl<Key: KeyT + ?Sized, BF: BucketFn, F: MutPacked, Hx: KeyHasher<Key>>
             rem_buckets_total: Rb::new(buckets_total),
             rem_slots: RemSlots::new(slots_per_part.max(1)), // fix for n=0
             seed: 0,
+            hash_seed: 0,
+            keyed: false,
             pilots: Default::default(),
             remap: F::default(),
             _key: PhantomData,
@@ -563,29 +646,47 @@ impl<Key: KeyT + ?Sized, BF: BucketFn, F: MutPacked, Hx: KeyHasher<Key>>
     fn compute_pilots<'a>(
         &mut self,
         keys: impl ParallelIterator<Item = impl Borrow<Key>> + Clone + 'a,
-    ) -> Option<BucketStats> {
+    ) -> Result<BucketStats, BuildError> {
+        self.compute_pilots_seeded(keys, DEFAULT_RNG_SEED, None)
+    }
+
+    /// Search for a global seed, drawing candidates from an RNG seeded with
+    /// `rng_seed`, or trying only `fixed_seed` when given.
+    fn compute_pilots_seeded<'a>(
+        &mut self,
+        keys: impl ParallelIterator<Item = impl Borrow<Key>> + Clone + 'a,
+        rng_seed: u64,
+        fixed_seed: Option<u64>,
+    ) -> Result<BucketStats, BuildError> {
         let overall_start = std::time::Instant::now();
         // Initialize arrays;
         let mut taken: Vec<BitVec> = vec![];
         let mut pilots: Vec<u8> = vec![];
 
         let mut tries = 0;
-        const MAX_TRIES: usize = 10;
+        let mut duplicate_tries = 0;
+        let max_tries = if fixed_seed.is_some() { 1 } else { MAX_TRIES };
 
-        let mut rng = ChaCha8Rng::seed_from_u64(31415);
+        let mut rng = ChaCha8Rng::seed_from_u64(rng_seed);
 
         // Loop over global seeds `s`.
         let stats = 's: loop {
             tries += 1;
-            if tries > MAX_TRIES {
-                log::error!("PtrHash failed to find a global seed after {MAX_TRIES} tries.");
-                return None;
+            if tries > max_tries {
+                log::error!("PtrHash failed to find a global seed after {max_tries} tries.");
+                // Duplicate hashes under every seed means duplicate keys (or an
+                // unkeyed hash under attack), not bad luck.
+                return Err(if duplicate_tries == max_tries {
+                    BuildError::DuplicateHashes
+                } else {
+                    BuildError::PilotSearchFailed
+                });
             }
 
             let old_seed = self.seed;
 
             // Choose a global seed s.
-            self.seed = rng.random();
+            self.seed = fixed_seed.unwrap_or_else(|| rng.random());
             if tries == 1 {
                 log::info!("First seed tried: {}", self.seed);
             } else {
@@ -618,7 +719,12 @@ impl<Key: KeyT + ?Sized, BF: BucketFn, F: MutPacked, Hx: KeyHasher<Key>>
                 let start = std::time::Instant::now();
                 let Some((hashes, part_starts)) = self.sort_parts(shard, hashes) else {
                     trace!("Found duplicate hashes");
-                    // Found duplicate hashes.
+                    // Found duplicate hashes. Keyed hashes are the same for
+                    // every global seed, so trying more cannot help.
+                    if self.keyed {
+                        return Err(BuildError::DuplicateHashes);
+                    }
+                    duplicate_tries += 1;
                     continue 's;
                 };
                 let start = log_duration("sort buckets", start);
@@ -652,7 +758,7 @@ impl<Key: KeyT + ?Sized, BF: BucketFn, F: MutPacked, Hx: KeyHasher<Key>>
         let (p, r) = self.bits_per_element();
         trace!("bits/element: {}", p + r);
         log_duration("total build", overall_start);
-        Some(stats)
+        Ok(stats)
     }
 
     fn remap_free_slots(&mut self, taken: &Vec<BitVec>) -> Result<(), ()> {
@@ -731,7 +837,46 @@ impl<Key: KeyT + ?Sized, BF: BucketFn, F: Packed, Hx: KeyHasher<Key>, V: AsRef<[
     /// Use `index` to get a key in `[0, n)`.
     #[inline(always)]
     pub fn index_no_remap(&self, key: &Key) -> usize {
-        let hx = self.hash_key(key);
+        self.index_hash_no_remap(self.hash_key(key))
+    }
+
+    /// The hash of `key` that [`index`](Self::index) works from, so callers
+    /// can reuse it, e.g. for a filter in front of the table. Depends only on
+    /// the hash seed for keyed tables, and on the global seed otherwise.
+    #[inline(always)]
+    pub fn hash(&self, key: &Key) -> Hx::H {
+        if self.keyed {
+            Hx::hash(key, self.hash_seed)
+        } else {
+            Hx::hash(key, self.seed)
+        }
+    }
+
+    /// [`hash`](Self::hash) of a borrowed form of a key, e.g. a `str` for
+    /// `String` keys. `Hx` must hash `Q` exactly like the key it borrows from.
+    #[inline(always)]
+    pub fn hash_borrowed<Q: ?Sized>(&self, key: &Q) -> <Hx as KeyHasher<Key>>::H
+    where
+        Hx: KeyHasher<Q, H = <Hx as KeyHasher<Key>>::H>,
+    {
+        let seed = if self.keyed { self.hash_seed } else { self.seed };
+        <Hx as KeyHasher<Q>>::hash(key, seed)
+    }
+
+    /// Get the index in `[0, n)` for a hash returned by [`hash`](Self::hash),
+    /// possibly of another table with the same hasher and hash seed.
+    #[inline(always)]
+    pub fn index_from_hash(&self, hx: Hx::H) -> usize {
+        let slot = self.index_hash_no_remap(self.reseed(hx));
+        if slot < self.n {
+            slot
+        } else {
+            self.remap.index(slot - self.n) as usize
+        }
+    }
+
+    #[inline(always)]
+    fn index_hash_no_remap(&self, hx: Hx::H) -> usize {
         let b = self.bucket(hx);
         let pilot = self.pilots.as_ref().index(b);
         self.slot(hx, pilot)
@@ -1025,8 +1170,24 @@ impl<Key: KeyT + ?Sized, BF: BucketFn, F: Packed, Hx: KeyHasher<Key>, V: AsRef<[
         })
     }
 
+    /// The global seed settled on during construction.
+    pub fn global_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The hash that buckets and slots are computed from.
     fn hash_key(&self, x: &Key) -> Hx::H {
-        Hx::hash(x, self.seed)
+        self.reseed(self.hash(x))
+    }
+
+    /// Mix the global seed into a keyed hash; unkeyed hashes already depend on it.
+    #[inline(always)]
+    fn reseed(&self, hx: Hx::H) -> Hx::H {
+        if self.keyed {
+            hx.reseed(self.seed)
+        } else {
+            hx
+        }
     }
 
     fn hash_pilot(&self, p: Pilot) -> PilotHash {
@@ -59,11 +59,17 @@ fn int_hash_speed() {
     }
     eprintln!("Time {:?}", start.elapsed());
 
-    let start = std::time::Instant::now();
-    for k in &keys {
-        black_box(GxInt::hash(k, seed));
+    #[cfg(all(
+        feature = "gxhash",
+        any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    {
+        let start = std::time::Instant::now();
+        for k in &keys {
+            black_box(GxInt::hash(k, seed));
+        }
+        eprintln!("Time {:?}", start.elapsed());
     }
-    eprintln!("Time {:?}", start.elapsed());
 
     let start = std::time::Instant::now();
     for k in &keys {
@@ -263,6 +269,10 @@ fn integer_key_types() {
 }
 
 #[test]
+#[cfg(all(
+    feature = "gxhash",
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
 fn string_key_types() {
     let h = DefaultPtrHash::<StringHash, &str>::new(&["a"], PtrHashParams::default());
 
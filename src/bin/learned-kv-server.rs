@@ -0,0 +1,107 @@
+//! `learned-kv-server`: serve a store file read-only over the network.
+
+use clap::{Parser, ValueEnum};
+use learned_kv::server::{http, memcached, resp, StoreHandle};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "learned-kv-server",
+    version,
+    about = "Serve a learned-kv store file read-only"
+)]
+struct Args {
+    /// Store file with String keys and String or byte values
+    file: PathBuf,
+    /// Address to listen on [default: 127.0.0.1:6380 for resp, 127.0.0.1:11211 for memcached, 127.0.0.1:8080 for http]
+    #[arg(long)]
+    bind: Option<String>,
+    /// Wire protocol
+    #[arg(long, value_enum, default_value_t = Protocol::Resp)]
+    protocol: Protocol,
+    /// Seconds between checks for a changed file; 0 disables reloading
+    #[arg(long, default_value_t = 1)]
+    reload_interval: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Protocol {
+    /// Redis protocol (GET, MGET, EXISTS, DBSIZE, INFO)
+    Resp,
+    /// Memcached text and meta protocols (get, gets, mg)
+    Memcached,
+    /// JSON over HTTP (/v1/keys/{key}, /v1/batch, /v1/stats, /v1/health)
+    Http,
+}
+
+impl Protocol {
+    fn default_bind(self) -> &'static str {
+        match self {
+            Protocol::Resp => "127.0.0.1:6380",
+            Protocol::Memcached => "127.0.0.1:11211",
+            Protocol::Http => "127.0.0.1:8080",
+        }
+    }
+}
+
+/// Prints library log messages (reloads, refused connections) on stderr.
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{}: {}",
+                record.level().as_str().to_lowercase(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let handle = Arc::new(StoreHandle::open(&args.file)?);
+    if args.reload_interval > 0 {
+        handle.watch(Duration::from_secs(args.reload_interval));
+    }
+
+    let bind = args.bind.as_deref().unwrap_or(args.protocol.default_bind());
+    let listener = TcpListener::bind(bind)?;
+    eprintln!(
+        "serving {} ({} keys) on {}",
+        args.file.display(),
+        handle.store().len(),
+        listener.local_addr()?
+    );
+
+    match args.protocol {
+        Protocol::Resp => resp::serve(listener, handle)?,
+        Protocol::Memcached => memcached::serve(listener, handle)?,
+        Protocol::Http => http::serve(listener, handle)?,
+    }
+    Ok(())
+}
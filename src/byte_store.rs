@@ -0,0 +1,178 @@
+//! Type-erased stores with `String` keys and raw byte values.
+//!
+//! Servers and foreign-language bindings don't know at compile time which
+//! hasher a file was built with. [`load_byte_store`] reads the hasher from the
+//! file header and returns a [`ByteStore`] trait object.
+//!
+//! Values are loaded as `Vec<u8>`. Files written with `String` values load
+//! as their UTF-8 bytes, since both types have the same on-disk encoding.
+
+use crate::error::KvError;
+#[cfg(gxhash)]
+use crate::hasher::GxHash;
+use crate::hasher::{HasherKind, PortableHash, StrHasher, Xxh3Hash};
+use crate::persistence::inspect_file;
+use crate::verified_kv_store::VerifiedKvStore;
+use std::path::Path;
+
+/// Read-only view of a `String` → bytes store, whatever its hasher.
+pub trait ByteStore: Send + Sync {
+    /// Look up `key`. Keys that aren't valid UTF-8 are never found.
+    ///
+    /// Returns `KvError::KeyNotFoundFast` on a miss.
+    fn get(&self, key: &[u8]) -> Result<&[u8], KvError>;
+
+    /// Whether `key` is in the store.
+    fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_ok()
+    }
+
+    /// Number of entries.
+    fn len(&self) -> usize;
+
+    /// Whether the store has no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hash function family used for keys.
+    fn hasher_kind(&self) -> HasherKind;
+
+    /// Verified slot index of `key`; see [`VerifiedKvStore::index_of`].
+    fn index_of(&self, key: &[u8]) -> Result<usize, KvError>;
+
+    /// Key and value in slot `index`, or `None` past the end.
+    fn get_by_index(&self, index: usize) -> Option<(&str, &[u8])>;
+}
+
+impl<H: StrHasher> ByteStore for VerifiedKvStore<String, Vec<u8>, H> {
+    fn get(&self, key: &[u8]) -> Result<&[u8], KvError> {
+        let key = std::str::from_utf8(key).map_err(|_| KvError::KeyNotFoundFast)?;
+        self.get_str(key).map(Vec::as_slice)
+    }
+
+    fn len(&self) -> usize {
+        VerifiedKvStore::len(self)
+    }
+
+    fn hasher_kind(&self) -> HasherKind {
+        VerifiedKvStore::hasher_kind(self)
+    }
+
+    fn index_of(&self, key: &[u8]) -> Result<usize, KvError> {
+        let key = std::str::from_utf8(key).map_err(|_| KvError::KeyNotFoundFast)?;
+        self.index_of_str(key)
+    }
+
+    fn get_by_index(&self, index: usize) -> Option<(&str, &[u8])> {
+        VerifiedKvStore::get_by_index(self, index)
+            .map(|(key, value)| (key.as_str(), value.as_slice()))
+    }
+}
+
+/// Load a store file with `String` keys, using the hasher recorded in its header.
+///
+/// # Errors
+///
+/// Same as [`VerifiedKvStore::load_from_file`]; also fails for files with
+/// integer keys, and with `KvError::IncompatibleNormalizer` for files
+/// written by a [`NormalizedKvStore`](crate::normalize::NormalizedKvStore).
+pub fn load_byte_store<P: AsRef<Path>>(path: P) -> Result<Box<dyn ByteStore>, KvError> {
+    let path = path.as_ref();
+    let meta = inspect_file(path)?.meta;
+    if !meta.normalizer.is_empty() {
+        return Err(KvError::IncompatibleNormalizer(format!(
+            "file keys were normalized with {}; byte stores only support exact keys",
+            meta.normalizer
+        )));
+    }
+    Ok(match meta.hasher {
+        #[cfg(gxhash)]
+        HasherKind::Gx => Box::new(VerifiedKvStore::<String, Vec<u8>, GxHash>::load_from_file(
+            path,
+        )?),
+        #[cfg(not(gxhash))]
+        HasherKind::Gx => return Err(HasherKind::Gx.unavailable()),
+        HasherKind::Xxh3 => {
+            Box::new(VerifiedKvStore::<String, Vec<u8>, Xxh3Hash>::load_from_file(path)?)
+        }
+        HasherKind::Portable => {
+            Box::new(VerifiedKvStore::<String, Vec<u8>, PortableHash>::load_from_file(path)?)
+        }
+        HasherKind::StrongerInt => {
+            return Err(KvError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "store has integer keys; byte stores need String keys",
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::PortableHash;
+    use crate::VerifiedKvStoreBuilder;
+
+    #[test]
+    fn test_load_string_valued_file_as_bytes() {
+        let store: VerifiedKvStore<String, String, PortableHash> = VerifiedKvStoreBuilder::new()
+            .insert("greeting".to_string(), "hello".to_string())
+            .insert("empty".to_string(), String::new())
+            .build()
+            .unwrap();
+        let test_file = "/tmp/test_byte_store_strings.bin";
+        store.save_to_file(test_file).unwrap();
+
+        let bytes = load_byte_store(test_file).unwrap();
+        assert_eq!(bytes.len(), 2);
+        assert_eq!(bytes.hasher_kind(), HasherKind::Portable);
+        assert_eq!(bytes.get(b"greeting").unwrap(), b"hello");
+        assert_eq!(bytes.get(b"empty").unwrap(), b"");
+        assert!(matches!(
+            bytes.get(b"missing"),
+            Err(KvError::KeyNotFoundFast)
+        ));
+        assert!(!bytes.contains_key(&[0xff, 0xfe]));
+
+        let slot = store.index_of_str("greeting").unwrap();
+        assert_eq!(bytes.index_of(b"greeting").unwrap(), slot);
+        assert_eq!(bytes.get_by_index(slot), Some(("greeting", &b"hello"[..])));
+        assert!(bytes.get_by_index(2).is_none());
+
+        std::fs::remove_file(test_file).ok();
+    }
+
+    #[test]
+    fn test_integer_keys_rejected() {
+        let store: VerifiedKvStore<u64, Vec<u8>> = VerifiedKvStoreBuilder::new()
+            .insert(1, vec![1])
+            .build()
+            .unwrap();
+        let test_file = "/tmp/test_byte_store_int_keys.bin";
+        store.save_to_file(test_file).unwrap();
+
+        assert!(load_byte_store(test_file).is_err());
+
+        std::fs::remove_file(test_file).ok();
+    }
+
+    #[test]
+    fn test_normalized_files_rejected() {
+        use crate::normalize::{AsciiCaseFold, NormalizedKvStoreBuilder};
+
+        let store = NormalizedKvStoreBuilder::<Vec<u8>, _, PortableHash>::new(AsciiCaseFold)
+            .insert("Key".to_string(), vec![1])
+            .build()
+            .unwrap();
+        let test_file = "/tmp/test_byte_store_normalized.bin";
+        store.save_to_file(test_file).unwrap();
+
+        assert!(matches!(
+            load_byte_store(test_file),
+            Err(KvError::IncompatibleNormalizer(msg)) if msg.contains("ascii-case-fold")
+        ));
+
+        std::fs::remove_file(test_file).ok();
+    }
+}
@@ -0,0 +1,348 @@
+//! Stores that keep their values zstd-compressed in memory.
+//!
+//! Large JSON or protobuf values are often most of a store's footprint.
+//! [`CompressedKvStore`] groups the values of neighbouring slots into small
+//! blocks and compresses each block on its own, optionally against a shared
+//! dictionary trained on the blocks. A lookup verifies the key as usual, then
+//! decompresses just the one block holding the value, so it returns an owned
+//! value instead of a reference.
+//!
+//! Fewer values per block means faster lookups and a worse ratio; a trained
+//! dictionary wins back most of the ratio for small blocks.
+
+use crate::error::KvError;
+use crate::hasher::{DefaultHasherFor, HasherKind, KvKey, StoreHasher};
+use crate::verified_kv_store::VerifiedKvStore;
+use std::cell::RefCell;
+use std::io;
+use zstd::zstd_safe::{get_error_name, DCtx, DDict};
+
+/// How [`CompressedKvStore`] compresses values.
+#[derive(Debug, Clone)]
+pub struct ValueCompressionOptions {
+    /// Values of consecutive slots compressed together. 1 compresses each value alone.
+    pub values_per_block: usize,
+    /// zstd compression level.
+    pub level: i32,
+    /// Maximum size of a dictionary trained on the blocks; 0 for none.
+    ///
+    /// Training needs a reasonable number of blocks. If it fails, the store
+    /// is built without a dictionary; see [`CompressedKvStore::dictionary_bytes`].
+    pub dictionary_size: usize,
+}
+
+impl Default for ValueCompressionOptions {
+    fn default() -> Self {
+        Self {
+            values_per_block: 16,
+            level: 3,
+            dictionary_size: 0,
+        }
+    }
+}
+
+impl ValueCompressionOptions {
+    /// One value per block, compressed against a trained dictionary of
+    /// `dictionary_size` bytes. Lookups decompress only the value they return.
+    pub fn per_value(dictionary_size: usize) -> Self {
+        Self {
+            values_per_block: 1,
+            dictionary_size,
+            ..Self::default()
+        }
+    }
+}
+
+thread_local! {
+    // Decompression contexts are large; keep one per thread
+    static DCTX: RefCell<DCtx<'static>> = RefCell::new(DCtx::create());
+}
+
+/// Verified key-value store with byte values kept compressed in memory.
+///
+/// Keys are indexed and verified exactly like [`VerifiedKvStore`]. Values are
+/// any byte-like type on the way in and come back as `Vec<u8>`.
+///
+/// # Example
+/// ```
+/// use learned_kv::compressed_store::{CompressedKvStore, ValueCompressionOptions};
+/// use learned_kv::VerifiedKvStoreBuilder;
+///
+/// let store = VerifiedKvStoreBuilder::new()
+///     .insert("user:1".to_string(), r#"{"name":"alice","active":true}"#.to_string())
+///     .insert("user:2".to_string(), r#"{"name":"bob","active":false}"#.to_string())
+///     .build()
+///     .unwrap();
+/// let store: CompressedKvStore<String> =
+///     CompressedKvStore::new(store, &ValueCompressionOptions::default()).unwrap();
+///
+/// assert_eq!(store.get(&"user:2".to_string()).unwrap(), br#"{"name":"bob","active":false}"#);
+///
+/// // Reuse one buffer across lookups
+/// let mut buf = Vec::new();
+/// store.get_into(&"user:1".to_string(), &mut buf).unwrap();
+/// assert!(buf.starts_with(b"{\"name\":\"alice\""));
+/// ```
+pub struct CompressedKvStore<K, H = DefaultHasherFor<K>>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    index: VerifiedKvStore<K, (), H>,
+    values_per_block: usize,
+    blocks: Vec<Box<[u8]>>,
+    /// End of each slot's value within its decompressed block.
+    ends: Vec<u32>,
+    dictionary: Option<(Vec<u8>, DDict<'static>)>,
+    raw_bytes: usize,
+}
+
+impl<K, H> CompressedKvStore<K, H>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    /// Compress the values of `store`, keeping its keys, seeds and slot layout.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KvError::IoError` if zstd fails or a block's values add up
+    /// to 4 GiB or more.
+    pub fn new<V>(
+        store: VerifiedKvStore<K, V, H>,
+        options: &ValueCompressionOptions,
+    ) -> Result<Self, KvError>
+    where
+        V: AsRef<[u8]>,
+    {
+        let values_per_block = options.values_per_block.max(1);
+        let (index, values) = store.split_values();
+
+        let mut raw_blocks = Vec::with_capacity(values.len().div_ceil(values_per_block));
+        let mut ends = Vec::with_capacity(values.len());
+        for chunk in values.chunks(values_per_block) {
+            let mut raw = Vec::new();
+            for value in chunk {
+                raw.extend_from_slice(value.as_ref());
+                let end = u32::try_from(raw.len())
+                    .map_err(|_| invalid_input("value block exceeds 4 GiB"))?;
+                ends.push(end);
+            }
+            raw_blocks.push(raw);
+        }
+        let raw_bytes = raw_blocks.iter().map(Vec::len).sum();
+
+        let dictionary = if options.dictionary_size > 0 {
+            zstd::dict::from_samples(&raw_blocks, options.dictionary_size).ok()
+        } else {
+            None
+        };
+        let mut compressor = match &dictionary {
+            Some(dict) => zstd::bulk::Compressor::with_dictionary(options.level, dict)?,
+            None => zstd::bulk::Compressor::new(options.level)?,
+        };
+        let blocks = raw_blocks
+            .iter()
+            .map(|raw| compressor.compress(raw).map(Vec::into_boxed_slice))
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            index,
+            values_per_block,
+            blocks,
+            ends,
+            dictionary: dictionary.map(|dict| {
+                let ddict = DDict::create(&dict);
+                (dict, ddict)
+            }),
+            raw_bytes,
+        })
+    }
+
+    /// Look up `key` and return a decompressed copy of its value.
+    pub fn get(&self, key: &K) -> Result<Vec<u8>, KvError> {
+        let mut buf = Vec::new();
+        self.get_into(key, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Look up `key` and replace the contents of `buf` with its value.
+    ///
+    /// Reusing `buf` across lookups avoids an allocation per call. On error
+    /// `buf` is left unspecified.
+    pub fn get_into(&self, key: &K, buf: &mut Vec<u8>) -> Result<(), KvError> {
+        let slot = self.index.slot(key).ok_or(KvError::KeyNotFoundFast)?;
+        let block = slot / self.values_per_block;
+        let first = block * self.values_per_block;
+        let start = if slot == first {
+            0
+        } else {
+            self.ends[slot - 1] as usize
+        };
+        let end = self.ends[slot] as usize;
+        let block_len = self.ends[(first + self.values_per_block).min(self.ends.len()) - 1];
+
+        // The whole block is decompressed, then the value moved to the front
+        buf.clear();
+        buf.reserve(block_len as usize);
+        let src = &self.blocks[block];
+        let written = DCTX.with(|dctx| {
+            let mut dctx = dctx.borrow_mut();
+            match &self.dictionary {
+                Some((_, ddict)) => dctx.decompress_using_ddict(buf, src, ddict),
+                None => dctx.decompress(buf, src),
+            }
+        });
+        match written {
+            Ok(n) if n == block_len as usize => {}
+            Ok(_) => return Err(corrupt("decompressed block has the wrong size")),
+            Err(code) => return Err(corrupt(get_error_name(code))),
+        }
+        buf.copy_within(start..end, 0);
+        buf.truncate(end - start);
+        Ok(())
+    }
+
+    /// Check if a key is in the store, without decompressing anything.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns the number of key-value pairs in the store.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Check if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns the hash function family used for keys.
+    pub fn hasher_kind(&self) -> HasherKind {
+        self.index.hasher_kind()
+    }
+
+    /// Returns an iterator over all keys in the store, in slot order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.index.keys()
+    }
+
+    /// Number of compressed blocks.
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Total size of all values before compression.
+    pub fn raw_value_bytes(&self) -> usize {
+        self.raw_bytes
+    }
+
+    /// Bytes held for values: compressed blocks plus the dictionary.
+    pub fn compressed_value_bytes(&self) -> usize {
+        self.blocks.iter().map(|block| block.len()).sum::<usize>() + self.dictionary_bytes()
+    }
+
+    /// Size of the trained dictionary, or 0 if the store has none.
+    pub fn dictionary_bytes(&self) -> usize {
+        self.dictionary.as_ref().map_or(0, |(dict, _)| dict.len())
+    }
+
+    /// Raw value bytes per compressed byte, dictionary included.
+    pub fn compression_ratio(&self) -> f64 {
+        match self.compressed_value_bytes() {
+            0 => 1.0,
+            stored => self.raw_bytes as f64 / stored as f64,
+        }
+    }
+}
+
+fn invalid_input(message: &str) -> KvError {
+    KvError::IoError(io::Error::new(io::ErrorKind::InvalidInput, message))
+}
+
+fn corrupt(message: &str) -> KvError {
+    KvError::IoError(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::PortableHash;
+    use crate::VerifiedKvStoreBuilder;
+
+    fn json_store(n: usize) -> VerifiedKvStore<String, String, PortableHash> {
+        VerifiedKvStoreBuilder::new()
+            .extend((0..n).map(|i| {
+                let value = format!(
+                    r#"{{"id":{},"name":"user-{}","email":"user{}@example.com","roles":["reader","writer"],"active":{}}}"#,
+                    i,
+                    i,
+                    i,
+                    i % 3 == 0
+                );
+                (format!("user:{}", i), value)
+            }))
+            .seed(7)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_blocks_roundtrip() {
+        let store = json_store(1000);
+        let expected: Vec<(String, String)> =
+            store.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+
+        let compressed =
+            CompressedKvStore::new(store, &ValueCompressionOptions::default()).unwrap();
+        assert_eq!(compressed.len(), 1000);
+        assert_eq!(compressed.block_count(), 63);
+        assert_eq!(compressed.dictionary_bytes(), 0);
+        assert!(compressed.compression_ratio() > 3.0);
+
+        let mut buf = Vec::new();
+        for (key, value) in &expected {
+            compressed.get_into(key, &mut buf).unwrap();
+            assert_eq!(buf, value.as_bytes());
+        }
+        assert!(matches!(
+            compressed.get(&"user:1000".to_string()),
+            Err(KvError::KeyNotFoundFast)
+        ));
+        assert!(!compressed.contains_key(&"nope".to_string()));
+    }
+
+    #[test]
+    fn test_per_value_dictionary() {
+        let store = json_store(2000);
+        let raw = store.values().map(String::len).sum::<usize>();
+        let compressed =
+            CompressedKvStore::new(store, &ValueCompressionOptions::per_value(4096)).unwrap();
+
+        assert_eq!(compressed.block_count(), 2000);
+        assert_eq!(compressed.raw_value_bytes(), raw);
+        assert!(compressed.dictionary_bytes() > 0);
+        assert!(compressed.compression_ratio() > 2.0);
+        let value = compressed.get(&"user:42".to_string()).unwrap();
+        assert!(value.starts_with(br#"{"id":42,"name":"user-42""#));
+    }
+
+    #[test]
+    fn test_empty_values_and_short_last_block() {
+        let store: VerifiedKvStore<u64, Vec<u8>> = VerifiedKvStoreBuilder::new()
+            .extend((0..5u64).map(|i| (i, vec![b'x'; i as usize])))
+            .build()
+            .unwrap();
+        let options = ValueCompressionOptions {
+            values_per_block: 2,
+            ..ValueCompressionOptions::default()
+        };
+        let compressed = CompressedKvStore::new(store, &options).unwrap();
+
+        assert_eq!(compressed.block_count(), 3);
+        for i in 0..5u64 {
+            assert_eq!(compressed.get(&i).unwrap(), vec![b'x'; i as usize]);
+        }
+    }
+}
@@ -0,0 +1,655 @@
+//! Stores that keep each distinct value once.
+//!
+//! When millions of keys map to a few thousand distinct values (category
+//! labels, country codes, ...), [`DedupKvStore`] keeps a table of the
+//! distinct values and a per-slot code into it. Codes are one, two or four
+//! bytes wide, whatever the number of distinct values needs.
+//!
+//! Lookups return `&V` just like [`VerifiedKvStore`], and files are in the same
+//! format: a `DedupKvStore` file loads as a `VerifiedKvStore` and the other way
+//! round.
+
+use crate::error::KvError;
+use crate::hasher::{DefaultHasherFor, HashWidth, HasherKind, KvKey, StoreHasher};
+use crate::persistence::{PersistedData, PersistedSections, StoreMetadata, WriteOptions};
+use crate::prefilter::PrefilterStats;
+use crate::verified_kv_store::{HashedKey, VerifiedKvStore, VerifiedKvStoreBuilder};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// Per-slot indices into the distinct-values table, as narrow as possible.
+#[derive(Clone)]
+enum Codes {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Codes {
+    /// Pack `ids`, all below `distinct`.
+    fn pack(ids: Vec<u32>, distinct: usize) -> Self {
+        if distinct <= 1 << 8 {
+            Codes::U8(ids.into_iter().map(|id| id as u8).collect())
+        } else if distinct <= 1 << 16 {
+            Codes::U16(ids.into_iter().map(|id| id as u16).collect())
+        } else {
+            Codes::U32(ids)
+        }
+    }
+
+    #[inline(always)]
+    fn get(&self, slot: usize) -> usize {
+        match self {
+            Codes::U8(codes) => codes[slot] as usize,
+            Codes::U16(codes) => codes[slot] as usize,
+            Codes::U32(codes) => codes[slot] as usize,
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            Codes::U8(_) => 1,
+            Codes::U16(_) => 2,
+            Codes::U32(_) => 4,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Codes::U8(codes) => codes.len(),
+            Codes::U16(codes) => codes.len(),
+            Codes::U32(codes) => codes.len(),
+        }
+    }
+}
+
+/// Interns values as they are added, handing out dense ids.
+struct Interner<V> {
+    ids: HashMap<V, u32>,
+    values: Vec<V>,
+    /// Maximum number of distinct values
+    limit: u64,
+}
+
+impl<V: Clone + Eq + Hash> Interner<V> {
+    fn new() -> Self {
+        Self::with_limit(1 << 32)
+    }
+
+    fn with_limit(limit: u64) -> Self {
+        Self {
+            ids: HashMap::new(),
+            values: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Id of `value`. Fails with `KvError::TooManyDistinctValues` if
+    /// `value` is new and the limit is reached.
+    fn intern(&mut self, value: V) -> Result<u32, KvError> {
+        if let Some(&id) = self.ids.get(&value) {
+            return Ok(id);
+        }
+        if self.values.len() as u64 >= self.limit {
+            return Err(KvError::TooManyDistinctValues);
+        }
+        let id = self.values.len() as u32;
+        self.values.push(value.clone());
+        self.ids.insert(value, id);
+        Ok(id)
+    }
+}
+
+/// A values section decoded straight into ids, one per entry, and the
+/// distinct values, so that repeated values are never held twice.
+struct InternedValues<V> {
+    ids: Vec<u32>,
+    distinct: Vec<V>,
+}
+
+impl<'de, V> Deserialize<'de> for InternedValues<V>
+where
+    V: Clone + Eq + Hash + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<V>(PhantomData<V>);
+
+        impl<'de, V> de::Visitor<'de> for Visitor<V>
+        where
+            V: Clone + Eq + Hash + Deserialize<'de>,
+        {
+            type Value = InternedValues<V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence of values")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut interner = Interner::new();
+                // The length comes from the file; don't trust it with a huge allocation
+                let mut ids = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(1 << 20));
+                while let Some(value) = seq.next_element()? {
+                    ids.push(interner.intern(value).map_err(de::Error::custom)?);
+                }
+                Ok(InternedValues {
+                    ids,
+                    distinct: interner.values,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor(PhantomData))
+    }
+}
+
+/// Verified key-value store that stores each distinct value once.
+///
+/// Build one with [`DedupKvStoreBuilder`], or convert an existing store with
+/// [`from_store`](Self::from_store).
+///
+/// # Example
+/// ```
+/// use learned_kv::dedup_store::DedupKvStoreBuilder;
+///
+/// let store = DedupKvStoreBuilder::<u64, String>::new()
+///     .extend((0..1000).map(|id| (id, ["DE", "FR", "NL"][id as usize % 3].to_string())))
+///     .build()?;
+///
+/// assert_eq!(store.get(&4)?, "FR");
+/// assert_eq!(store.distinct_values().len(), 3);
+/// assert_eq!(store.code_bytes(), 1);
+/// # Ok::<(), learned_kv::KvError>(())
+/// ```
+#[derive(Clone)]
+pub struct DedupKvStore<K, V, H = DefaultHasherFor<K>>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    index: VerifiedKvStore<K, (), H>,
+    codes: Codes,
+    distinct: Vec<V>,
+}
+
+impl<K, V, H> DedupKvStore<K, V, H>
+where
+    K: KvKey,
+    V: Clone + Eq + Hash,
+    H: StoreHasher<K>,
+{
+    /// Intern the values of `store`, keeping its keys, seeds and slot layout.
+    ///
+    /// Fails with `KvError::TooManyDistinctValues` if `store` has more than
+    /// 2^32 distinct values.
+    pub fn from_store(store: VerifiedKvStore<K, V, H>) -> Result<Self, KvError> {
+        let (index, values) = store.split_values();
+        let mut interner = Interner::new();
+        let ids = values
+            .into_iter()
+            .map(|v| interner.intern(v))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::from_parts(index, ids, interner.values))
+    }
+
+    /// Assemble a store from slot-ordered ids into `distinct`, dropping
+    /// values no slot refers to.
+    fn from_parts(index: VerifiedKvStore<K, (), H>, mut ids: Vec<u32>, distinct: Vec<V>) -> Self {
+        let mut remap = vec![u32::MAX; distinct.len()];
+        for &id in &ids {
+            remap[id as usize] = 0;
+        }
+        let mut kept = Vec::with_capacity(distinct.len());
+        for (value, new_id) in distinct.into_iter().zip(&mut remap) {
+            if *new_id == 0 {
+                *new_id = kept.len() as u32;
+                kept.push(value);
+            }
+        }
+        for id in &mut ids {
+            *id = remap[*id as usize];
+        }
+
+        Self {
+            codes: Codes::pack(ids, kept.len()),
+            index,
+            distinct: kept,
+        }
+    }
+}
+
+impl<K, V, H> DedupKvStore<K, V, H>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    /// Fast lookup with key verification; same contract as [`VerifiedKvStore::get`].
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Result<&V, KvError> {
+        match self.index.slot(key) {
+            Some(slot) => Ok(&self.distinct[self.codes.get(slot)]),
+            None => Err(KvError::KeyNotFoundFast),
+        }
+    }
+
+    /// Lookup with detailed error messages.
+    pub fn get_detailed(&self, key: &K) -> Result<&V, KvError>
+    where
+        K: Debug,
+    {
+        self.get(key).map_err(|_| KvError::KeyNotFound {
+            key: format!("{:?}", key),
+        })
+    }
+
+    /// Check if a key is in the store (accurate, no false positives).
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Hash `key` once; see [`VerifiedKvStore::hash_key`].
+    #[inline(always)]
+    pub fn hash_key<'k>(&self, key: &'k K) -> HashedKey<'k, K, H> {
+        self.index.hash_key(key)
+    }
+
+    /// Lookup with a precomputed hash; see [`VerifiedKvStore::get_hashed`].
+    #[inline(always)]
+    pub fn get_hashed(&self, key: &HashedKey<'_, K, H>) -> Result<&V, KvError> {
+        match self.index.slot_hashed(key) {
+            Some(slot) => Ok(&self.distinct[self.codes.get(slot)]),
+            None => Err(KvError::KeyNotFoundFast),
+        }
+    }
+
+    /// Returns the number of key-value pairs in the store.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Check if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns the width of the key hashes used by the MPHF.
+    pub fn hash_width(&self) -> HashWidth {
+        self.index.hash_width()
+    }
+
+    /// Returns the secret seed mixed into every key hash.
+    pub fn hash_seed(&self) -> u64 {
+        self.index.hash_seed()
+    }
+
+    /// Returns the global seed the MPHF settled on.
+    pub fn global_seed(&self) -> u64 {
+        self.index.global_seed()
+    }
+
+    /// Returns the hash function family used for keys.
+    pub fn hasher_kind(&self) -> HasherKind {
+        self.index.hasher_kind()
+    }
+
+    /// The distinct values, each stored once.
+    pub fn distinct_values(&self) -> &[V] {
+        &self.distinct
+    }
+
+    /// Bytes per slot used to refer to a distinct value: 1, 2 or 4.
+    pub fn code_bytes(&self) -> usize {
+        self.codes.width()
+    }
+
+    /// See [`VerifiedKvStore::prefilter_stats`].
+    pub fn prefilter_stats(&self) -> Option<PrefilterStats> {
+        self.index.prefilter_stats()
+    }
+
+    /// Returns an iterator over all keys in the store.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.index.keys()
+    }
+
+    /// Returns an iterator over the value of every slot, repeats included.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        (0..self.codes.len()).map(|slot| &self.distinct[self.codes.get(slot)])
+    }
+
+    /// Returns an iterator over all key-value pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.keys().zip(self.values())
+    }
+
+    /// Returns the approximate **stack-allocated** memory usage in bytes.
+    ///
+    /// Like [`VerifiedKvStore::memory_usage_bytes`], heap data owned by keys
+    /// and values and the MPHF itself are not included.
+    pub fn memory_usage_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.index.memory_usage_bytes()
+            + self.codes.len() * self.codes.width()
+            + self.distinct.capacity() * std::mem::size_of::<V>()
+    }
+}
+
+// Persistence, in the VerifiedKvStore file format
+impl<K, V, H> DedupKvStore<K, V, H>
+where
+    K: KvKey + Serialize + for<'de> Deserialize<'de>,
+    V: Clone + Eq + Hash + Serialize + for<'de> Deserialize<'de>,
+    H: StoreHasher<K>,
+{
+    /// Save the store; see [`VerifiedKvStore::save_to_file`].
+    ///
+    /// Values are written out per key. Use [`save_to_file_with`](Self::save_to_file_with)
+    /// and compress the values section to keep the file small.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), KvError> {
+        self.save_to_file_with(path, &WriteOptions::default())
+    }
+
+    /// Save the store, compressing sections as set in `options`.
+    pub fn save_to_file_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<(), KvError> {
+        crate::persistence::write_with_integrity(
+            path,
+            &self.persisted_data(),
+            &self.index.metadata(),
+            options,
+        )
+    }
+
+    /// Write the store to `writer`; see [`VerifiedKvStore::to_writer`].
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), KvError> {
+        self.to_writer_with(writer, &WriteOptions::default())
+    }
+
+    /// [`to_writer`](Self::to_writer) with the given compression settings.
+    pub fn to_writer_with<W: Write>(
+        &self,
+        writer: W,
+        options: &WriteOptions,
+    ) -> Result<(), KvError> {
+        crate::persistence::write_to(
+            writer,
+            &self.persisted_data(),
+            &self.index.metadata(),
+            options,
+        )
+    }
+
+    /// Encode the store as the bytes [`save_to_file`](Self::save_to_file) would write.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, KvError> {
+        let mut bytes = Vec::new();
+        self.to_writer(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn persisted_data(&self) -> PersistedData<K, V> {
+        PersistedData {
+            keys: self.keys().cloned().collect(),
+            values: self.values().cloned().collect(),
+            order: self.index.persisted_order(),
+        }
+    }
+
+    /// Load a store file, interning values as they are read; see
+    /// [`VerifiedKvStore::load_from_file`].
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, KvError> {
+        let (data, meta) = crate::persistence::read_with_validation(path)?;
+        Self::from_persisted(data, &meta)
+    }
+
+    /// Read a store written by [`to_writer`](Self::to_writer); see
+    /// [`VerifiedKvStore::from_reader`].
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, KvError> {
+        let (data, meta) = crate::persistence::read_from(reader)?;
+        Self::from_persisted(data, &meta)
+    }
+
+    fn from_persisted(
+        data: PersistedSections<K, InternedValues<V>>,
+        meta: &StoreMetadata,
+    ) -> Result<Self, KvError> {
+        let InternedValues { ids, distinct } = data.values;
+        let data = PersistedData {
+            keys: data.keys,
+            values: ids,
+            order: data.order,
+        };
+        let (index, ids) = VerifiedKvStore::<K, u32, H>::from_persisted(data, meta)?.split_values();
+        Ok(Self::from_parts(index, ids, distinct))
+    }
+
+    /// Decode a store from bytes produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KvError> {
+        Self::from_reader(bytes)
+    }
+}
+
+/// Builder for [`DedupKvStore`] that interns values as they are added.
+///
+/// Only one copy of each distinct value is held while building. Settings
+/// behave as on [`VerifiedKvStoreBuilder`].
+pub struct DedupKvStoreBuilder<K, V, H = DefaultHasherFor<K>>
+where
+    K: KvKey,
+{
+    ids: VerifiedKvStoreBuilder<K, u32, H>,
+    interner: Interner<V>,
+    overflow: Option<KvError>,
+}
+
+impl<K, V, H> DedupKvStoreBuilder<K, V, H>
+where
+    K: KvKey,
+    V: Clone + Eq + Hash,
+    H: StoreHasher<K>,
+{
+    pub fn new() -> Self {
+        Self {
+            ids: VerifiedKvStoreBuilder::new(),
+            interner: Interner::new(),
+            overflow: None,
+        }
+    }
+
+    pub fn insert(mut self, key: K, value: V) -> Self {
+        match self.interner.intern(value) {
+            Ok(id) => self.ids = self.ids.insert(key, id),
+            Err(err) => self.overflow = self.overflow.or(Some(err)),
+        }
+        self
+    }
+
+    pub fn extend<I>(mut self, iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            self = self.insert(key, value);
+        }
+        self
+    }
+
+    /// Whether an entry for `key` has been added.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.ids.contains_key(key)
+    }
+
+    /// Number of entries added so far.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether no entries have been added.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// See [`VerifiedKvStoreBuilder::hash_width`].
+    pub fn hash_width(mut self, width: HashWidth) -> Self {
+        self.ids = self.ids.hash_width(width);
+        self
+    }
+
+    /// See [`VerifiedKvStoreBuilder::hash_seed`].
+    pub fn hash_seed(mut self, seed: u64) -> Self {
+        self.ids = self.ids.hash_seed(seed);
+        self
+    }
+
+    /// See [`VerifiedKvStoreBuilder::seed`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.ids = self.ids.seed(seed);
+        self
+    }
+
+    /// See [`VerifiedKvStoreBuilder::prefilter`].
+    pub fn prefilter(mut self, fp_rate: f64) -> Self {
+        self.ids = self.ids.prefilter(fp_rate);
+        self
+    }
+
+    /// See [`VerifiedKvStoreBuilder::hash_tags`].
+    pub fn hash_tags(mut self, enabled: bool) -> Self {
+        self.ids = self.ids.hash_tags(enabled);
+        self
+    }
+
+    /// Build the store. Fails with `KvError::TooManyDistinctValues` if more
+    /// than 2^32 distinct values were added, or with the errors of
+    /// [`VerifiedKvStoreBuilder::build`].
+    pub fn build(self) -> Result<DedupKvStore<K, V, H>, KvError> {
+        if let Some(err) = self.overflow {
+            return Err(err);
+        }
+        let (index, ids) = self.ids.build()?.split_values();
+        Ok(DedupKvStore::from_parts(index, ids, self.interner.values))
+    }
+}
+
+impl<K, V, H> DedupKvStoreBuilder<K, V, H>
+where
+    K: KvKey + Ord,
+{
+    /// See [`VerifiedKvStoreBuilder::sorted_index`]. The index is kept in
+    /// saved files, so a [`VerifiedKvStore`] loaded from them can iterate in
+    /// key order.
+    pub fn sorted_index(mut self) -> Self {
+        self.ids = self.ids.sorted_index();
+        self
+    }
+}
+
+impl<K, V, H> Default for DedupKvStoreBuilder<K, V, H>
+where
+    K: KvKey,
+    V: Clone + Eq + Hash,
+    H: StoreHasher<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::PortableHash;
+
+    #[test]
+    fn test_code_width_follows_cardinality() {
+        let narrow: DedupKvStore<u64, u32> = DedupKvStoreBuilder::new()
+            .extend((0..10_000u64).map(|i| (i, (i % 256) as u32)))
+            .build()
+            .unwrap();
+        assert_eq!(narrow.code_bytes(), 1);
+        assert_eq!(narrow.distinct_values().len(), 256);
+
+        let medium: DedupKvStore<u64, u32> = DedupKvStoreBuilder::new()
+            .extend((0..10_000u64).map(|i| (i, (i % 257) as u32)))
+            .build()
+            .unwrap();
+        assert_eq!(medium.code_bytes(), 2);
+
+        let wide: DedupKvStore<u64, u32> = DedupKvStoreBuilder::new()
+            .extend((0..70_000u64).map(|i| (i, i as u32)))
+            .build()
+            .unwrap();
+        assert_eq!(wide.code_bytes(), 4);
+
+        for i in (0..70_000u64).step_by(997) {
+            assert_eq!(*wide.get(&i).unwrap(), i as u32);
+        }
+        assert_eq!(*medium.get(&300).unwrap(), 43);
+        assert!(matches!(narrow.get(&10_000), Err(KvError::KeyNotFoundFast)));
+    }
+
+    #[test]
+    fn test_overwritten_values_are_dropped() {
+        let store: DedupKvStore<String, String> = DedupKvStoreBuilder::new()
+            .insert("a".to_string(), "old".to_string())
+            .insert("b".to_string(), "shared".to_string())
+            .insert("a".to_string(), "shared".to_string())
+            .build()
+            .unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.distinct_values(), ["shared".to_string()]);
+        assert!(store.iter().all(|(_, v)| v == "shared"));
+    }
+
+    #[test]
+    fn test_file_format_matches_verified_store() {
+        let store: DedupKvStore<String, String, PortableHash> = DedupKvStoreBuilder::new()
+            .extend((0..500).map(|i| (format!("item:{}", i), format!("cat-{}", i % 7))))
+            .seed(11)
+            .hash_tags(true)
+            .sorted_index()
+            .build()
+            .unwrap();
+
+        let plain: VerifiedKvStore<String, String, PortableHash> =
+            VerifiedKvStore::from_bytes(&store.to_bytes().unwrap()).unwrap();
+        assert_eq!(plain.len(), 500);
+        assert!(plain.has_hash_tags() && plain.has_sorted_index());
+        assert_eq!(plain.get(&"item:12".to_string()).unwrap(), "cat-5");
+        assert_eq!(plain.to_bytes().unwrap(), store.to_bytes().unwrap());
+
+        let test_file = "/tmp/test_dedup_store.bin";
+        plain.save_to_file(test_file).unwrap();
+        let loaded: DedupKvStore<String, String, PortableHash> =
+            DedupKvStore::load_from_file(test_file).unwrap();
+        assert_eq!(loaded.distinct_values().len(), 7);
+        assert_eq!(loaded.global_seed(), store.global_seed());
+        assert_eq!(loaded.get(&"item:499".to_string()).unwrap(), "cat-2");
+        let read: DedupKvStore<String, String, PortableHash> =
+            DedupKvStore::from_bytes(&plain.to_bytes().unwrap()).unwrap();
+        assert_eq!(read.to_bytes().unwrap(), store.to_bytes().unwrap());
+
+        std::fs::remove_file(test_file).ok();
+    }
+
+    #[test]
+    fn test_interner_limit() {
+        let mut interner = Interner::with_limit(2);
+        assert_eq!(interner.intern("a").unwrap(), 0);
+        assert_eq!(interner.intern("b").unwrap(), 1);
+        assert_eq!(interner.intern("a").unwrap(), 0);
+        assert!(matches!(
+            interner.intern("c"),
+            Err(KvError::TooManyDistinctValues)
+        ));
+        assert_eq!(interner.values, ["a", "b"]);
+    }
+}
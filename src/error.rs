@@ -16,4 +16,56 @@ pub enum KvError {
 
     #[error("Empty key set provided")]
     EmptyKeySet,
+
+    #[error("Incompatible hasher: {0}")]
+    IncompatibleHasher(String),
+
+    #[error("Incompatible key normalizer: {0}")]
+    IncompatibleNormalizer(String),
+
+    #[error("Keys {first:?} and {second:?} both normalize to {normalized:?}")]
+    NormalizedKeyCollision {
+        normalized: String,
+        first: String,
+        second: String,
+    },
+
+    #[error(
+        "MPHF construction failed: keys hash to duplicate values under the hash seed(s) tried"
+    )]
+    DuplicateHashes,
+
+    #[error("MPHF construction failed: no seed led to a valid pilot assignment")]
+    PilotSearchFailed,
+
+    #[error("Too many distinct values: at most 2^32 are supported")]
+    TooManyDistinctValues,
+
+    #[error("Invalid input on line {line}: {message}")]
+    InvalidInput { line: u64, message: String },
+
+    #[error("Invalid import settings: {0}")]
+    InvalidImportSettings(String),
+
+    #[error("Column not found: {0}")]
+    ColumnNotFound(String),
+
+    #[error("Column already exists: {0}")]
+    ColumnExists(String),
+
+    #[error("Column {column} holds {actual}, not {expected}")]
+    ColumnType {
+        column: String,
+        expected: &'static str,
+        actual: &'static str,
+    },
+}
+
+impl From<ptr_hash::BuildError> for KvError {
+    fn from(err: ptr_hash::BuildError) -> Self {
+        match err {
+            ptr_hash::BuildError::DuplicateHashes => KvError::DuplicateHashes,
+            ptr_hash::BuildError::PilotSearchFailed => KvError::PilotSearchFailed,
+        }
+    }
 }
@@ -0,0 +1,240 @@
+//! Write store entries as NDJSON or CSV/TSV.
+//!
+//! Entries come out in MPHF slot order by default, which looks random and
+//! changes whenever the key set does. Use [`Order::Sorted`] for dumps that are
+//! reproducible and can be diffed with text tools.
+//!
+//! Output can be read back with [`crate::import`]: NDJSON lines are
+//! `{"key": .., "value": ..}`, and CSV cells hold strings as-is and any other
+//! value as its JSON text.
+//!
+//! # Example
+//!
+//! ```
+//! use learned_kv::export::{export_ndjson, Order};
+//! use learned_kv::VerifiedKvStoreBuilder;
+//!
+//! let store = VerifiedKvStoreBuilder::<u64, &str>::new()
+//!     .insert(2, "bob")
+//!     .insert(1, "alice")
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut out = Vec::new();
+//! export_ndjson(&store, &mut out, Order::Sorted).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(out).unwrap(),
+//!     "{\"key\":1,\"value\":\"alice\"}\n{\"key\":2,\"value\":\"bob\"}\n"
+//! );
+//! ```
+
+use crate::error::KvError;
+use crate::hasher::{KvKey, StoreHasher};
+use crate::verified_kv_store::VerifiedKvStore;
+use serde::Serialize;
+use std::io::{BufWriter, Write};
+
+/// Order in which entries are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// MPHF slot order, as returned by `iter()`: entry `i` is the one at
+    /// [`index_of`](VerifiedKvStore::index_of) `i`. No extra memory.
+    #[default]
+    Slot,
+    /// Ascending by key. Sorts references to all entries first.
+    Sorted,
+}
+
+/// CSV/TSV output settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// Field delimiter (default `,`).
+    pub delimiter: u8,
+    /// Write a `key,value` header row first (default `false`).
+    pub header: bool,
+    /// Entry order (default [`Order::Slot`]).
+    pub order: Order,
+}
+
+impl CsvOptions {
+    /// Tab-separated output.
+    pub fn tsv() -> Self {
+        Self {
+            delimiter: b'\t',
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: false,
+            order: Order::Slot,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Entry<'a, K, V> {
+    key: &'a K,
+    value: &'a V,
+}
+
+/// Write every entry as a `{"key": .., "value": ..}` line. Returns the number
+/// of entries written.
+pub fn export_ndjson<K, V, H, W>(
+    store: &VerifiedKvStore<K, V, H>,
+    writer: W,
+    order: Order,
+) -> Result<usize, KvError>
+where
+    K: KvKey + Ord + Serialize,
+    V: Clone + Serialize,
+    H: StoreHasher<K>,
+    W: Write,
+{
+    let mut writer = BufWriter::new(writer);
+    for (key, value) in entries(store, order) {
+        serde_json::to_writer(&mut writer, &Entry { key, value }).map_err(std::io::Error::from)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(store.len())
+}
+
+/// Write every entry as a `key,value` row. Returns the number of entries
+/// written.
+pub fn export_csv<K, V, H, W>(
+    store: &VerifiedKvStore<K, V, H>,
+    writer: W,
+    options: &CsvOptions,
+) -> Result<usize, KvError>
+where
+    K: KvKey + Ord + Serialize,
+    V: Clone + Serialize,
+    H: StoreHasher<K>,
+    W: Write,
+{
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .from_writer(writer);
+    if options.header {
+        writer.write_record(["key", "value"]).map_err(csv_error)?;
+    }
+    for (key, value) in entries(store, options.order) {
+        writer
+            .write_record([cell(key)?, cell(value)?])
+            .map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(store.len())
+}
+
+fn entries<K, V, H>(
+    store: &VerifiedKvStore<K, V, H>,
+    order: Order,
+) -> Box<dyn Iterator<Item = (&K, &V)> + '_>
+where
+    K: KvKey + Ord,
+    V: Clone,
+    H: StoreHasher<K>,
+{
+    match order {
+        Order::Slot => Box::new(store.iter()),
+        Order::Sorted => {
+            let mut sorted: Vec<_> = store.iter().collect();
+            sorted.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            Box::new(sorted.into_iter())
+        }
+    }
+}
+
+/// Strings as-is, anything else as JSON text.
+fn cell<T: Serialize>(value: &T) -> Result<String, KvError> {
+    match serde_json::to_value(value).map_err(std::io::Error::from)? {
+        serde_json::Value::String(s) => Ok(s),
+        other => Ok(other.to_string()),
+    }
+}
+
+fn csv_error(err: csv::Error) -> KvError {
+    KvError::IoError(std::io::Error::other(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::{Format, Importer};
+    use crate::VerifiedKvStoreBuilder;
+
+    fn store(seed: u64) -> VerifiedKvStore<String, Vec<u32>> {
+        VerifiedKvStoreBuilder::new()
+            .seed(seed)
+            .extend((0..100u32).map(|i| (format!("key_{:03}", i), vec![i, i * 2])))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_sorted_dump_is_stable() {
+        let dump = |seed| {
+            let mut out = Vec::new();
+            export_ndjson(&store(seed), &mut out, Order::Sorted).unwrap();
+            out
+        };
+        let first = dump(1);
+        assert_eq!(first, dump(2));
+
+        let text = String::from_utf8(first).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("{\"key\":\"key_000\",\"value\":[0,0]}"));
+        assert_eq!(
+            lines.last(),
+            Some("{\"key\":\"key_099\",\"value\":[99,198]}")
+        );
+    }
+
+    #[test]
+    fn test_csv_header_and_cells() {
+        let store = VerifiedKvStoreBuilder::<String, Vec<u32>>::new()
+            .insert("b".to_string(), vec![2])
+            .insert("a".to_string(), vec![1, 1])
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        let options = CsvOptions {
+            header: true,
+            order: Order::Sorted,
+            ..CsvOptions::default()
+        };
+        assert_eq!(export_csv(&store, &mut out, &options).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "key,value\na,\"[1,1]\"\nb,[2]\n"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_through_import() {
+        let original = store(7);
+
+        let mut csv = Vec::new();
+        export_csv(&original, &mut csv, &CsvOptions::tsv()).unwrap();
+        let mut ndjson = Vec::new();
+        export_ndjson(&original, &mut ndjson, Order::Slot).unwrap();
+
+        for (format, bytes) in [(Format::Tsv, csv), (Format::Ndjson, ndjson)] {
+            let imported: VerifiedKvStore<String, Vec<u32>> = Importer::new(format)
+                .read(bytes.as_slice(), VerifiedKvStoreBuilder::new())
+                .unwrap()
+                .build()
+                .unwrap();
+            assert_eq!(imported.len(), original.len());
+            for (key, value) in original.iter() {
+                assert_eq!(imported.get(key).unwrap(), value);
+            }
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! C ABI for byte-valued stores, declared in `include/learned_kv.h`.
+//!
+//! Stores are opened with [`lkv_open`] from files written by `save_to_file`
+//! with `String` keys and `String` or `Vec<u8>` values (see
+//! [`crate::byte_store`]). Every function returns an `LKV_*` status code, or
+//! a plain value where it cannot fail. An open store is immutable, so it may
+//! be shared between threads until [`lkv_close`].
+
+use crate::byte_store::{load_byte_store, ByteStore};
+use crate::error::KvError;
+use std::ffi::{c_char, c_int, CStr};
+use std::path::PathBuf;
+use std::ptr;
+
+pub const LKV_OK: c_int = 0;
+pub const LKV_ERR_NOT_FOUND: c_int = 1;
+pub const LKV_ERR_INVALID_ARGUMENT: c_int = 2;
+pub const LKV_ERR_IO: c_int = 3;
+pub const LKV_ERR_INVALID_FILE: c_int = 4;
+pub const LKV_ERR_INCOMPATIBLE_HASHER: c_int = 5;
+pub const LKV_ERR_OTHER: c_int = 6;
+
+/// Opaque store handle (`lkv_store` in C).
+pub struct LkvStore {
+    store: Box<dyn ByteStore>,
+}
+
+/// Status code for `err`.
+fn error_code(err: &KvError) -> c_int {
+    match err {
+        KvError::KeyNotFound { .. } | KvError::KeyNotFoundFast => LKV_ERR_NOT_FOUND,
+        KvError::IoError(e) if e.kind() == std::io::ErrorKind::InvalidData => LKV_ERR_INVALID_FILE,
+        KvError::IoError(_) => LKV_ERR_IO,
+        KvError::SerializationError(_) => LKV_ERR_INVALID_FILE,
+        KvError::IncompatibleHasher(_) => LKV_ERR_INCOMPATIBLE_HASHER,
+        KvError::IncompatibleNormalizer(_)
+        | KvError::NormalizedKeyCollision { .. }
+        | KvError::EmptyKeySet
+        | KvError::DuplicateHashes
+        | KvError::PilotSearchFailed
+        | KvError::TooManyDistinctValues
+        | KvError::InvalidInput { .. }
+        | KvError::InvalidImportSettings(_)
+        | KvError::ColumnNotFound(_)
+        | KvError::ColumnExists(_)
+        | KvError::ColumnType { .. } => LKV_ERR_OTHER,
+    }
+}
+
+/// Borrow `len` bytes at `ptr`, which may be null when `len` is 0.
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(std::slice::from_raw_parts(ptr, len))
+    }
+}
+
+#[cfg(unix)]
+fn path_from_c(path: &CStr) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+    Some(std::ffi::OsStr::from_bytes(path.to_bytes()).into())
+}
+
+#[cfg(not(unix))]
+fn path_from_c(path: &CStr) -> Option<PathBuf> {
+    path.to_str().ok().map(PathBuf::from)
+}
+
+/// Open the store file at `path` (NUL-terminated) and write its handle to `*out`.
+///
+/// # Safety
+///
+/// `path` must be a valid C string and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lkv_open(path: *const c_char, out: *mut *mut LkvStore) -> c_int {
+    if path.is_null() || out.is_null() {
+        return LKV_ERR_INVALID_ARGUMENT;
+    }
+    *out = ptr::null_mut();
+    let Some(path) = path_from_c(CStr::from_ptr(path)) else {
+        return LKV_ERR_INVALID_ARGUMENT;
+    };
+    match load_byte_store(path) {
+        Ok(store) => {
+            *out = Box::into_raw(Box::new(LkvStore { store }));
+            LKV_OK
+        }
+        Err(e) => error_code(&e),
+    }
+}
+
+/// Look up a key. On success `*val_ptr`/`*val_len` point at the value, which
+/// stays valid until the store is closed.
+///
+/// # Safety
+///
+/// `store` must come from [`lkv_open`] and not be closed, `key_ptr` must be
+/// valid for `key_len` bytes, and `val_ptr`/`val_len` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lkv_get(
+    store: *const LkvStore,
+    key_ptr: *const u8,
+    key_len: usize,
+    val_ptr: *mut *const u8,
+    val_len: *mut usize,
+) -> c_int {
+    if store.is_null() || val_ptr.is_null() || val_len.is_null() {
+        return LKV_ERR_INVALID_ARGUMENT;
+    }
+    let Some(key) = bytes(key_ptr, key_len) else {
+        return LKV_ERR_INVALID_ARGUMENT;
+    };
+    match (*store).store.get(key) {
+        Ok(value) => {
+            *val_ptr = value.as_ptr();
+            *val_len = value.len();
+            LKV_OK
+        }
+        Err(e) => error_code(&e),
+    }
+}
+
+/// 1 if the key is present, 0 if not or if an argument is null.
+///
+/// # Safety
+///
+/// Same as [`lkv_get`].
+#[no_mangle]
+pub unsafe extern "C" fn lkv_contains(
+    store: *const LkvStore,
+    key_ptr: *const u8,
+    key_len: usize,
+) -> c_int {
+    if store.is_null() {
+        return 0;
+    }
+    match bytes(key_ptr, key_len) {
+        Some(key) => c_int::from((*store).store.contains_key(key)),
+        None => 0,
+    }
+}
+
+/// Number of entries, or 0 for a null handle.
+///
+/// # Safety
+///
+/// `store` must be null or come from [`lkv_open`] and not be closed.
+#[no_mangle]
+pub unsafe extern "C" fn lkv_len(store: *const LkvStore) -> usize {
+    if store.is_null() {
+        0
+    } else {
+        (*store).store.len()
+    }
+}
+
+/// Free a store. Null is ignored.
+///
+/// # Safety
+///
+/// `store` must be null or come from [`lkv_open`], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn lkv_close(store: *mut LkvStore) {
+    if !store.is_null() {
+        drop(Box::from_raw(store));
+    }
+}
+
+/// Static description of a status code.
+#[no_mangle]
+pub extern "C" fn lkv_strerror(code: c_int) -> *const c_char {
+    let message: &'static CStr = match code {
+        LKV_OK => c"success",
+        LKV_ERR_NOT_FOUND => c"key not found",
+        LKV_ERR_INVALID_ARGUMENT => c"invalid argument",
+        LKV_ERR_IO => c"I/O error",
+        LKV_ERR_INVALID_FILE => c"invalid or corrupt store file",
+        LKV_ERR_INCOMPATIBLE_HASHER => c"store was built with an incompatible hasher",
+        LKV_ERR_OTHER => c"other error",
+        _ => c"unknown status code",
+    };
+    message.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_store;
+
+    #[test]
+    fn test_open_get_close() {
+        let path = "/tmp/test_ffi_store.bin";
+        write_store(path, &[("user:1", "alice"), ("empty", "")]);
+        let c_path = std::ffi::CString::new(path).unwrap();
+
+        unsafe {
+            let mut store = ptr::null_mut();
+            assert_eq!(lkv_open(c_path.as_ptr(), &mut store), LKV_OK);
+            assert_eq!(lkv_len(store), 2);
+
+            let mut val_ptr = ptr::null();
+            let mut val_len = 0;
+            let key = b"user:1";
+            assert_eq!(
+                lkv_get(store, key.as_ptr(), key.len(), &mut val_ptr, &mut val_len),
+                LKV_OK
+            );
+            assert_eq!(std::slice::from_raw_parts(val_ptr, val_len), b"alice");
+
+            let key = b"empty";
+            assert_eq!(
+                lkv_get(store, key.as_ptr(), key.len(), &mut val_ptr, &mut val_len),
+                LKV_OK
+            );
+            assert_eq!(val_len, 0);
+
+            assert_eq!(
+                lkv_get(store, b"nope".as_ptr(), 4, &mut val_ptr, &mut val_len),
+                LKV_ERR_NOT_FOUND
+            );
+            assert_eq!(lkv_contains(store, b"user:1".as_ptr(), 6), 1);
+            assert_eq!(lkv_contains(store, ptr::null(), 0), 0);
+            assert_eq!(lkv_contains(store, ptr::null(), 3), 0);
+
+            lkv_close(store);
+        }
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_open_errors() {
+        let path = "/tmp/test_ffi_corrupt.bin";
+        std::fs::write(path, b"not a store").unwrap();
+
+        unsafe {
+            let mut store = ptr::null_mut();
+            assert_eq!(
+                lkv_open(c"/tmp/test_ffi_missing.bin".as_ptr(), &mut store),
+                LKV_ERR_IO
+            );
+            assert!(store.is_null());
+            let c_path = std::ffi::CString::new(path).unwrap();
+            assert_eq!(lkv_open(c_path.as_ptr(), &mut store), LKV_ERR_INVALID_FILE);
+            assert_eq!(lkv_open(ptr::null(), &mut store), LKV_ERR_INVALID_ARGUMENT);
+            assert_eq!(lkv_len(ptr::null()), 0);
+            lkv_close(ptr::null_mut());
+
+            let message = CStr::from_ptr(lkv_strerror(LKV_ERR_NOT_FOUND));
+            assert_eq!(message.to_str().unwrap(), "key not found");
+        }
+
+        std::fs::remove_file(path).ok();
+    }
+}
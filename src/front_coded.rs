@@ -0,0 +1,371 @@
+//! Stores that keep their verification keys front-coded.
+//!
+//! Full keys are needed to verify lookups, and for URLs, file paths and
+//! similar keys they are usually most of a store's memory. [`FrontCodedKvStore`]
+//! sorts the keys and stores them in small blocks: the first key of a block in
+//! full, every later key as the length of the prefix it shares with the key
+//! before it plus the remaining suffix. A lookup compares the query against
+//! its block incrementally, without rebuilding any key.
+//!
+//! Memory use is reported by [`FrontCodedKvStore::key_memory`].
+
+use crate::error::KvError;
+use crate::hasher::{DefaultHasherFor, HasherKind, KvKey, StoreHasher};
+use crate::mphf::{hash_tag, Mphf};
+use crate::prefilter::Prefilter;
+use crate::verified_kv_store::StoreParts;
+use crate::verified_kv_store::VerifiedKvStore;
+
+/// Keys per block unless given otherwise. Longer blocks compress slightly
+/// better but make each verification scan further.
+pub const DEFAULT_KEYS_PER_BLOCK: usize = 16;
+
+/// Memory held for keys by a [`FrontCodedKvStore`], next to what a
+/// [`VerifiedKvStore`] holds for the same keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyMemory {
+    /// Number of keys.
+    pub keys: usize,
+    /// Total length of all keys.
+    pub raw_bytes: usize,
+    /// Key objects plus their heap data, as kept by a `VerifiedKvStore`.
+    pub uncompressed_bytes: usize,
+    /// Front-coded blocks plus their offsets.
+    pub front_coded_bytes: usize,
+    /// Slot to sorted-position table.
+    pub rank_bytes: usize,
+    /// Slot hash tags, 0 without them.
+    pub tag_bytes: usize,
+}
+
+impl KeyMemory {
+    /// Bytes held for keys in total.
+    pub fn total_bytes(&self) -> usize {
+        self.front_coded_bytes + self.rank_bytes + self.tag_bytes
+    }
+
+    /// `uncompressed_bytes` per byte actually held.
+    pub fn ratio(&self) -> f64 {
+        match self.total_bytes() {
+            0 => 1.0,
+            total => self.uncompressed_bytes as f64 / total as f64,
+        }
+    }
+}
+
+/// Verified key-value store with front-coded keys.
+///
+/// Lookups verify the full key like [`VerifiedKvStore`], at the cost of a
+/// short scan through one block. Keys are any byte-like key type, such as
+/// `String` or `Vec<u8>`.
+///
+/// # Example
+/// ```
+/// use learned_kv::front_coded::FrontCodedKvStore;
+/// use learned_kv::VerifiedKvStoreBuilder;
+///
+/// let store = VerifiedKvStoreBuilder::new()
+///     .extend((0..100).map(|i| (format!("https://example.com/docs/page/{}", i), i)))
+///     .build()?;
+/// let store: FrontCodedKvStore<String, i32> = FrontCodedKvStore::new(store);
+///
+/// assert_eq!(store.get(&"https://example.com/docs/page/42".to_string())?, &42);
+/// assert!(!store.contains_key(&"https://example.com/docs/page/".to_string()));
+/// assert!(store.key_memory().ratio() > 2.0);
+/// # Ok::<(), learned_kv::KvError>(())
+/// ```
+pub struct FrontCodedKvStore<K, V, H = DefaultHasherFor<K>>
+where
+    K: KvKey + AsRef<[u8]>,
+    H: StoreHasher<K>,
+{
+    mphf: Mphf<K, H>,
+    hash_seed: u64,
+    prefilter: Option<Prefilter>,
+    tags: Option<Vec<u32>>,
+    values: Vec<V>,
+    /// Sorted position of the key in each slot.
+    ranks: Vec<u32>,
+    keys_per_block: usize,
+    blocks: Vec<u8>,
+    block_offsets: Vec<usize>,
+    uncompressed_bytes: usize,
+    raw_bytes: usize,
+}
+
+impl<K, V, H> FrontCodedKvStore<K, V, H>
+where
+    K: KvKey + AsRef<[u8]>,
+    H: StoreHasher<K>,
+{
+    /// Front-code the keys of `store` in blocks of [`DEFAULT_KEYS_PER_BLOCK`].
+    pub fn new(store: VerifiedKvStore<K, V, H>) -> Self {
+        Self::with_block_size(store, DEFAULT_KEYS_PER_BLOCK)
+    }
+
+    /// Front-code the keys of `store` in blocks of `keys_per_block` (at least 1).
+    ///
+    /// # Panics
+    ///
+    /// If the store has 2^32 keys or more.
+    pub fn with_block_size(store: VerifiedKvStore<K, V, H>, keys_per_block: usize) -> Self {
+        let keys_per_block = keys_per_block.max(1);
+        let StoreParts {
+            mphf,
+            hash_seed,
+            prefilter,
+            tags,
+            keys,
+            values,
+        } = store.into_parts();
+        let n = u32::try_from(keys.len()).expect("front coding supports fewer than 2^32 keys");
+
+        let raw_bytes = keys.iter().map(|key| key.as_ref().len()).sum();
+        let uncompressed_bytes = keys.len() * std::mem::size_of::<K>() + raw_bytes;
+
+        let mut order: Vec<u32> = (0..n).collect();
+        order.sort_unstable_by(|&a, &b| keys[a as usize].as_ref().cmp(keys[b as usize].as_ref()));
+
+        let mut ranks = vec![0; keys.len()];
+        let mut blocks = Vec::new();
+        let mut block_offsets = Vec::with_capacity(keys.len().div_ceil(keys_per_block));
+        let mut previous: &[u8] = &[];
+        for (rank, &slot) in order.iter().enumerate() {
+            ranks[slot as usize] = rank as u32;
+            let key = keys[slot as usize].as_ref();
+            if rank % keys_per_block == 0 {
+                block_offsets.push(blocks.len());
+                write_varint(&mut blocks, key.len());
+                blocks.extend_from_slice(key);
+            } else {
+                let shared = common_prefix(previous, key);
+                write_varint(&mut blocks, shared);
+                write_varint(&mut blocks, key.len() - shared);
+                blocks.extend_from_slice(&key[shared..]);
+            }
+            previous = key;
+        }
+        blocks.shrink_to_fit();
+
+        Self {
+            mphf,
+            hash_seed,
+            prefilter,
+            tags,
+            values,
+            ranks,
+            keys_per_block,
+            blocks,
+            block_offsets,
+            uncompressed_bytes,
+            raw_bytes,
+        }
+    }
+
+    /// Fast lookup with key verification; same contract as [`VerifiedKvStore::get`].
+    pub fn get(&self, key: &K) -> Result<&V, KvError> {
+        match self.slot(key) {
+            Some(slot) => Ok(&self.values[slot]),
+            None => Err(KvError::KeyNotFoundFast),
+        }
+    }
+
+    /// Check if a key is in the store (accurate, no false positives).
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.slot(key).is_some()
+    }
+
+    /// Slot of `key`, verified against the front-coded key at its rank.
+    fn slot(&self, key: &K) -> Option<usize> {
+        let hx = self.mphf.hash(key);
+        if let Some(prefilter) = &self.prefilter {
+            if !prefilter.may_contain(hx) {
+                return None;
+            }
+        }
+        let slot = self.mphf.index_from_hash(hx);
+        let rank = *self.ranks.get(slot)? as usize;
+        if let Some(tags) = &self.tags {
+            if tags[slot] != hash_tag(hx) {
+                return None;
+            }
+        }
+        let query = key.as_ref();
+
+        let block = rank / self.keys_per_block;
+        let mut pos = self.block_offsets[block];
+        let first_len = read_varint(&self.blocks, &mut pos);
+        let first = &self.blocks[pos..pos + first_len];
+        pos += first_len;
+
+        // Invariant: `matched` is the common prefix length of `query` and
+        // the current key, which is `len` bytes long.
+        let mut matched = common_prefix(first, query);
+        let mut len = first_len;
+        for _ in 0..rank % self.keys_per_block {
+            let shared = read_varint(&self.blocks, &mut pos);
+            let suffix_len = read_varint(&self.blocks, &mut pos);
+            let suffix = &self.blocks[pos..pos + suffix_len];
+            pos += suffix_len;
+            // A key sharing more than `matched` bytes with the previous one
+            // differs from the query exactly where the previous one did.
+            if shared <= matched {
+                matched = shared + common_prefix(suffix, &query[shared..]);
+            }
+            len = shared + suffix_len;
+        }
+        (matched == query.len() && len == query.len()).then_some(slot)
+    }
+
+    /// Returns the number of key-value pairs in the store.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Check if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the secret seed mixed into every key hash.
+    pub fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    /// Returns the hash function family used for keys.
+    pub fn hasher_kind(&self) -> HasherKind {
+        H::KIND
+    }
+
+    /// Returns an iterator over all values in the store, in slot order.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.values.iter()
+    }
+
+    /// Memory held for keys, and what it would be without front coding.
+    pub fn key_memory(&self) -> KeyMemory {
+        KeyMemory {
+            keys: self.len(),
+            raw_bytes: self.raw_bytes,
+            uncompressed_bytes: self.uncompressed_bytes,
+            front_coded_bytes: self.blocks.capacity()
+                + self.block_offsets.capacity() * std::mem::size_of::<usize>(),
+            rank_bytes: self.ranks.capacity() * std::mem::size_of::<u32>(),
+            tag_bytes: self
+                .tags
+                .as_ref()
+                .map_or(0, |tags| tags.capacity() * std::mem::size_of::<u32>()),
+        }
+    }
+}
+
+/// Length of the longest common prefix of `a` and `b`.
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// Append `value` as a LEB128 varint.
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Read a LEB128 varint written by [`write_varint`] at `*pos`, advancing it.
+fn read_varint(data: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::PortableHash;
+    use crate::VerifiedKvStoreBuilder;
+
+    fn path_store(n: usize) -> VerifiedKvStore<String, usize, PortableHash> {
+        VerifiedKvStoreBuilder::new()
+            .extend((0..n).map(|i| {
+                (
+                    format!("/srv/data/projects/p{}/assets/images/{}.png", i % 13, i),
+                    i,
+                )
+            }))
+            .seed(3)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_lookups_match_verified_store() {
+        let plain = path_store(5000);
+        let expected: Vec<(String, usize)> = plain.iter().map(|(k, v)| (k.clone(), *v)).collect();
+
+        for keys_per_block in [1, 4, 16, 64] {
+            let store = FrontCodedKvStore::with_block_size(plain.clone(), keys_per_block);
+            assert_eq!(store.len(), 5000);
+            for (key, value) in &expected {
+                assert_eq!(store.get(key).unwrap(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_near_misses_are_rejected() {
+        let store = FrontCodedKvStore::new(path_store(2000));
+        let key = "/srv/data/projects/p3/assets/images/42.png".to_string();
+        assert!(store.contains_key(&key));
+
+        let near_misses = [
+            "/srv/data/projects/p3/assets/images/42.pn",
+            "/srv/data/projects/p3/assets/images/42.pngx",
+            "/srv/data/projects/p3/assets/images/43.png",
+            "/srv/data/projects/p3/assets/images/",
+            "",
+            "/srv/data/projects/p3/assets/images/42.png/",
+        ];
+        for miss in near_misses {
+            assert!(
+                matches!(store.get(&miss.to_string()), Err(KvError::KeyNotFoundFast)),
+                "{:?} should miss",
+                miss
+            );
+        }
+    }
+
+    #[test]
+    fn test_key_memory_report() {
+        let store = FrontCodedKvStore::new(path_store(10_000));
+        let memory = store.key_memory();
+
+        assert_eq!(memory.keys, 10_000);
+        assert_eq!(memory.rank_bytes, 40_000);
+        assert!(memory.front_coded_bytes < memory.raw_bytes / 3);
+        assert!(memory.ratio() > 3.0, "ratio {}", memory.ratio());
+    }
+
+    #[test]
+    fn test_varint_roundtrip() {
+        let mut buf = Vec::new();
+        let values = [0, 1, 127, 128, 300, 16_384, usize::MAX];
+        for &value in &values {
+            write_varint(&mut buf, value);
+        }
+        let mut pos = 0;
+        for &value in &values {
+            assert_eq!(read_varint(&buf, &mut pos), value);
+        }
+        assert_eq!(pos, buf.len());
+    }
+}
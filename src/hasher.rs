@@ -0,0 +1,484 @@
+//! Key types and the hash functions used to index them.
+//!
+//! The MPHF hashes every key through a [`StoreHasher`]. Each key type picks a
+//! sensible default via [`KvKey::DefaultHasher`]:
+//!
+//! - `String` / `Vec<u8>`: [`GxHash`], AES-NI accelerated; [`Xxh3Hash`] in
+//!   builds without the `gxhash` feature or on CPUs other than x86 and ARM
+//! - Integers and `(u32, u32)` / `(u64, u64)` pairs: [`StrongerIntHash`], a
+//!   couple of multiplications instead of a full string hash
+//!
+//! Any other [`StoreHasher`] can be chosen explicitly through the `H` parameter
+//! of [`VerifiedKvStore`](crate::VerifiedKvStore).
+//!
+//! ## Portability
+//!
+//! GxHash needs AES instructions and produces different values on x86 and ARM.
+//! The hasher is recorded in the file header, and loading fails with
+//! [`KvError::IncompatibleHasher`] if this machine can't reproduce it. For
+//! files that move between CPU families use [`Xxh3Hash`] (SIMD accelerated)
+//! or [`PortableHash`] (SipHash-1-3, plain software).
+//!
+//! Both feed keys through std [`Hash`], so they give the same values on every
+//! little-endian platform only for keys whose `Hash` impl doesn't depend on
+//! the pointer width: strings and fixed-width integers do, while `Vec<u8>`
+//! (length-prefixed with a `usize`) and `usize` / `isize` keys hash
+//! differently on 32-bit and 64-bit targets.
+//!
+//! ## Hash width
+//!
+//! 64-bit hashes start colliding once a store holds around 10^9 keys. Above
+//! [`WIDE_HASH_THRESHOLD`] keys the store switches to the 128-bit hasher of
+//! the same family ([`StoreHasher::Wide`]), e.g. [`GxHash`] → [`GxHash128`].
+//! Use [`HashWidth`] to force either width.
+//!
+//! ## Hash seed
+//!
+//! Every store mixes a secret 64-bit seed into its key hashes. Without one,
+//! whoever controls the keys could precompute keys with colliding hashes and
+//! make construction fail for every MPHF seed. By default the seed comes from
+//! [`random_hash_seed`]; it can also be supplied through
+//! [`VerifiedKvStoreBuilder::hash_seed`](crate::VerifiedKvStoreBuilder::hash_seed).
+//! The seed is stored in the file header so the store can be rebuilt on load.
+
+pub use ptr_hash::hash::{KeyHasher, StrongerIntHash};
+
+use crate::error::KvError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Key count above which [`HashWidth::Auto`] selects 128-bit hashes.
+///
+/// Follows the PtrHash recommendation of 64-bit string hashes for at most 10^9 keys.
+pub const WIDE_HASH_THRESHOLD: usize = 1_000_000_000;
+
+/// A 64-bit hasher usable by a `VerifiedKvStore`, paired with its 128-bit counterpart.
+pub trait StoreHasher<K: ?Sized>: KeyHasher<K, H = u64> + Send {
+    /// 128-bit hasher from the same family, used for very large key sets.
+    type Wide: KeyHasher<K, H = u128> + Send;
+    /// Identifies the hash function in the file header.
+    const KIND: HasherKind;
+}
+
+/// A [`StoreHasher`] for `String` keys that hashes a `str` exactly like the
+/// `String` it borrows from, so that lookups by `&str` don't allocate.
+///
+/// Implemented for every hasher that hashes through std [`Hash`], which
+/// includes all the hashers in this module.
+pub trait StrHasher:
+    StoreHasher<String, Wide: KeyHasher<str, H = u128>> + KeyHasher<str, H = u64>
+{
+}
+
+impl<H> StrHasher for H where
+    H: StoreHasher<String, Wide: KeyHasher<str, H = u128>> + KeyHasher<str, H = u64>
+{
+}
+
+/// Hash function family recorded in the file header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HasherKind {
+    /// GxHash; needs AES instructions, values differ between x86 and ARM.
+    Gx,
+    /// XXH3; same values on every platform.
+    Xxh3,
+    /// SipHash-1-3; same values on every platform, no special instructions.
+    Portable,
+    /// Multiply-xor integer hash; same values on every platform.
+    StrongerInt,
+}
+
+impl HasherKind {
+    /// Platform family whose hash values this hasher reproduces.
+    pub fn platform(self) -> HashPlatform {
+        match self {
+            HasherKind::Gx => HashPlatform::current(),
+            _ => HashPlatform::Any,
+        }
+    }
+
+    /// Whether this CPU has the instructions the hasher needs.
+    pub fn is_available(self) -> bool {
+        match self {
+            HasherKind::Gx => gx_available(),
+            _ => true,
+        }
+    }
+
+    /// Check that hashes recorded as `self` on `platform` can be reproduced
+    /// by hasher `current` on this machine.
+    pub(crate) fn check_reproducible(
+        self,
+        platform: HashPlatform,
+        current: HasherKind,
+    ) -> Result<(), KvError> {
+        self.check_reproducible_on(platform, current, current.is_available())
+    }
+
+    /// [`check_reproducible`](Self::check_reproducible) on a machine where
+    /// `current` is `available` or not.
+    fn check_reproducible_on(
+        self,
+        platform: HashPlatform,
+        current: HasherKind,
+        available: bool,
+    ) -> Result<(), KvError> {
+        if self != current {
+            return Err(KvError::IncompatibleHasher(format!(
+                "file was built with {}, but the store type uses {}",
+                self, current
+            )));
+        }
+        if platform != current.platform() {
+            return Err(KvError::IncompatibleHasher(format!(
+                "file was built with {} on {:?}, which produces different hashes than {:?}",
+                self,
+                platform,
+                current.platform()
+            )));
+        }
+        if !available {
+            return Err(current.unavailable());
+        }
+        Ok(())
+    }
+
+    /// Error for a hasher this build or CPU can't run.
+    pub(crate) fn unavailable(self) -> KvError {
+        KvError::IncompatibleHasher(format!("{} is not supported by this build or CPU", self))
+    }
+}
+
+impl fmt::Display for HasherKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HasherKind::Gx => "GxHash",
+            HasherKind::Xxh3 => "XXH3",
+            HasherKind::Portable => "SipHash-1-3",
+            HasherKind::StrongerInt => "StrongerIntHash",
+        })
+    }
+}
+
+/// Platform family a hasher's output depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashPlatform {
+    /// Output is the same everywhere.
+    Any,
+    /// x86 / x86_64 with AES-NI.
+    X86,
+    /// aarch64 with AES + NEON.
+    Arm,
+}
+
+impl HashPlatform {
+    /// The platform family of this machine.
+    pub fn current() -> HashPlatform {
+        if cfg!(target_arch = "aarch64") {
+            HashPlatform::Arm
+        } else {
+            HashPlatform::X86
+        }
+    }
+}
+
+#[cfg(all(gxhash, any(target_arch = "x86", target_arch = "x86_64")))]
+fn gx_available() -> bool {
+    std::arch::is_x86_feature_detected!("aes") && std::arch::is_x86_feature_detected!("sse2")
+}
+
+#[cfg(all(gxhash, target_arch = "aarch64"))]
+fn gx_available() -> bool {
+    std::arch::is_aarch64_feature_detected!("aes")
+        && std::arch::is_aarch64_feature_detected!("neon")
+}
+
+#[cfg(not(gxhash))]
+fn gx_available() -> bool {
+    false
+}
+
+#[cfg(gxhash)]
+pub use gx::{GxHash, GxHash128};
+
+#[cfg(gxhash)]
+mod gx {
+    use super::{HasherKind, KeyHasher, StoreHasher};
+    use std::hash::{Hash, Hasher};
+
+    /// GxHash (64-bit), seeded. AES-NI accelerated; the fastest choice for strings.
+    #[derive(Clone)]
+    pub struct GxHash;
+
+    /// GxHash (128-bit), seeded.
+    #[derive(Clone)]
+    pub struct GxHash128;
+
+    impl<K: Hash + ?Sized> KeyHasher<K> for GxHash {
+        type H = u64;
+        #[inline(always)]
+        fn hash(x: &K, seed: u64) -> u64 {
+            let mut hasher = gxhash::GxHasher::with_seed(seed as i64);
+            x.hash(&mut hasher);
+            hasher.finish()
+        }
+    }
+
+    impl<K: Hash + ?Sized> KeyHasher<K> for GxHash128 {
+        type H = u128;
+        #[inline(always)]
+        fn hash(x: &K, seed: u64) -> u128 {
+            let mut hasher = gxhash::GxHasher::with_seed(seed as i64);
+            x.hash(&mut hasher);
+            hasher.finish_u128()
+        }
+    }
+
+    impl<K: Hash + ?Sized> StoreHasher<K> for GxHash {
+        type Wide = GxHash128;
+        const KIND: HasherKind = HasherKind::Gx;
+    }
+}
+
+/// XXH3 (64-bit), seeded. Platform independent.
+#[derive(Clone)]
+pub struct Xxh3Hash;
+
+/// XXH3 (128-bit), seeded. Platform independent.
+#[derive(Clone)]
+pub struct Xxh3Hash128;
+
+impl<K: Hash + ?Sized> KeyHasher<K> for Xxh3Hash {
+    type H = u64;
+    #[inline(always)]
+    fn hash(x: &K, seed: u64) -> u64 {
+        let mut hasher = xxhash_rust::xxh3::Xxh3::with_seed(seed);
+        x.hash(&mut hasher);
+        hasher.digest()
+    }
+}
+
+impl<K: Hash + ?Sized> KeyHasher<K> for Xxh3Hash128 {
+    type H = u128;
+    #[inline(always)]
+    fn hash(x: &K, seed: u64) -> u128 {
+        let mut hasher = xxhash_rust::xxh3::Xxh3::with_seed(seed);
+        x.hash(&mut hasher);
+        hasher.digest128()
+    }
+}
+
+impl<K: Hash + ?Sized> StoreHasher<K> for Xxh3Hash {
+    type Wide = Xxh3Hash128;
+    const KIND: HasherKind = HasherKind::Xxh3;
+}
+
+/// Second SipHash key; the first one is the MPHF seed.
+const SIP_KEY1: u64 = 0x6c6561726e65646b; // "learnedk"
+
+/// SipHash-1-3 (64-bit), seeded. Pure software, runs anywhere.
+#[derive(Clone)]
+pub struct PortableHash;
+
+/// SipHash-1-3 (128-bit), seeded. Pure software, runs anywhere.
+#[derive(Clone)]
+pub struct PortableHash128;
+
+impl<K: Hash + ?Sized> KeyHasher<K> for PortableHash {
+    type H = u64;
+    #[inline(always)]
+    fn hash(x: &K, seed: u64) -> u64 {
+        let mut hasher = siphasher::sip::SipHasher13::new_with_keys(seed, SIP_KEY1);
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl<K: Hash + ?Sized> KeyHasher<K> for PortableHash128 {
+    type H = u128;
+    #[inline(always)]
+    fn hash(x: &K, seed: u64) -> u128 {
+        let mut hasher = siphasher::sip128::SipHasher13::new_with_keys(seed, SIP_KEY1);
+        x.hash(&mut hasher);
+        siphasher::sip128::Hasher128::finish128(&hasher).as_u128()
+    }
+}
+
+impl<K: Hash + ?Sized> StoreHasher<K> for PortableHash {
+    type Wide = PortableHash128;
+    const KIND: HasherKind = HasherKind::Portable;
+}
+
+/// Draw a fresh secret hash seed from OS randomness.
+///
+/// Stores built without an explicit seed use one of these, so that key hashes
+/// can't be predicted by whoever supplies the keys.
+pub fn random_hash_seed() -> Result<u64, KvError> {
+    getrandom::u64().map_err(|err| KvError::IoError(std::io::Error::other(err)))
+}
+
+/// Width of the key hashes fed to the MPHF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashWidth {
+    /// 64-bit up to [`WIDE_HASH_THRESHOLD`] keys, 128-bit above.
+    #[default]
+    Auto,
+    /// Always use the 64-bit hasher.
+    Bits64,
+    /// Always use the 128-bit hasher ([`StoreHasher::Wide`]).
+    Bits128,
+}
+
+impl HashWidth {
+    /// Resolve `Auto` for a store of `n` keys. Never returns `Auto`.
+    pub fn resolve(self, n: usize) -> HashWidth {
+        match self {
+            HashWidth::Auto if n > WIDE_HASH_THRESHOLD => HashWidth::Bits128,
+            HashWidth::Auto => HashWidth::Bits64,
+            width => width,
+        }
+    }
+}
+
+/// A type that can be used as a key in a `VerifiedKvStore`.
+pub trait KvKey: Clone + Eq + Hash + Send + Sync {
+    /// Hasher used when the store does not specify one.
+    type DefaultHasher: StoreHasher<Self>;
+}
+
+/// The default hasher for key type `K`.
+pub type DefaultHasherFor<K> = <K as KvKey>::DefaultHasher;
+
+/// Default hasher of `String` and `Vec<u8>` keys.
+#[cfg(gxhash)]
+type BytesHasher = GxHash;
+#[cfg(not(gxhash))]
+type BytesHasher = Xxh3Hash;
+
+impl KvKey for String {
+    type DefaultHasher = BytesHasher;
+}
+
+impl KvKey for Vec<u8> {
+    type DefaultHasher = BytesHasher;
+}
+
+macro_rules! int_keys {
+    ($($t:ty),*) => {
+        $(
+            impl KvKey for $t {
+                type DefaultHasher = StrongerIntHash;
+            }
+
+            impl StoreHasher<$t> for StrongerIntHash {
+                type Wide = Xxh3Hash128;
+                const KIND: HasherKind = HasherKind::StrongerInt;
+            }
+        )*
+    };
+}
+int_keys!(
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    isize,
+    (u32, u32),
+    (u64, u64)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_width_resolve() {
+        assert_eq!(HashWidth::Auto.resolve(1000), HashWidth::Bits64);
+        assert_eq!(
+            HashWidth::Auto.resolve(WIDE_HASH_THRESHOLD),
+            HashWidth::Bits64
+        );
+        assert_eq!(
+            HashWidth::Auto.resolve(WIDE_HASH_THRESHOLD + 1),
+            HashWidth::Bits128
+        );
+        assert_eq!(HashWidth::Bits128.resolve(10), HashWidth::Bits128);
+        assert_eq!(HashWidth::Bits64.resolve(usize::MAX), HashWidth::Bits64);
+    }
+
+    #[test]
+    fn test_hashers_use_seed() {
+        let key = "some key";
+        #[cfg(gxhash)]
+        assert_ne!(
+            <GxHash as KeyHasher<str>>::hash(key, 1),
+            <GxHash as KeyHasher<str>>::hash(key, 2)
+        );
+        assert_ne!(
+            <Xxh3Hash as KeyHasher<str>>::hash(key, 1),
+            <Xxh3Hash as KeyHasher<str>>::hash(key, 2)
+        );
+        assert_ne!(
+            <PortableHash as KeyHasher<str>>::hash(key, 1),
+            <PortableHash as KeyHasher<str>>::hash(key, 2)
+        );
+    }
+
+    #[test]
+    fn test_portable_hash_is_stable() {
+        // Pinned values: these must never change, or stored files stop loading.
+        assert_eq!(
+            <PortableHash as KeyHasher<str>>::hash("learned-kv", 0),
+            0xb3aad8b83a1c025a
+        );
+        assert_eq!(
+            <Xxh3Hash as KeyHasher<u64>>::hash(&42, 7),
+            0xb117e48ca46dc725
+        );
+    }
+
+    #[test]
+    fn test_check_reproducible() {
+        let gx = HasherKind::Gx;
+        assert!(gx.check_reproducible_on(gx.platform(), gx, true).is_ok());
+        assert!(HasherKind::Portable
+            .check_reproducible(HashPlatform::Any, HasherKind::Portable)
+            .is_ok());
+
+        let other = match HashPlatform::current() {
+            HashPlatform::Arm => HashPlatform::X86,
+            _ => HashPlatform::Arm,
+        };
+        assert!(matches!(
+            gx.check_reproducible(other, gx),
+            Err(KvError::IncompatibleHasher(_))
+        ));
+        assert!(matches!(
+            gx.check_reproducible(gx.platform(), HasherKind::Xxh3),
+            Err(KvError::IncompatibleHasher(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_reproducible_without_aes() {
+        // A GxHash file moved to a host of the same family that lacks AES
+        let gx = HasherKind::Gx;
+        assert!(matches!(
+            gx.check_reproducible_on(gx.platform(), gx, false),
+            Err(KvError::IncompatibleHasher(message)) if message.contains("not supported")
+        ));
+        assert_eq!(
+            gx.check_reproducible(gx.platform(), gx).is_ok(),
+            cfg!(gxhash) && gx.is_available()
+        );
+    }
+}
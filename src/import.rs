@@ -0,0 +1,520 @@
+//! Build stores from CSV, TSV or newline-delimited JSON input.
+//!
+//! Records are streamed one at a time into a [`VerifiedKvStoreBuilder`]; the
+//! input is never held in memory as a whole.
+//!
+//! Keys and values are parsed with serde:
+//! - CSV/TSV cells are first read as plain strings, then as JSON, so `42`
+//!   becomes a `u64` for a `u64` column and stays `"42"` for a `String` one.
+//! - NDJSON fields are deserialized from their JSON value; a value that doesn't
+//!   fit the target type is retried as its JSON text (so any field can be
+//!   imported into a `String`).
+//!
+//! Bad lines are reported as [`KvError::InvalidInput`] with their 1-based line
+//! number; column settings that can't apply to the format or input fail with
+//! [`KvError::InvalidImportSettings`].
+//!
+//! # Example
+//!
+//! ```
+//! use learned_kv::import::{Format, Importer};
+//! use learned_kv::{VerifiedKvStore, VerifiedKvStoreBuilder};
+//!
+//! let input = "id,name,score\n1,alice,90\n2,bob,85\n";
+//! let store: VerifiedKvStore<u64, u32> = Importer::new(Format::Csv)
+//!     .has_header(true)
+//!     .value_column("score")
+//!     .read(input.as_bytes(), VerifiedKvStoreBuilder::new())
+//!     .unwrap()
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(store.get(&2).unwrap(), &85);
+//! ```
+
+use crate::error::KvError;
+use crate::hasher::{KvKey, StoreHasher};
+use crate::verified_kv_store::{VerifiedKvStore, VerifiedKvStoreBuilder};
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Input format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Comma-separated values (RFC 4180 quoting).
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl Format {
+    /// Guess the format from a file extension (`csv`, `tsv`, `ndjson`/`jsonl`).
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            "tsv" | "tab" => Some(Format::Tsv),
+            "ndjson" | "jsonl" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// Where to find the key or value in a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Column {
+    /// 0-based column position (CSV/TSV only).
+    Index(usize),
+    /// Header name (CSV/TSV with a header row) or NDJSON field name.
+    Name(String),
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Self {
+        Column::Index(index)
+    }
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Self {
+        Column::Name(name.to_string())
+    }
+}
+
+impl From<String> for Column {
+    fn from(name: String) -> Self {
+        Column::Name(name)
+    }
+}
+
+/// What to do when a key appears more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Fail with `KvError::InvalidInput` on the repeated line.
+    #[default]
+    Error,
+    /// Keep the value from the first occurrence.
+    KeepFirst,
+    /// Keep the value from the last occurrence.
+    KeepLast,
+}
+
+/// Reads records from an input and feeds them to a builder.
+#[derive(Debug, Clone)]
+pub struct Importer {
+    format: Format,
+    key_column: Column,
+    value_column: Column,
+    has_header: bool,
+    duplicates: DuplicatePolicy,
+}
+
+impl Importer {
+    /// Importer for `format`. Keys and values default to columns 0 and 1 for
+    /// CSV/TSV and to the fields `"key"` and `"value"` for NDJSON.
+    pub fn new(format: Format) -> Self {
+        let (key_column, value_column) = match format {
+            Format::Ndjson => (Column::from("key"), Column::from("value")),
+            Format::Csv | Format::Tsv => (Column::Index(0), Column::Index(1)),
+        };
+        Self {
+            format,
+            key_column,
+            value_column,
+            has_header: false,
+            duplicates: DuplicatePolicy::Error,
+        }
+    }
+
+    /// Column (or NDJSON field) holding the key.
+    pub fn key_column(mut self, column: impl Into<Column>) -> Self {
+        self.key_column = column.into();
+        self
+    }
+
+    /// Column (or NDJSON field) holding the value.
+    pub fn value_column(mut self, column: impl Into<Column>) -> Self {
+        self.value_column = column.into();
+        self
+    }
+
+    /// Treat the first CSV/TSV row as column names (default `false`).
+    pub fn has_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    /// How to handle repeated keys (default [`DuplicatePolicy::Error`]).
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Read every record from `reader` into `builder`.
+    pub fn read<K, V, H, R>(
+        &self,
+        reader: R,
+        builder: VerifiedKvStoreBuilder<K, V, H>,
+    ) -> Result<VerifiedKvStoreBuilder<K, V, H>, KvError>
+    where
+        K: KvKey + DeserializeOwned,
+        V: Clone + DeserializeOwned,
+        H: StoreHasher<K>,
+        R: Read,
+    {
+        match self.format {
+            Format::Csv => self.read_delimited(reader, b',', builder),
+            Format::Tsv => self.read_delimited(reader, b'\t', builder),
+            Format::Ndjson => self.read_ndjson(reader, builder),
+        }
+    }
+
+    /// Build a store from the file at `path` with the default builder settings.
+    pub fn build_file<K, V, H, P>(&self, path: P) -> Result<VerifiedKvStore<K, V, H>, KvError>
+    where
+        K: KvKey + DeserializeOwned,
+        V: Clone + DeserializeOwned,
+        H: StoreHasher<K>,
+        P: AsRef<Path>,
+    {
+        let file = File::open(path)?;
+        self.read(BufReader::new(file), VerifiedKvStoreBuilder::new())?
+            .build()
+    }
+
+    fn read_delimited<K, V, H, R>(
+        &self,
+        reader: R,
+        delimiter: u8,
+        mut builder: VerifiedKvStoreBuilder<K, V, H>,
+    ) -> Result<VerifiedKvStoreBuilder<K, V, H>, KvError>
+    where
+        K: KvKey + DeserializeOwned,
+        V: Clone + DeserializeOwned,
+        H: StoreHasher<K>,
+        R: Read,
+    {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(self.has_header)
+            .flexible(true)
+            .from_reader(reader);
+
+        let headers = if self.has_header {
+            Some(reader.headers().map_err(|e| csv_error(1, e))?.clone())
+        } else {
+            None
+        };
+        let key_index = column_index(&self.key_column, headers.as_ref())?;
+        let value_index = column_index(&self.value_column, headers.as_ref())?;
+
+        let mut record = csv::StringRecord::new();
+        loop {
+            let line = reader.position().line();
+            match reader.read_record(&mut record) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => return Err(csv_error(line, e)),
+            }
+            let line = record.position().map_or(line, |p| p.line());
+
+            let field = |index: usize| {
+                record.get(index).ok_or_else(|| KvError::InvalidInput {
+                    line,
+                    message: format!("missing column {}", index),
+                })
+            };
+            let key: K = parse_cell(field(key_index)?).map_err(|e| invalid(line, "key", e))?;
+            let value: V =
+                parse_cell(field(value_index)?).map_err(|e| invalid(line, "value", e))?;
+            builder = self.insert(builder, key, value, line)?;
+        }
+
+        Ok(builder)
+    }
+
+    fn read_ndjson<K, V, H, R>(
+        &self,
+        reader: R,
+        mut builder: VerifiedKvStoreBuilder<K, V, H>,
+    ) -> Result<VerifiedKvStoreBuilder<K, V, H>, KvError>
+    where
+        K: KvKey + DeserializeOwned,
+        V: Clone + DeserializeOwned,
+        H: StoreHasher<K>,
+        R: Read,
+    {
+        let (Column::Name(key_field), Column::Name(value_field)) =
+            (&self.key_column, &self.value_column)
+        else {
+            return Err(KvError::InvalidImportSettings(
+                "NDJSON columns must be field names".to_string(),
+            ));
+        };
+
+        let mut text = String::new();
+        let mut reader = BufReader::new(reader);
+        let mut line = 0;
+        loop {
+            text.clear();
+            if reader.read_line(&mut text)? == 0 {
+                break;
+            }
+            line += 1;
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let mut object: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(&text).map_err(|e| invalid(line, "record", e))?;
+            let mut take = |name: &str| {
+                object.remove(name).ok_or_else(|| KvError::InvalidInput {
+                    line,
+                    message: format!("missing field \"{}\"", name),
+                })
+            };
+            let key: K = parse_json(take(key_field)?).map_err(|e| invalid(line, "key", e))?;
+            let value: V = parse_json(take(value_field)?).map_err(|e| invalid(line, "value", e))?;
+            builder = self.insert(builder, key, value, line)?;
+        }
+
+        Ok(builder)
+    }
+
+    fn insert<K, V, H>(
+        &self,
+        builder: VerifiedKvStoreBuilder<K, V, H>,
+        key: K,
+        value: V,
+        line: u64,
+    ) -> Result<VerifiedKvStoreBuilder<K, V, H>, KvError>
+    where
+        K: KvKey,
+        V: Clone,
+        H: StoreHasher<K>,
+    {
+        if !builder.contains_key(&key) {
+            return Ok(builder.insert(key, value));
+        }
+        match self.duplicates {
+            DuplicatePolicy::Error => Err(KvError::InvalidInput {
+                line,
+                message: "duplicate key".to_string(),
+            }),
+            DuplicatePolicy::KeepFirst => Ok(builder),
+            DuplicatePolicy::KeepLast => Ok(builder.insert(key, value)),
+        }
+    }
+}
+
+/// Resolve a column to its position, looking names up in the header row.
+fn column_index(column: &Column, headers: Option<&csv::StringRecord>) -> Result<usize, KvError> {
+    match (column, headers) {
+        (Column::Index(index), _) => Ok(*index),
+        (Column::Name(name), Some(headers)) => {
+            headers
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| KvError::InvalidInput {
+                    line: 1,
+                    message: format!("no column named \"{}\" in header", name),
+                })
+        }
+        (Column::Name(name), None) => Err(KvError::InvalidImportSettings(format!(
+            "column \"{}\" given by name, but the input has no header",
+            name
+        ))),
+    }
+}
+
+/// Parse a CSV cell as a plain string first, then as JSON.
+fn parse_cell<T: DeserializeOwned>(cell: &str) -> Result<T, serde_json::Error> {
+    let as_str: StrDeserializer<'_, ValueError> = cell.into_deserializer();
+    T::deserialize(as_str).or_else(|_| serde_json::from_str(cell))
+}
+
+/// Parse a JSON field, falling back to its JSON text.
+fn parse_json<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, serde_json::Error> {
+    match T::deserialize(&value) {
+        Ok(parsed) => Ok(parsed),
+        Err(e) if value.is_string() => Err(e),
+        Err(e) => {
+            let text = value.to_string();
+            let as_str: StrDeserializer<'_, ValueError> = text.as_str().into_deserializer();
+            T::deserialize(as_str).map_err(|_| e)
+        }
+    }
+}
+
+fn invalid(line: u64, what: &str, err: impl std::fmt::Display) -> KvError {
+    KvError::InvalidInput {
+        line,
+        message: format!("invalid {}: {}", what, err),
+    }
+}
+
+fn csv_error(line: u64, err: csv::Error) -> KvError {
+    let line = err.position().map_or(line, |p| p.line());
+    KvError::InvalidInput {
+        line,
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import<K, V>(importer: Importer, input: &str) -> Result<VerifiedKvStore<K, V>, KvError>
+    where
+        K: KvKey + DeserializeOwned,
+        V: Clone + DeserializeOwned,
+    {
+        importer
+            .read(input.as_bytes(), VerifiedKvStoreBuilder::new())?
+            .build()
+    }
+
+    #[test]
+    fn test_csv_default_columns() {
+        let store: VerifiedKvStore<String, String> =
+            import(Importer::new(Format::Csv), "a,1\nb,\"x, y\"\n").unwrap();
+        assert_eq!(store.get(&"a".to_string()).unwrap(), "1");
+        assert_eq!(store.get(&"b".to_string()).unwrap(), "x, y");
+    }
+
+    #[test]
+    fn test_tsv_named_columns_typed_values() {
+        let input = "name\tid\tscore\nalice\t1\t90\nbob\t2\t85\n";
+        let importer = Importer::new(Format::Tsv)
+            .has_header(true)
+            .key_column("id")
+            .value_column("score");
+        let store: VerifiedKvStore<u64, u32> = import(importer, input).unwrap();
+        assert_eq!(store.get(&1).unwrap(), &90);
+        assert_eq!(store.get(&2).unwrap(), &85);
+    }
+
+    #[test]
+    fn test_ndjson_values() {
+        #[derive(Clone, Debug, PartialEq, serde::Deserialize)]
+        struct User {
+            name: String,
+            age: u32,
+        }
+
+        let input = "{\"key\": 1, \"value\": {\"name\": \"alice\", \"age\": 30}}\n\n\
+                     {\"key\": 2, \"value\": {\"name\": \"bob\", \"age\": 25}}\n";
+        let store: VerifiedKvStore<u64, User> =
+            import(Importer::new(Format::Ndjson), input).unwrap();
+        assert_eq!(
+            store.get(&2).unwrap(),
+            &User {
+                name: "bob".to_string(),
+                age: 25
+            }
+        );
+    }
+
+    #[test]
+    fn test_ndjson_non_string_into_string() {
+        let input = "{\"key\": \"a\", \"value\": {\"n\": 1}}\n{\"key\": \"b\", \"value\": 7}\n";
+        let store: VerifiedKvStore<String, String> =
+            import(Importer::new(Format::Ndjson), input).unwrap();
+        assert_eq!(store.get(&"a".to_string()).unwrap(), "{\"n\":1}");
+        assert_eq!(store.get(&"b".to_string()).unwrap(), "7");
+    }
+
+    #[test]
+    fn test_bad_lines_have_line_numbers() {
+        let err = import::<u64, u32>(Importer::new(Format::Csv), "1,2\n3,x\n")
+            .err()
+            .expect("import should fail");
+        assert!(
+            matches!(err, KvError::InvalidInput { line: 2, .. }),
+            "{}",
+            err
+        );
+
+        let err = import::<u64, u32>(Importer::new(Format::Csv), "1,2\n3\n")
+            .err()
+            .expect("import should fail");
+        assert!(
+            matches!(err, KvError::InvalidInput { line: 2, .. }),
+            "{}",
+            err
+        );
+
+        let input = "{\"key\": 1, \"value\": 2}\n\n{\"key\": 3\n";
+        let err = import::<u64, u32>(Importer::new(Format::Ndjson), input)
+            .err()
+            .expect("import should fail");
+        assert!(
+            matches!(err, KvError::InvalidInput { line: 3, .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_duplicate_policies() {
+        let input = "a,1\nb,2\na,3\n";
+
+        let err = import::<String, u32>(Importer::new(Format::Csv), input)
+            .err()
+            .expect("import should fail");
+        assert!(
+            matches!(err, KvError::InvalidInput { line: 3, .. }),
+            "{}",
+            err
+        );
+
+        let first: VerifiedKvStore<String, u32> = import(
+            Importer::new(Format::Csv).duplicates(DuplicatePolicy::KeepFirst),
+            input,
+        )
+        .unwrap();
+        assert_eq!(first.get(&"a".to_string()).unwrap(), &1);
+
+        let last: VerifiedKvStore<String, u32> = import(
+            Importer::new(Format::Csv).duplicates(DuplicatePolicy::KeepLast),
+            input,
+        )
+        .unwrap();
+        assert_eq!(last.get(&"a".to_string()).unwrap(), &3);
+    }
+
+    #[test]
+    fn test_unknown_column_name() {
+        let importer = Importer::new(Format::Csv)
+            .has_header(true)
+            .value_column("missing");
+        let err = import::<String, String>(importer, "k,v\na,1\n")
+            .err()
+            .expect("import should fail");
+        assert!(
+            matches!(err, KvError::InvalidInput { line: 1, .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_column_settings_rejected() {
+        let by_name = Importer::new(Format::Csv).value_column("v");
+        let err = import::<String, String>(by_name, "a,1\n")
+            .err()
+            .expect("import should fail");
+        assert!(matches!(err, KvError::InvalidImportSettings(_)), "{}", err);
+
+        let by_index = Importer::new(Format::Ndjson).key_column(0);
+        let err = import::<String, String>(by_index, "{\"key\":\"a\"}\n")
+            .err()
+            .expect("import should fail");
+        assert!(matches!(err, KvError::InvalidImportSettings(_)), "{}", err);
+    }
+}
@@ -1,10 +1,10 @@
 //! # VerifiedKvStore
 //!
-//! A high-performance **String key-value store** using Minimal Perfect Hash Functions (MPHF).
+//! A high-performance **key-value store** using Minimal Perfect Hash Functions (MPHF).
 //!
 //! ## Features
 //!
-//! - **String keys only** - Simplified, no type confusion
+//! - **Generic keys** - `String`, integers and integer pairs, each with a matching hasher
 //! - **O(1) lookups** - 5-300ns depending on key size
 //! - **Safe verification** - Returns errors for missing keys, never wrong values
 //! - **GxHash** - AES-NI accelerated, handles all string patterns
@@ -40,18 +40,60 @@
 //!
 //! // Serialize
 //! store.save_to_file("data.bin").unwrap();
-//! let loaded: VerifiedKvStore<String> = VerifiedKvStore::load_from_file("data.bin").unwrap();
+//! let loaded: VerifiedKvStore<String, String> = VerifiedKvStore::load_from_file("data.bin").unwrap();
 //! # std::fs::remove_file("data.bin").ok();
 //! ```
+//!
+//! ## Non-string keys
+//!
+//! Integer keys skip string hashing entirely:
+//!
+//! ```rust
+//! use learned_kv::VerifiedKvStoreBuilder;
+//!
+//! let store = VerifiedKvStoreBuilder::<u64, &str>::new()
+//!     .insert(1001, "alice")
+//!     .insert(1002, "bob")
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(store.get(&1002).unwrap(), &"bob");
+//! ```
 
+pub mod byte_store;
+#[cfg(feature = "compression")]
+pub mod compressed_store;
+pub mod dedup_store;
 pub mod error;
+#[cfg(feature = "import")]
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod front_coded;
+pub mod hasher;
+#[cfg(feature = "import")]
+pub mod import;
+pub mod normalize;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod table;
 pub mod verified_kv_store;
 
-// Persistence is internal implementation detail
+mod mphf;
+mod prefilter;
+#[cfg(all(test, any(feature = "ffi", feature = "server")))]
+mod test_util;
+// The file format types are re-exported below; the module holds the
+// reader and writer internals
 mod persistence;
 
 pub use error::KvError;
-pub use verified_kv_store::{VerifiedKvStore, VerifiedKvStoreBuilder};
+pub use hasher::{DefaultHasherFor, HashPlatform, HashWidth, HasherKind, KvKey};
+pub use persistence::{
+    inspect_file, inspect_reader, AtomicWriter, Compression, FileInfo, PersistenceStrategy,
+    SectionInfo, StoreMetadata, WriteOptions,
+};
+pub use prefilter::PrefilterStats;
+pub use verified_kv_store::{HashedKey, SortedIter, VerifiedKvStore, VerifiedKvStoreBuilder};
 
 #[cfg(test)]
 mod tests {
@@ -87,7 +129,7 @@ mod tests {
 
     #[test]
     fn test_builder_pattern() {
-        let store: VerifiedKvStore<String> = VerifiedKvStoreBuilder::new()
+        let store: VerifiedKvStore<String, String> = VerifiedKvStoreBuilder::new()
             .insert("hello".to_string(), "world".to_string())
             .insert("foo".to_string(), "bar".to_string())
             .build()
@@ -100,7 +142,7 @@ mod tests {
 
     #[test]
     fn test_values_iterator() {
-        let store: VerifiedKvStore<String> = VerifiedKvStoreBuilder::new()
+        let store: VerifiedKvStore<String, String> = VerifiedKvStoreBuilder::new()
             .insert("1".to_string(), "one".to_string())
             .insert("2".to_string(), "two".to_string())
             .insert("3".to_string(), "three".to_string())
@@ -117,7 +159,7 @@ mod tests {
 
     #[test]
     fn test_serialization() {
-        let store: VerifiedKvStore<String> = VerifiedKvStoreBuilder::new()
+        let store: VerifiedKvStore<String, String> = VerifiedKvStoreBuilder::new()
             .insert("test".to_string(), "data".to_string())
             .insert("more".to_string(), "info".to_string())
             .build()
@@ -129,7 +171,8 @@ mod tests {
         assert!(store.save_to_file(test_file).is_ok());
 
         // Load should succeed
-        let loaded: VerifiedKvStore<String> = VerifiedKvStore::load_from_file(test_file).unwrap();
+        let loaded: VerifiedKvStore<String, String> =
+            VerifiedKvStore::load_from_file(test_file).unwrap();
 
         assert_eq!(loaded.len(), 2);
         assert_eq!(loaded.get(&"test".to_string()).unwrap(), "data");
@@ -160,7 +203,7 @@ mod tests {
 
     #[test]
     fn test_memory_usage() {
-        let store: VerifiedKvStore<String> = VerifiedKvStoreBuilder::new()
+        let store: VerifiedKvStore<String, String> = VerifiedKvStoreBuilder::new()
             .insert("test".to_string(), "data".to_string())
             .build()
             .unwrap();
@@ -310,7 +353,8 @@ mod tests {
 
     #[test]
     fn test_empty_builder() {
-        let result: Result<VerifiedKvStore<String>, _> = VerifiedKvStoreBuilder::new().build();
+        let result: Result<VerifiedKvStore<String, String>, _> =
+            VerifiedKvStoreBuilder::new().build();
         assert!(result.is_err());
         assert!(matches!(result, Err(KvError::EmptyKeySet)));
     }
@@ -319,7 +363,7 @@ mod tests {
 
     #[test]
     fn test_keys_iterator() {
-        let store: VerifiedKvStore<i32> = VerifiedKvStoreBuilder::new()
+        let store: VerifiedKvStore<String, i32> = VerifiedKvStoreBuilder::new()
             .insert("a".to_string(), 1)
             .insert("b".to_string(), 2)
             .insert("c".to_string(), 3)
@@ -355,7 +399,7 @@ mod tests {
     fn test_builder_extend() {
         let initial_data = vec![("a".to_string(), 1), ("b".to_string(), 2)];
 
-        let store: VerifiedKvStore<i32> = VerifiedKvStoreBuilder::new()
+        let store: VerifiedKvStore<String, i32> = VerifiedKvStoreBuilder::new()
             .extend(initial_data)
             .insert("c".to_string(), 3)
             .build()
@@ -374,7 +418,7 @@ mod tests {
             ("key2".to_string(), "val2".to_string()),
         ];
 
-        let store: VerifiedKvStore<String> =
+        let store: VerifiedKvStore<String, String> =
             VerifiedKvStoreBuilder::with_entries(data).build().unwrap();
 
         assert_eq!(store.len(), 2);
@@ -383,7 +427,7 @@ mod tests {
 
     #[test]
     fn test_builder_default() {
-        let builder: VerifiedKvStoreBuilder<String> = Default::default();
+        let builder: VerifiedKvStoreBuilder<String, String> = Default::default();
         let result = builder.build();
         assert!(result.is_err());
     }
@@ -412,19 +456,19 @@ mod tests {
     #[test]
     fn test_different_value_types() {
         // Test with various value types
-        let int_store: VerifiedKvStore<i32> = VerifiedKvStoreBuilder::new()
+        let int_store: VerifiedKvStore<String, i32> = VerifiedKvStoreBuilder::new()
             .insert("one".to_string(), 1)
             .build()
             .unwrap();
         assert_eq!(int_store.get(&"one".to_string()).unwrap(), &1);
 
-        let vec_store: VerifiedKvStore<Vec<u8>> = VerifiedKvStoreBuilder::new()
+        let vec_store: VerifiedKvStore<String, Vec<u8>> = VerifiedKvStoreBuilder::new()
             .insert("bytes".to_string(), vec![1, 2, 3])
             .build()
             .unwrap();
         assert_eq!(vec_store.get(&"bytes".to_string()).unwrap(), &vec![1, 2, 3]);
 
-        let option_store: VerifiedKvStore<Option<String>> = VerifiedKvStoreBuilder::new()
+        let option_store: VerifiedKvStore<String, Option<String>> = VerifiedKvStoreBuilder::new()
             .insert("some".to_string(), Some("value".to_string()))
             .insert("none".to_string(), None)
             .build()
@@ -436,6 +480,403 @@ mod tests {
         assert_eq!(option_store.get(&"none".to_string()).unwrap(), &None);
     }
 
+    // === Key Type Tests ===
+
+    #[test]
+    fn test_integer_keys() {
+        let mut data = HashMap::new();
+        for i in 0..1000u64 {
+            data.insert(i, format!("value_{}", i));
+        }
+
+        let store = VerifiedKvStore::new(data).unwrap();
+
+        for i in 0..1000u64 {
+            assert_eq!(store.get(&i).unwrap(), &format!("value_{}", i));
+        }
+        assert!(matches!(store.get(&1000), Err(KvError::KeyNotFoundFast)));
+    }
+
+    #[test]
+    fn test_pair_keys() {
+        let mut data = HashMap::new();
+        for a in 0..50u32 {
+            for b in 0..50u32 {
+                data.insert((a, b), a * 100 + b);
+            }
+        }
+
+        let store = VerifiedKvStore::new(data).unwrap();
+
+        assert_eq!(store.len(), 2500);
+        assert_eq!(store.get(&(12, 34)).unwrap(), &1234);
+        assert_eq!(store.get(&(34, 12)).unwrap(), &3412);
+        assert!(!store.contains_key(&(50, 0)));
+    }
+
+    #[test]
+    fn test_explicit_hasher() {
+        use crate::hasher::Xxh3Hash;
+
+        let store: VerifiedKvStore<String, i32, Xxh3Hash> = VerifiedKvStoreBuilder::new()
+            .insert("a".to_string(), 1)
+            .insert("b".to_string(), 2)
+            .build()
+            .unwrap();
+
+        assert_eq!(store.get(&"a".to_string()).unwrap(), &1);
+        assert_eq!(store.get(&"b".to_string()).unwrap(), &2);
+        assert!(store.get(&"c".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_integer_keys_roundtrip() {
+        let store: VerifiedKvStore<(u32, u32), String> = VerifiedKvStoreBuilder::new()
+            .insert((1, 2), "one-two".to_string())
+            .insert((2, 1), "two-one".to_string())
+            .build()
+            .unwrap();
+        let test_file = "/tmp/test_integer_keys_roundtrip.bin";
+
+        store.save_to_file(test_file).unwrap();
+        let loaded: VerifiedKvStore<(u32, u32), String> =
+            VerifiedKvStore::load_from_file(test_file).unwrap();
+
+        assert_eq!(loaded.get(&(1, 2)).unwrap(), "one-two");
+        assert_eq!(loaded.get(&(2, 1)).unwrap(), "two-one");
+
+        std::fs::remove_file(test_file).ok();
+    }
+
+    // === Hash Width Tests ===
+
+    #[test]
+    fn test_default_hash_width() {
+        let mut data = HashMap::new();
+        data.insert("a".to_string(), 1);
+        let store = VerifiedKvStore::new(data).unwrap();
+
+        assert_eq!(store.hash_width(), HashWidth::Bits64);
+    }
+
+    #[test]
+    fn test_wide_hashes() {
+        let mut builder = VerifiedKvStoreBuilder::new().hash_width(HashWidth::Bits128);
+        for i in 0..1000 {
+            builder = builder.insert(format!("key_{}", i), i);
+        }
+        let store: VerifiedKvStore<String, i32> = builder.build().unwrap();
+
+        assert_eq!(store.hash_width(), HashWidth::Bits128);
+        for i in 0..1000 {
+            assert_eq!(store.get(&format!("key_{}", i)).unwrap(), &i);
+        }
+        assert!(store.get(&"key_1000".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_hash_width_persisted() {
+        let store: VerifiedKvStore<u64, String> = VerifiedKvStoreBuilder::new()
+            .hash_width(HashWidth::Bits128)
+            .insert(7, "seven".to_string())
+            .insert(8, "eight".to_string())
+            .build()
+            .unwrap();
+        let test_file = "/tmp/test_hash_width_persisted.bin";
+
+        store.save_to_file(test_file).unwrap();
+        let loaded: VerifiedKvStore<u64, String> =
+            VerifiedKvStore::load_from_file(test_file).unwrap();
+
+        assert_eq!(loaded.hash_width(), HashWidth::Bits128);
+        assert_eq!(loaded.get(&7).unwrap(), "seven");
+        assert_eq!(loaded.get(&8).unwrap(), "eight");
+
+        std::fs::remove_file(test_file).ok();
+    }
+
+    #[test]
+    fn test_portable_hasher_roundtrip() {
+        use crate::hasher::PortableHash;
+
+        let mut data = HashMap::new();
+        data.insert("alpha".to_string(), 1);
+        data.insert("beta".to_string(), 2);
+        let store = VerifiedKvStore::<String, i32, PortableHash>::with_hasher(data).unwrap();
+        assert_eq!(store.hasher_kind(), HasherKind::Portable);
+        let test_file = "/tmp/test_portable_hasher_roundtrip.bin";
+
+        store.save_to_file(test_file).unwrap();
+        let loaded: VerifiedKvStore<String, i32, PortableHash> =
+            VerifiedKvStore::load_from_file(test_file).unwrap();
+        assert_eq!(loaded.get(&"alpha".to_string()).unwrap(), &1);
+        assert_eq!(loaded.get(&"beta".to_string()).unwrap(), &2);
+
+        std::fs::remove_file(test_file).ok();
+    }
+
+    #[test]
+    fn test_load_with_wrong_hasher_fails() {
+        use crate::hasher::PortableHash;
+
+        let mut data = HashMap::new();
+        data.insert("alpha".to_string(), 1);
+        let store = VerifiedKvStore::<String, i32, PortableHash>::with_hasher(data).unwrap();
+        let test_file = "/tmp/test_load_with_wrong_hasher.bin";
+        store.save_to_file(test_file).unwrap();
+
+        let result: Result<VerifiedKvStore<String, i32, crate::hasher::Xxh3Hash>, _> =
+            VerifiedKvStore::load_from_file(test_file);
+        assert!(matches!(result, Err(KvError::IncompatibleHasher(_))));
+
+        std::fs::remove_file(test_file).ok();
+    }
+
+    #[test]
+    fn test_random_hash_seed_per_store() {
+        let mut data = HashMap::new();
+        data.insert("a".to_string(), 1);
+        let first = VerifiedKvStore::new(data.clone()).unwrap();
+        let second = VerifiedKvStore::new(data).unwrap();
+
+        // 2^-64 chance of a false failure
+        assert_ne!(first.hash_seed(), second.hash_seed());
+    }
+
+    #[test]
+    fn test_hash_seed_persisted() {
+        let store: VerifiedKvStore<String, i32> = VerifiedKvStoreBuilder::new()
+            .hash_seed(0xdead_beef)
+            .insert("a".to_string(), 1)
+            .insert("b".to_string(), 2)
+            .build()
+            .unwrap();
+        assert_eq!(store.hash_seed(), 0xdead_beef);
+        let test_file = "/tmp/test_hash_seed_persisted.bin";
+
+        store.save_to_file(test_file).unwrap();
+        let loaded: VerifiedKvStore<String, i32> =
+            VerifiedKvStore::load_from_file(test_file).unwrap();
+
+        assert_eq!(loaded.hash_seed(), 0xdead_beef);
+        assert_eq!(loaded.get(&"a".to_string()).unwrap(), &1);
+        assert_eq!(loaded.get(&"b".to_string()).unwrap(), &2);
+
+        std::fs::remove_file(test_file).ok();
+    }
+
+    #[test]
+    fn test_duplicate_hashes_reported() {
+        use crate::hasher::{HasherKind, KeyHasher, StoreHasher};
+
+        // Every key collides, like a crafted adversarial key set would.
+        #[derive(Clone)]
+        struct ConstHash;
+        #[derive(Clone)]
+        struct ConstHash128;
+        impl KeyHasher<String> for ConstHash {
+            type H = u64;
+            fn hash(_: &String, _: u64) -> u64 {
+                0
+            }
+        }
+        impl KeyHasher<String> for ConstHash128 {
+            type H = u128;
+            fn hash(_: &String, _: u64) -> u128 {
+                0
+            }
+        }
+        impl StoreHasher<String> for ConstHash {
+            type Wide = ConstHash128;
+            const KIND: HasherKind = HasherKind::Portable;
+        }
+
+        let mut data = HashMap::new();
+        data.insert("a".to_string(), 1);
+        data.insert("b".to_string(), 2);
+        let result = VerifiedKvStore::<String, i32, ConstHash>::with_hasher(data);
+
+        assert!(matches!(result, Err(KvError::DuplicateHashes)));
+    }
+
+    #[test]
+    fn test_unlucky_hash_seed_retried() {
+        use crate::hasher::{HasherKind, KeyHasher, PortableHash, StoreHasher};
+
+        // Keys collide under one hash seed only, like an unlucky random draw.
+        const UNLUCKY: u64 = 0xbad5eed;
+        #[derive(Clone)]
+        struct UnluckyHash;
+        impl KeyHasher<String> for UnluckyHash {
+            type H = u64;
+            fn hash(key: &String, seed: u64) -> u64 {
+                match seed {
+                    UNLUCKY => 0,
+                    _ => <PortableHash as KeyHasher<String>>::hash(key, seed),
+                }
+            }
+        }
+        impl StoreHasher<String> for UnluckyHash {
+            type Wide = crate::hasher::PortableHash128;
+            const KIND: HasherKind = HasherKind::Portable;
+        }
+
+        let build = |builder: VerifiedKvStoreBuilder<String, i32, UnluckyHash>| {
+            builder
+                .insert("a".to_string(), 1)
+                .insert("b".to_string(), 2)
+                .build()
+        };
+
+        // The hash seed follows the MPHF seed, so it isn't pinned
+        let store = build(VerifiedKvStoreBuilder::new().seed(UNLUCKY)).unwrap();
+        assert_ne!(store.hash_seed(), UNLUCKY);
+        assert_eq!(store.get(&"b".to_string()).unwrap(), &2);
+        let again = build(VerifiedKvStoreBuilder::new().seed(UNLUCKY)).unwrap();
+        assert_eq!(again.hash_seed(), store.hash_seed());
+
+        let pinned = build(VerifiedKvStoreBuilder::new().hash_seed(UNLUCKY));
+        assert!(matches!(pinned, Err(KvError::DuplicateHashes)));
+    }
+
+    #[test]
+    fn test_str_lookups_match_string_lookups() {
+        use crate::hasher::{PortableHash, StrHasher, Xxh3Hash};
+
+        fn check<H: StrHasher>(width: HashWidth) {
+            let store: VerifiedKvStore<String, usize, H> = VerifiedKvStoreBuilder::new()
+                .extend((0..500).map(|i| (format!("key:{}", i), i)))
+                .hash_width(width)
+                .prefilter(0.01)
+                .hash_tags(true)
+                .build()
+                .unwrap();
+            for i in (0..600).step_by(7) {
+                let key = format!("key:{}", i);
+                assert_eq!(store.get_str(&key).ok(), store.get(&key).ok());
+                assert_eq!(store.contains_str(&key), i < 500);
+            }
+        }
+        check::<DefaultHasherFor<String>>(HashWidth::Bits64);
+        check::<PortableHash>(HashWidth::Bits64);
+        check::<Xxh3Hash>(HashWidth::Bits128);
+    }
+
+    /// Build a PortableHash store over `n` entries with a fixed seed and
+    /// return its saved bytes.
+    fn seeded_file_bytes(n: u64, name: &str) -> Vec<u8> {
+        use crate::hasher::PortableHash;
+
+        let store: VerifiedKvStore<String, u64, PortableHash> = VerifiedKvStoreBuilder::new()
+            .seed(42)
+            .extend((0..n).map(|i| (format!("key_{}", i), i)))
+            .build()
+            .unwrap();
+        let test_file = format!("/tmp/{}.bin", name);
+        store.save_to_file(&test_file).unwrap();
+        let bytes = std::fs::read(&test_file).unwrap();
+        std::fs::remove_file(&test_file).ok();
+        bytes
+    }
+
+    #[test]
+    fn test_seeded_build_is_byte_identical() {
+        let first = seeded_file_bytes(1000, "test_seeded_build_first");
+        let second = seeded_file_bytes(1000, "test_seeded_build_second");
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_seeded_build_independent_of_thread_count() {
+        let build_with_threads = |threads: usize| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| {
+                    seeded_file_bytes(200_000, &format!("test_seeded_build_threads_{}", threads))
+                })
+        };
+        assert_eq!(build_with_threads(1), build_with_threads(4));
+    }
+
+    #[test]
+    fn test_seeded_build_pinned() {
+        // Changes whenever the file format or construction changes; bump
+        // FORMAT_VERSION and update the value when that is intended.
+        let bytes = seeded_file_bytes(1000, "test_seeded_build_pinned");
+        assert_eq!(crc32fast::hash(&bytes), 0xab0b88fc);
+    }
+
+    /// A three-entry `String` -> `String` store as saved by format version 1.
+    const VERSION_1_FILE: &[u8] = &[
+        0x4c, 0x45, 0x41, 0x52, 0x4e, 0x4b, 0x56, 0x31, 0x01, 0x00, 0x00, 0x00, 0x88, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x6d, 0x27, 0xcd, 0xc2, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x01, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x62, 0x61, 0x6e, 0x61, 0x6e, 0x61, 0x05, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x67, 0x72, 0x61, 0x70, 0x65, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x61, 0x70, 0x70, 0x6c, 0x65, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x79, 0x65, 0x6c, 0x6c, 0x6f, 0x77, 0x06, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x70, 0x75, 0x72, 0x70, 0x6c, 0x65, 0x03, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x72, 0x65, 0x64, 0x00,
+    ];
+
+    #[test]
+    fn test_load_version_1_file() {
+        use crate::hasher::PortableHash;
+
+        let info = inspect_reader(VERSION_1_FILE).unwrap();
+        assert_eq!(info.version, 1);
+        assert_eq!(info.key_count, 3);
+        assert!(info.checksum_ok());
+        assert_eq!(info.meta.strategy, PersistenceStrategy::BuildOnLoad);
+
+        let store: VerifiedKvStore<String, String> =
+            VerifiedKvStore::from_bytes(VERSION_1_FILE).unwrap();
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.get(&"apple".to_string()).unwrap(), "red");
+        assert_eq!(store.get(&"banana".to_string()).unwrap(), "yellow");
+        assert_eq!(store.get(&"grape".to_string()).unwrap(), "purple");
+        assert!(!store.contains_key(&"kiwi".to_string()));
+
+        // Any hasher can load it, each time under a new random hash seed
+        let portable: VerifiedKvStore<String, String, PortableHash> =
+            VerifiedKvStore::from_bytes(VERSION_1_FILE).unwrap();
+        assert_eq!(portable.get(&"grape".to_string()).unwrap(), "purple");
+        let again: VerifiedKvStore<String, String, PortableHash> =
+            VerifiedKvStore::from_bytes(VERSION_1_FILE).unwrap();
+        assert_ne!(again.hash_seed(), portable.hash_seed());
+
+        // Saving writes the current version
+        let resaved = store.to_bytes().unwrap();
+        let info = inspect_reader(resaved.as_slice()).unwrap();
+        assert_eq!(info.version, 2);
+        assert_eq!(info.meta.strategy, PersistenceStrategy::RebuildOnLoad);
+        let reloaded: VerifiedKvStore<String, String> =
+            VerifiedKvStore::from_bytes(&resaved).unwrap();
+        assert_eq!(reloaded.get(&"apple".to_string()).unwrap(), "red");
+
+        let mut damaged = VERSION_1_FILE.to_vec();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert!(VerifiedKvStore::<String, String>::from_bytes(&damaged).is_err());
+    }
+
+    #[test]
+    fn test_load_resave_is_byte_identical() {
+        use crate::hasher::PortableHash;
+
+        let original = seeded_file_bytes(1000, "test_resave_original");
+        let test_file = "/tmp/test_resave.bin";
+        std::fs::write(test_file, &original).unwrap();
+
+        let loaded: VerifiedKvStore<String, u64, PortableHash> =
+            VerifiedKvStore::load_from_file(test_file).unwrap();
+        loaded.save_to_file(test_file).unwrap();
+
+        assert_eq!(std::fs::read(test_file).unwrap(), original);
+        std::fs::remove_file(test_file).ok();
+    }
+
     // === Persistence Tests ===
 
     #[test]
@@ -449,7 +890,8 @@ mod tests {
         let test_file = "/tmp/test_roundtrip.bin";
 
         original.save_to_file(test_file).unwrap();
-        let loaded: VerifiedKvStore<Vec<i32>> = VerifiedKvStore::load_from_file(test_file).unwrap();
+        let loaded: VerifiedKvStore<String, Vec<i32>> =
+            VerifiedKvStore::load_from_file(test_file).unwrap();
 
         assert_eq!(original.len(), loaded.len());
         assert_eq!(
@@ -470,12 +912,112 @@ mod tests {
 
     #[test]
     fn test_load_nonexistent_file() {
-        let result: Result<VerifiedKvStore<String>, _> =
+        let result: Result<VerifiedKvStore<String, String>, _> =
             VerifiedKvStore::load_from_file("/tmp/nonexistent_file_12345.bin");
         assert!(result.is_err());
         assert!(matches!(result, Err(KvError::IoError(_))));
     }
 
+    #[test]
+    fn test_bytes_roundtrip_matches_file() {
+        use crate::hasher::PortableHash;
+
+        let store: VerifiedKvStore<String, u64, PortableHash> = VerifiedKvStoreBuilder::new()
+            .seed(42)
+            .extend((0..1000u64).map(|i| (format!("key_{}", i), i)))
+            .build()
+            .unwrap();
+        let bytes = store.to_bytes().unwrap();
+        assert_eq!(bytes, seeded_file_bytes(1000, "test_bytes_roundtrip"));
+
+        let loaded: VerifiedKvStore<String, u64, PortableHash> =
+            VerifiedKvStore::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.get(&"key_7".to_string()).unwrap(), &7);
+
+        // Flip a bit in the last value
+        let mut corrupt = bytes;
+        let at = corrupt.len() - 2;
+        corrupt[at] ^= 1;
+        let result: Result<VerifiedKvStore<String, u64, PortableHash>, _> =
+            VerifiedKvStore::from_bytes(&corrupt);
+        assert!(matches!(result, Err(KvError::IoError(_))));
+    }
+
+    #[test]
+    fn test_stream_over_open_socket() {
+        use std::io::BufReader;
+        use std::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sender = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            for round in 0..2u64 {
+                let store: VerifiedKvStore<u64, u64> = VerifiedKvStoreBuilder::new()
+                    .extend((0..100).map(|i| (i, i * round)))
+                    .build()
+                    .unwrap();
+                store.to_writer(&mut socket).unwrap();
+            }
+            // Keep the connection open until the receiver is done
+            let mut done = [0];
+            std::io::Read::read_exact(&mut socket, &mut done).unwrap();
+        });
+
+        let mut socket = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(socket.try_clone().unwrap());
+        for round in 0..2u64 {
+            let store: VerifiedKvStore<u64, u64> =
+                VerifiedKvStore::from_reader(&mut reader).unwrap();
+            assert_eq!(store.get(&10).unwrap(), &(10 * round));
+        }
+        std::io::Write::write_all(&mut socket, b"x").unwrap();
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_serde_embedded_store() {
+        use crate::hasher::PortableHash;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize)]
+        struct State {
+            name: String,
+            users: VerifiedKvStore<u64, String, PortableHash>,
+        }
+
+        let state = State {
+            name: "prod".to_string(),
+            users: VerifiedKvStoreBuilder::new()
+                .insert(1, "alice".to_string())
+                .insert(2, "bob".to_string())
+                .build()
+                .unwrap(),
+        };
+
+        let json = serde_json::to_string(&state).unwrap();
+        let from_json: State = serde_json::from_str(&json).unwrap();
+        let from_bincode: State =
+            bincode::deserialize(&bincode::serialize(&state).unwrap()).unwrap();
+
+        for copy in [from_json, from_bincode] {
+            assert_eq!(copy.name, "prod");
+            assert_eq!(copy.users.get(&2).unwrap(), "bob");
+            assert_eq!(copy.users.hash_seed(), state.users.hash_seed());
+            // Same seeds, so the rebuilt MPHF has the same slot layout
+            assert!(copy.users.iter().eq(state.users.iter()));
+        }
+
+        // The recorded hasher must match the store type
+        let result: Result<VerifiedKvStore<u64, String>, _> =
+            serde_json::from_str(&serde_json::to_string(&state.users).unwrap());
+        let err = result.err().expect("hasher mismatch accepted");
+        assert!(err.to_string().contains("Incompatible hasher"), "{}", err);
+
+        let mismatched = json.replace("\"values\":[", "\"values\":[\"extra\",");
+        assert!(serde_json::from_str::<State>(&mismatched).is_err());
+    }
+
     #[test]
     fn test_persistence_large_dataset() {
         let mut data = HashMap::new();
@@ -487,7 +1029,8 @@ mod tests {
         let test_file = "/tmp/test_large_persistence.bin";
 
         original.save_to_file(test_file).unwrap();
-        let loaded: VerifiedKvStore<String> = VerifiedKvStore::load_from_file(test_file).unwrap();
+        let loaded: VerifiedKvStore<String, String> =
+            VerifiedKvStore::load_from_file(test_file).unwrap();
 
         assert_eq!(original.len(), loaded.len());
 
@@ -551,7 +1094,7 @@ mod tests {
 
     #[test]
     fn test_memory_usage_scaling() {
-        let small: VerifiedKvStore<String> = VerifiedKvStoreBuilder::new()
+        let small: VerifiedKvStore<String, String> = VerifiedKvStoreBuilder::new()
             .insert("a".to_string(), "v".to_string())
             .build()
             .unwrap();
@@ -586,4 +1129,290 @@ mod tests {
         assert!(!store.contains_key(&"".to_string()));
         assert!(!store.contains_key(&"presentt".to_string())); // Similar but different
     }
+
+    // === Prefilter Tests ===
+
+    #[test]
+    fn test_prefilter_rejects_misses() {
+        use crate::hasher::PortableHash;
+
+        let store: VerifiedKvStore<String, u32, PortableHash> = VerifiedKvStoreBuilder::new()
+            .extend((0..20_000).map(|i| (format!("key_{}", i), i)))
+            .prefilter(0.01)
+            .seed(5)
+            .build()
+            .unwrap();
+
+        for i in 0..20_000 {
+            assert_eq!(*store.get(&format!("key_{}", i)).unwrap(), i);
+        }
+        for i in 20_000..40_000 {
+            assert!(matches!(
+                store.get(&format!("key_{}", i)),
+                Err(KvError::KeyNotFoundFast)
+            ));
+        }
+
+        let stats = store.prefilter_stats().unwrap();
+        assert_eq!(stats.bits_per_key, 10);
+        assert!(stats.false_positive_rate < 0.012);
+        assert!(stats.bytes >= 20_000 * 10 / 8);
+        assert!(store
+            .clone()
+            .without_prefilter()
+            .prefilter_stats()
+            .is_none());
+    }
+
+    #[test]
+    fn test_prefilter_persisted() {
+        use crate::hasher::PortableHash;
+
+        let store: VerifiedKvStore<u64, u64, PortableHash> = VerifiedKvStoreBuilder::new()
+            .extend((0..1000).map(|i| (i, i * 2)))
+            .seed(9)
+            .build()
+            .unwrap()
+            .with_prefilter(0.001);
+        let stats = store.prefilter_stats().unwrap();
+
+        let loaded: VerifiedKvStore<u64, u64, PortableHash> =
+            VerifiedKvStore::from_bytes(&store.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.prefilter_stats(), Some(stats));
+        assert_eq!(*loaded.get(&999).unwrap(), 1998);
+        assert!(loaded.get(&1000).is_err());
+
+        let json = serde_json::to_string(&store).unwrap();
+        let from_json: VerifiedKvStore<u64, u64, PortableHash> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json.prefilter_stats(), Some(stats));
+
+        let info = inspect_reader(&store.to_bytes().unwrap()[..]).unwrap();
+        assert_eq!(info.meta.prefilter_bits_per_key, stats.bits_per_key);
+    }
+
+    #[test]
+    fn test_hash_tags() {
+        use crate::hasher::PortableHash;
+
+        let store: VerifiedKvStore<String, usize, PortableHash> = VerifiedKvStoreBuilder::new()
+            .extend((0..1000).map(|i| (format!("a-shared-key-prefix-{i}"), i)))
+            .seed(4)
+            .hash_tags(true)
+            .build()
+            .unwrap();
+        assert!(store.has_hash_tags());
+        for i in 0..1000 {
+            assert_eq!(*store.get(&format!("a-shared-key-prefix-{i}")).unwrap(), i);
+        }
+        for i in 1000..2000 {
+            assert!(!store.contains_key(&format!("a-shared-key-prefix-{i}")));
+        }
+
+        let loaded: VerifiedKvStore<String, usize, PortableHash> =
+            VerifiedKvStore::from_bytes(&store.to_bytes().unwrap()).unwrap();
+        assert!(loaded.has_hash_tags());
+        assert_eq!(
+            *loaded.get(&"a-shared-key-prefix-7".to_string()).unwrap(),
+            7
+        );
+        let info = inspect_reader(&store.to_bytes().unwrap()[..]).unwrap();
+        assert!(info.meta.hash_tags);
+
+        let json = serde_json::to_string(&store).unwrap();
+        let from_json: VerifiedKvStore<String, usize, PortableHash> =
+            serde_json::from_str(&json).unwrap();
+        assert!(from_json.has_hash_tags());
+
+        let without = loaded.without_hash_tags();
+        assert!(!without.has_hash_tags());
+        assert!(without.memory_usage_bytes() < store.memory_usage_bytes());
+        assert_eq!(
+            *without.get(&"a-shared-key-prefix-7".to_string()).unwrap(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_hashed_key_across_stores() {
+        use crate::hasher::PortableHash;
+
+        // Per-day stores over overlapping key sets, sharing a hash seed but
+        // not a build seed, so their MPHFs differ.
+        let day = |day: u64, seed: u64| -> VerifiedKvStore<u64, u64, PortableHash> {
+            VerifiedKvStoreBuilder::new()
+                .extend((day * 100..day * 100 + 1000).map(|k| (k, k + day)))
+                .hash_seed(77)
+                .seed(seed)
+                .prefilter(0.01)
+                .build()
+                .unwrap()
+        };
+        let days = [day(0, 1), day(1, 2), day(2, 3)];
+        assert_ne!(days[0].global_seed(), days[1].global_seed());
+
+        for k in 0..1300 {
+            let hashed = days[0].hash_key(&k);
+            assert_eq!(*hashed.key(), k);
+            for (d, store) in days.iter().enumerate() {
+                assert_eq!(store.get_hashed(&hashed).ok(), store.get(&k).ok());
+                assert_eq!(store.contains_hashed(&hashed), store.contains_key(&k));
+                assert_eq!(
+                    store.contains_key(&k),
+                    (d as u64 * 100..d as u64 * 100 + 1000).contains(&k)
+                );
+            }
+        }
+
+        // Hashes for another hash seed are recomputed, not misused.
+        let other: VerifiedKvStore<u64, u64, PortableHash> = VerifiedKvStoreBuilder::new()
+            .extend((0..1000).map(|k| (k, k)))
+            .seed(5)
+            .build()
+            .unwrap();
+        let hashed = other.hash_key(&142);
+        assert_eq!(*days[0].get_hashed(&hashed).unwrap(), 142);
+        assert_eq!(*days[1].get_hashed(&hashed).unwrap(), 143);
+    }
+
+    #[test]
+    fn test_slot_index_survives_reload() {
+        let store: VerifiedKvStore<String, u32> = VerifiedKvStoreBuilder::new()
+            .extend((0..1000).map(|i| (format!("user-{i}"), i)))
+            .build()
+            .unwrap();
+
+        // Side data aligned to the slot order
+        let side: Vec<usize> = store.slot_order().iter().map(String::len).collect();
+        for i in (0..1000).step_by(7) {
+            let key = format!("user-{i}");
+            let index = store.index_of(&key).unwrap();
+            assert_eq!(side[index], key.len());
+            assert_eq!(store.get_by_index(index), Some((&key, &i)));
+        }
+        assert!(matches!(
+            store.index_of_str("user-1000"),
+            Err(KvError::KeyNotFoundFast)
+        ));
+        assert!(store.get_by_index(1000).is_none());
+
+        let loaded: VerifiedKvStore<String, u32> =
+            VerifiedKvStore::from_bytes(&store.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.slot_order(), store.slot_order());
+
+        #[cfg(feature = "import")]
+        {
+            let mut out = Vec::new();
+            export::export_ndjson(&loaded, &mut out, export::Order::Slot).unwrap();
+            let line = String::from_utf8(out)
+                .unwrap()
+                .lines()
+                .nth(123)
+                .unwrap()
+                .to_string();
+            assert!(line.contains(&format!("\"{}\"", store.slot_order()[123])));
+        }
+    }
+
+    #[test]
+    fn test_sorted_index_queries() {
+        use std::ops::Bound;
+
+        let keys: Vec<String> = (0..2000)
+            .map(|i| format!("user:{}:{}", i / 10, ["mail", "name"][i % 2]))
+            .collect();
+        let store: VerifiedKvStore<String, usize> = VerifiedKvStoreBuilder::new()
+            .extend(keys.iter().cloned().zip(0..))
+            .sorted_index()
+            .build()
+            .unwrap();
+        assert!(store.has_sorted_index());
+
+        let mut sorted = keys.clone();
+        sorted.sort();
+        sorted.dedup();
+        let all: Vec<&String> = store.sorted_iter().map(|(k, _)| k).collect();
+        assert_eq!(all, sorted.iter().collect::<Vec<_>>());
+        assert_eq!(
+            store.sorted_iter().next_back().unwrap().0,
+            sorted.last().unwrap()
+        );
+
+        let prefixed: Vec<&String> = store.prefix_iter("user:123:").map(|(k, _)| k).collect();
+        assert_eq!(prefixed, ["user:123:mail", "user:123:name"]);
+        assert_eq!(store.prefix_iter("user:12").len(), 11 * 2);
+        assert_eq!(store.prefix_iter("").len(), store.len());
+        assert_eq!(store.prefix_iter("nobody").len(), 0);
+        assert_eq!(store.prefix_iter(b"user:199:").len(), 2);
+
+        let range: Vec<&String> = store
+            .range("user:199:mail".to_string().."user:2".to_string())
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(
+            range,
+            [
+                "user:199:mail",
+                "user:199:name",
+                "user:19:mail",
+                "user:19:name",
+                "user:1:mail",
+                "user:1:name"
+            ]
+        );
+        assert_eq!(store.range("z".to_string()..).len(), 0);
+        assert_eq!(store.range("b".to_string().."a".to_string()).len(), 0);
+        let borrowed = (Bound::Unbounded, Bound::Excluded("user:2"));
+        // user:0 and user:1, user:10 to user:19 and user:100 to user:199
+        assert_eq!(store.range::<str, _>(borrowed).len(), 112 * 2);
+        for (key, value) in store.range::<str, _>(borrowed) {
+            assert_eq!(keys[*value], *key);
+        }
+
+        // Without an index the same queries sort on the fly
+        let plain = store.clone().without_sorted_index();
+        assert!(plain.memory_usage_bytes() < store.memory_usage_bytes());
+        assert!(plain
+            .prefix_iter("user:123:")
+            .eq(store.prefix_iter("user:123:")));
+        assert!(plain.sorted_iter().eq(store.sorted_iter()));
+    }
+
+    #[test]
+    fn test_sorted_index_persisted() {
+        use crate::hasher::PortableHash;
+
+        let store: VerifiedKvStore<u64, u64, PortableHash> = VerifiedKvStoreBuilder::new()
+            .extend((0..1000).map(|k| (k * 3, k)))
+            .seed(2)
+            .build()
+            .unwrap()
+            .with_sorted_index();
+        let bytes = store.to_bytes().unwrap();
+        assert!(inspect_reader(&bytes[..]).unwrap().meta.sorted_index);
+
+        let loaded: VerifiedKvStore<u64, u64, PortableHash> =
+            VerifiedKvStore::from_bytes(&bytes).unwrap();
+        assert!(loaded.has_sorted_index());
+        assert!(loaded
+            .range(30..=60)
+            .map(|(k, _)| *k)
+            .eq((30..=60).step_by(3)));
+
+        let json = serde_json::to_string(&store).unwrap();
+        let from_json: VerifiedKvStore<u64, u64, PortableHash> =
+            serde_json::from_str(&json).unwrap();
+        assert!(from_json.sorted_iter().eq(store.sorted_iter()));
+
+        // The index is checked against the keys on load
+        let mut repr: serde_json::Value = serde_json::from_str(&json).unwrap();
+        repr["order"][0] = repr["order"][1].clone();
+        let result: Result<VerifiedKvStore<u64, u64, PortableHash>, _> =
+            serde_json::from_value(repr);
+        assert!(result.is_err());
+
+        let plain = store.without_sorted_index();
+        let info = inspect_reader(&plain.to_bytes().unwrap()[..]).unwrap();
+        assert!(!info.meta.sorted_index);
+    }
 }
@@ -1,51 +1,616 @@
-use learned_kv::{VerifiedKvStore, VerifiedKvStoreBuilder};
-use std::collections::HashMap;
+//! `learned-kv` command-line tool: build, query and inspect store files.
+//!
+//! Stores built and read by this tool have `String` keys and `String` values.
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Learned Key-Value Store Demo");
-    println!("==========================");
-    println!("String-only key-value store with GxHash - handles ALL patterns reliably!\n");
+use clap::{Parser, Subcommand, ValueEnum};
+use learned_kv::export::{export_csv, export_ndjson, CsvOptions, Order};
+#[cfg(gxhash)]
+use learned_kv::hasher::GxHash;
+use learned_kv::hasher::{PortableHash, StoreHasher, Xxh3Hash};
+use learned_kv::import::{Column, DuplicatePolicy, Format, Importer};
+use learned_kv::{
+    inspect_file, Compression, HasherKind, KvError, PersistenceStrategy, SectionInfo,
+    VerifiedKvStore, VerifiedKvStoreBuilder, WriteOptions,
+};
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-    // Demonstrate sequential pattern support
-    let mut data = HashMap::new();
-    for i in 0..1000 {
-        data.insert(format!("key_{:04}", i), format!("value_{}", i));
+#[derive(Parser)]
+#[command(
+    name = "learned-kv",
+    version,
+    about = "Build, query and inspect learned-kv store files"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Build a store file from CSV, TSV or NDJSON input
+    Build {
+        /// Input file; CSV/TSV rows default to `key,value`, NDJSON lines to `{"key": .., "value": ..}`
+        input: PathBuf,
+        /// Store file to write
+        output: PathBuf,
+        /// Input format (default: from the file extension)
+        #[arg(long, value_enum)]
+        format: Option<InputFormat>,
+        /// Treat the first row of CSV/TSV input as column names
+        #[arg(long)]
+        header: bool,
+        /// Key column: 0-based position, or a header/NDJSON field name
+        #[arg(long)]
+        key_column: Option<String>,
+        /// Value column: 0-based position, or a header/NDJSON field name
+        #[arg(long)]
+        value_column: Option<String>,
+        /// What to do with repeated keys
+        #[arg(long, value_enum, default_value_t = DuplicatesArg::Error)]
+        duplicates: DuplicatesArg,
+        /// Key hasher; use `portable` or `xxh3` for files shared across CPU families
+        #[arg(long, value_enum, default_value_t = HasherArg::DEFAULT)]
+        hasher: HasherArg,
+        /// Fixed seed for a reproducible build (default: random)
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Add a Bloom prefilter with this false-positive rate (e.g. 0.01)
+        #[arg(long, value_name = "FP_RATE")]
+        prefilter: Option<f64>,
+        /// Keep a 32-bit hash tag per slot, checked before comparing keys
+        #[arg(long)]
+        hash_tags: bool,
+        /// Keep a sorted key index for prefix and range queries
+        #[arg(long)]
+        sorted_index: bool,
+        /// Block compression for the keys section
+        #[arg(long, value_enum, default_value_t = CompressionArg::None)]
+        compress_keys: CompressionArg,
+        /// Block compression for the values section
+        #[arg(long, value_enum, default_value_t = CompressionArg::None)]
+        compress_values: CompressionArg,
+    },
+    /// Look up one or more keys
+    Get {
+        /// Store file
+        file: PathBuf,
+        /// Keys to look up
+        #[arg(required = true)]
+        keys: Vec<String>,
+    },
+    /// Print all entries
+    Dump {
+        /// Store file
+        file: PathBuf,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = InputFormat::Tsv)]
+        format: InputFormat,
+        /// Sort entries by key, for dumps that can be diffed
+        #[arg(long)]
+        sorted: bool,
+    },
+    /// Print the file header and size breakdown
+    Inspect {
+        /// Store file
+        file: PathBuf,
+    },
+    /// Check header, checksum, MPHF rebuild and every lookup
+    Verify {
+        /// Store file
+        file: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    Csv,
+    Tsv,
+    Ndjson,
+}
+
+impl From<InputFormat> for Format {
+    fn from(format: InputFormat) -> Self {
+        match format {
+            InputFormat::Csv => Format::Csv,
+            InputFormat::Tsv => Format::Tsv,
+            InputFormat::Ndjson => Format::Ndjson,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DuplicatesArg {
+    /// Fail on the repeated line
+    Error,
+    /// Keep the first value
+    First,
+    /// Keep the last value
+    Last,
+}
+
+impl From<DuplicatesArg> for DuplicatePolicy {
+    fn from(arg: DuplicatesArg) -> Self {
+        match arg {
+            DuplicatesArg::Error => DuplicatePolicy::Error,
+            DuplicatesArg::First => DuplicatePolicy::KeepFirst,
+            DuplicatesArg::Last => DuplicatePolicy::KeepLast,
+        }
+    }
+}
+
+/// Parse a column argument: a number is a position, anything else a name.
+fn column(arg: String) -> Column {
+    match arg.parse() {
+        Ok(index) => Column::Index(index),
+        Err(_) => Column::Name(arg),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HasherArg {
+    Gx,
+    Xxh3,
+    Portable,
+}
+
+impl HasherArg {
+    /// The library's default for `String` keys.
+    #[cfg(gxhash)]
+    const DEFAULT: HasherArg = HasherArg::Gx;
+    #[cfg(not(gxhash))]
+    const DEFAULT: HasherArg = HasherArg::Xxh3;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CompressionArg {
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl From<CompressionArg> for Compression {
+    fn from(arg: CompressionArg) -> Self {
+        match arg {
+            CompressionArg::None => Compression::None,
+            CompressionArg::Lz4 => Compression::Lz4,
+            CompressionArg::Zstd => Compression::Zstd,
+        }
+    }
+}
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> CliResult<ExitCode> {
+    match command {
+        Command::Build {
+            input,
+            output,
+            format,
+            header,
+            key_column,
+            value_column,
+            duplicates,
+            hasher,
+            seed,
+            prefilter,
+            hash_tags,
+            sorted_index,
+            compress_keys,
+            compress_values,
+        } => {
+            let format = format
+                .map(Format::from)
+                .or_else(|| Format::from_path(&input))
+                .ok_or("cannot infer the input format, pass --format")?;
+            let mut importer = Importer::new(format)
+                .has_header(header)
+                .duplicates(duplicates.into());
+            if let Some(key_column) = key_column {
+                importer = importer.key_column(column(key_column));
+            }
+            if let Some(value_column) = value_column {
+                importer = importer.value_column(column(value_column));
+            }
+            let options = WriteOptions {
+                keys: compress_keys.into(),
+                values: compress_values.into(),
+                ..WriteOptions::default()
+            };
+            let settings = BuildSettings {
+                seed,
+                prefilter,
+                hash_tags,
+                sorted_index,
+            };
+            let count = match hasher {
+                #[cfg(gxhash)]
+                HasherArg::Gx => build::<GxHash>(&importer, &input, &settings, &output, &options)?,
+                #[cfg(not(gxhash))]
+                HasherArg::Gx => return Err("GxHash is not supported by this build".into()),
+                HasherArg::Xxh3 => {
+                    build::<Xxh3Hash>(&importer, &input, &settings, &output, &options)?
+                }
+                HasherArg::Portable => {
+                    build::<PortableHash>(&importer, &input, &settings, &output, &options)?
+                }
+            };
+            println!("wrote {} entries to {}", count, output.display());
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Get { file, keys } => get(load_store(&file)?.as_ref(), &keys),
+        Command::Dump {
+            file,
+            format,
+            sorted,
+        } => dump(load_store(&file)?.as_ref(), format, sorted),
+        Command::Inspect { file } => inspect(&file),
+        Command::Verify { file } => verify(&file),
+    }
+}
+
+/// Store settings taken from `build` flags.
+struct BuildSettings {
+    seed: Option<u64>,
+    prefilter: Option<f64>,
+    hash_tags: bool,
+    sorted_index: bool,
+}
+
+fn build<H: StoreHasher<String>>(
+    importer: &Importer,
+    input: &Path,
+    settings: &BuildSettings,
+    output: &Path,
+    options: &WriteOptions,
+) -> CliResult<usize> {
+    let mut builder =
+        VerifiedKvStoreBuilder::<String, String, H>::new().hash_tags(settings.hash_tags);
+    if let Some(seed) = settings.seed {
+        builder = builder.seed(seed);
+    }
+    if let Some(fp_rate) = settings.prefilter {
+        builder = builder.prefilter(fp_rate);
+    }
+    if settings.sorted_index {
+        builder = builder.sorted_index();
+    }
+    let input = BufReader::new(File::open(input)?);
+    let store = importer.read(input, builder)?.build()?;
+    store.save_to_file_with(output, options)?;
+    Ok(store.len())
+}
+
+/// Object-safe view of a `String -> String` store, so commands don't need to
+/// be generic over the hasher recorded in the file.
+trait StoreView {
+    #[allow(clippy::ptr_arg)] // mirrors `VerifiedKvStore::get`
+    fn get(&self, key: &String) -> Result<&String, KvError>;
+    fn len(&self) -> usize;
+    fn entries(&self) -> Box<dyn Iterator<Item = (&String, &String)> + '_>;
+    fn export(
+        &self,
+        format: InputFormat,
+        order: Order,
+        out: &mut dyn Write,
+    ) -> Result<usize, KvError>;
+}
+
+impl<H: StoreHasher<String>> StoreView for VerifiedKvStore<String, String, H> {
+    fn get(&self, key: &String) -> Result<&String, KvError> {
+        VerifiedKvStore::get(self, key)
+    }
+
+    fn len(&self) -> usize {
+        VerifiedKvStore::len(self)
     }
+
+    fn entries(&self) -> Box<dyn Iterator<Item = (&String, &String)> + '_> {
+        Box::new(self.iter())
+    }
+
+    fn export(
+        &self,
+        format: InputFormat,
+        order: Order,
+        out: &mut dyn Write,
+    ) -> Result<usize, KvError> {
+        match format {
+            InputFormat::Ndjson => export_ndjson(self, out, order),
+            InputFormat::Csv => export_csv(
+                self,
+                out,
+                &CsvOptions {
+                    order,
+                    ..CsvOptions::default()
+                },
+            ),
+            InputFormat::Tsv => export_csv(
+                self,
+                out,
+                &CsvOptions {
+                    order,
+                    ..CsvOptions::tsv()
+                },
+            ),
+        }
+    }
+}
+
+/// Load `path` with the hasher recorded in its header.
+fn load_store(path: &Path) -> CliResult<Box<dyn StoreView>> {
+    let meta = inspect_file(path)?.meta;
+    if !meta.normalizer.is_empty() {
+        return Err(format!(
+            "store keys are normalized with {}; the CLI only reads stores with exact keys",
+            meta.normalizer
+        )
+        .into());
+    }
+    Ok(match meta.hasher {
+        #[cfg(gxhash)]
+        HasherKind::Gx => Box::new(VerifiedKvStore::<String, String, GxHash>::load_from_file(
+            path,
+        )?),
+        #[cfg(not(gxhash))]
+        HasherKind::Gx => return Err("GxHash is not supported by this build".into()),
+        HasherKind::Xxh3 => Box::new(VerifiedKvStore::<String, String, Xxh3Hash>::load_from_file(
+            path,
+        )?),
+        HasherKind::Portable => {
+            Box::new(VerifiedKvStore::<String, String, PortableHash>::load_from_file(path)?)
+        }
+        HasherKind::StrongerInt => {
+            return Err("store has integer keys; only String keys are supported".into())
+        }
+    })
+}
+
+fn get(store: &dyn StoreView, keys: &[String]) -> CliResult<ExitCode> {
+    let mut stdout = io::stdout().lock();
+    let mut missing = 0;
+    for key in keys {
+        match store.get(key) {
+            Ok(value) => writeln!(stdout, "{}\t{}", key, value)?,
+            Err(_) => {
+                eprintln!("{}: not found", key);
+                missing += 1;
+            }
+        }
+    }
+    Ok(if missing == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn dump(store: &dyn StoreView, format: InputFormat, sorted: bool) -> CliResult<ExitCode> {
+    let order = if sorted { Order::Sorted } else { Order::Slot };
+    store.export(format, order, &mut io::stdout().lock())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn inspect(path: &Path) -> CliResult<ExitCode> {
+    let info = inspect_file(path)?;
+    let meta = &info.meta;
+
+    println!("File:         {}", path.display());
+    println!("Version:      {}", info.version);
+    println!("Strategy:     {:?}", meta.strategy);
+    println!("Keys:         {}", info.key_count);
+    println!("Hasher:       {} ({:?})", meta.hasher, meta.hash_platform);
+    println!("Hash width:   {:?}", meta.hash_width);
+    println!("Global seed:  {:#018x}", meta.global_seed);
+    match meta.prefilter_bits_per_key {
+        0 => println!("Prefilter:    none"),
+        bits => println!("Prefilter:    {} bits per key", bits),
+    }
+    println!(
+        "Hash tags:    {}",
+        if meta.hash_tags { "yes" } else { "no" }
+    );
     println!(
-        "Created {} key-value pairs with SEQUENTIAL pattern",
-        data.len()
+        "Sorted index: {}",
+        if meta.sorted_index { "yes" } else { "no" }
     );
+    if meta.normalizer.is_empty() {
+        println!("Normalizer:   none");
+    } else {
+        println!("Normalizer:   {}", meta.normalizer);
+    }
+    if info.checksum_ok() {
+        println!("Checksum:     {:#010x} (ok)", info.checksum);
+    } else {
+        println!(
+            "Checksum:     {:#010x} (MISMATCH, data is {:#010x})",
+            info.checksum, info.actual_checksum
+        );
+    }
+    println!("Size:");
+    println!("  header      {:>12} bytes", info.header_bytes);
+    println!("  data        {:>12} bytes", info.data_bytes);
+    println!("  total       {:>12} bytes", info.total_bytes());
+    if info.key_count > 0 {
+        println!(
+            "  per key     {:>12.1} bytes",
+            info.data_bytes as f64 / info.key_count as f64
+        );
+    }
+    if meta.strategy == PersistenceStrategy::BuildOnLoad {
+        println!("Sections:     none (version 1 layout)");
+        return Ok(ExitCode::SUCCESS);
+    }
+    println!("Sections:");
+    for (name, section) in [
+        ("keys", &info.keys),
+        ("values", &info.values),
+        ("order", &info.order),
+    ] {
+        match section {
+            Some(section) => println!("  {:<11} {}", name, describe_section(section)),
+            None => println!("  {:<11} unreadable block framing", name),
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
 
-    let store = VerifiedKvStore::new(data)?;
-    println!("Built key-value store using PtrHash MPHF with GxHash");
-    println!("Store contains {} items", store.len());
-    println!("Memory usage: ~{} bytes", store.memory_usage_bytes());
+fn describe_section(section: &SectionInfo) -> String {
+    let mut text = format!(
+        "{:?}, {} blocks, {} -> {} bytes ({:.1}x)",
+        section.compression,
+        section.blocks,
+        section.raw_bytes,
+        section.stored_bytes,
+        section.ratio()
+    );
+    if !section.bad_blocks.is_empty() {
+        text.push_str(&format!(", BAD blocks {:?}", section.bad_blocks));
+    }
+    text
+}
 
-    println!("\nTesting lookups (safe - verifies keys):");
-    for test_key in ["key_0000", "key_0042", "key_0999", "nonexistent_key"] {
-        match store.get(&test_key.to_string()) {
-            Ok(value) => println!("  {}: {}", test_key, value),
-            Err(e) => println!("  {}: Error - {}", test_key, e),
+fn verify(path: &Path) -> CliResult<ExitCode> {
+    let info = inspect_file(path)?;
+    println!(
+        "header:   ok (version {}, {} keys)",
+        info.version, info.key_count
+    );
+    if !info.checksum_ok() {
+        println!(
+            "checksum: FAILED (expected {:#010x}, got {:#010x})",
+            info.checksum, info.actual_checksum
+        );
+        // Version 1 files have no blocks to point at
+        let blocked = info.meta.strategy != PersistenceStrategy::BuildOnLoad;
+        for (name, section) in [
+            ("keys", &info.keys),
+            ("values", &info.values),
+            ("order", &info.order),
+        ] {
+            match section {
+                Some(section) if !section.bad_blocks.is_empty() => {
+                    println!("  {} blocks {:?} damaged", name, section.bad_blocks)
+                }
+                Some(_) => {}
+                None if blocked => println!("  {} block framing unreadable", name),
+                None => {}
+            }
         }
+        return Ok(ExitCode::FAILURE);
+    }
+    println!("checksum: ok");
+
+    let store = load_store(path)?;
+    println!("mphf:     ok (rebuilt over {} keys)", store.len());
+
+    let bad = store
+        .entries()
+        .filter(|&(key, value)| store.get(key).ok() != Some(value))
+        .count();
+    if bad > 0 {
+        println!("lookups:  FAILED ({} of {} keys)", bad, store.len());
+        return Ok(ExitCode::FAILURE);
+    }
+    println!("lookups:  ok");
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use learned_kv::normalize::{NormalizedKvStoreBuilder, Trim};
+
+    fn run_args(args: &[&str]) -> ExitCode {
+        let cli =
+            Cli::try_parse_from(std::iter::once("learned-kv").chain(args.iter().copied())).unwrap();
+        run(cli.command).unwrap()
     }
 
-    println!("\nTesting builder pattern:");
-    let small_store: VerifiedKvStore<String> = VerifiedKvStoreBuilder::new()
-        .insert("hello".to_string(), "world".to_string())
-        .insert("foo".to_string(), "bar".to_string())
-        .insert("rust".to_string(), "awesome".to_string())
-        .build()?;
+    #[test]
+    fn test_build_get_dump_verify() {
+        let dir = std::env::temp_dir();
+        let input = dir.join("learned_kv_cli_input.csv");
+        let output = dir.join("learned_kv_cli_store.bin");
+        std::fs::write(&input, "fruit,color\nplum,purple\napple,red\nkiwi,green\n").unwrap();
+        let (input_arg, output_arg) = (input.to_str().unwrap(), output.to_str().unwrap());
+
+        let code = run_args(&[
+            "build",
+            input_arg,
+            output_arg,
+            "--header",
+            "--hasher",
+            "portable",
+            "--seed",
+            "7",
+            "--sorted-index",
+        ]);
+        assert_eq!(code, ExitCode::SUCCESS);
 
-    println!("Small store has {} items:", small_store.len());
-    for (key, value) in small_store.iter() {
-        println!("  {}: {}", key, value);
+        assert_eq!(
+            run_args(&["get", output_arg, "apple", "kiwi"]),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(
+            run_args(&["get", output_arg, "apple", "pear"]),
+            ExitCode::FAILURE
+        );
+
+        assert_eq!(
+            run_args(&["dump", output_arg, "--sorted"]),
+            ExitCode::SUCCESS
+        );
+        let mut dumped = Vec::new();
+        load_store(&output)
+            .unwrap()
+            .export(InputFormat::Csv, Order::Sorted, &mut dumped)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(dumped).unwrap(),
+            "apple,red\nkiwi,green\nplum,purple\n"
+        );
+
+        assert_eq!(run_args(&["inspect", output_arg]), ExitCode::SUCCESS);
+        assert_eq!(run_args(&["verify", output_arg]), ExitCode::SUCCESS);
+
+        let mut bytes = std::fs::read(&output).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        std::fs::write(&output, bytes).unwrap();
+        assert_eq!(run_args(&["verify", output_arg]), ExitCode::FAILURE);
+
+        std::fs::remove_file(&input).ok();
+        std::fs::remove_file(&output).ok();
     }
 
-    println!("\nVerifiedKvStore supports:");
-    println!("  - String keys only (bytes/string interface)");
-    println!("  - Safe key verification (no wrong values)");
-    println!("  - Full API (iter, keys, serialization)");
-    println!("  - GxHash for optimal distribution of all string patterns");
+    #[test]
+    fn test_normalized_store_rejected() {
+        let store = NormalizedKvStoreBuilder::<String, _, PortableHash>::new(Trim)
+            .insert(" key ".to_string(), "value".to_string())
+            .build()
+            .unwrap();
+        let path = std::env::temp_dir().join("learned_kv_cli_normalized.bin");
+        store.save_to_file(&path).unwrap();
+
+        let err = load_store(&path).err().expect("normalized store loaded");
+        assert_eq!(
+            err.to_string(),
+            "store keys are normalized with trim; the CLI only reads stores with exact keys"
+        );
 
-    Ok(())
+        std::fs::remove_file(&path).ok();
+    }
 }
@@ -0,0 +1,131 @@
+//! MPHF wrapper that dispatches between 64-bit and 128-bit key hashes.
+
+use crate::error::KvError;
+use crate::hasher::{HashWidth, KeyHasher, KvKey, StoreHasher};
+use ptr_hash::bucket_fn::Linear;
+use ptr_hash::{PtrHash, PtrHashParams};
+
+type Inner<K, Hx> = PtrHash<K, Linear, Vec<u32>, Hx, Vec<u8>>;
+
+/// The minimal perfect hash function of a store.
+///
+/// The width is chosen once at build time; lookups pay a single predictable branch.
+#[derive(Clone)]
+pub(crate) enum Mphf<K: KvKey, H: StoreHasher<K>> {
+    Narrow(Inner<K, H>),
+    Wide(Inner<K, H::Wide>),
+}
+
+impl<K: KvKey, H: StoreHasher<K>> Mphf<K, H> {
+    /// Build an MPHF over `keys`, mixing the secret `hash_seed` into every
+    /// key hash and drawing global seeds from `rng_seed`. `width` may be `Auto`.
+    pub(crate) fn build(
+        keys: &[K],
+        width: HashWidth,
+        hash_seed: u64,
+        rng_seed: u64,
+    ) -> Result<Self, KvError> {
+        let params = PtrHashParams::default();
+        let mphf = match width.resolve(keys.len()) {
+            HashWidth::Bits128 => {
+                Mphf::Wide(PtrHash::try_new_keyed(keys, params, hash_seed, rng_seed)?)
+            }
+            _ => Mphf::Narrow(PtrHash::try_new_keyed(keys, params, hash_seed, rng_seed)?),
+        };
+        Ok(mphf)
+    }
+
+    /// Rebuild the MPHF recorded with `global_seed`; gives the same indices
+    /// as the original build.
+    pub(crate) fn rebuild(
+        keys: &[K],
+        width: HashWidth,
+        hash_seed: u64,
+        global_seed: u64,
+    ) -> Result<Self, KvError> {
+        let params = PtrHashParams::default();
+        let mphf = match width.resolve(keys.len()) {
+            HashWidth::Bits128 => Mphf::Wide(PtrHash::try_with_global_seed(
+                keys,
+                params,
+                hash_seed,
+                global_seed,
+            )?),
+            _ => Mphf::Narrow(PtrHash::try_with_global_seed(
+                keys,
+                params,
+                hash_seed,
+                global_seed,
+            )?),
+        };
+        Ok(mphf)
+    }
+
+    /// Index of `key` in `[0, n)`. Arbitrary for keys outside the build set.
+    #[inline(always)]
+    pub(crate) fn index(&self, key: &K) -> usize {
+        match self {
+            Mphf::Narrow(mphf) => mphf.index(key),
+            Mphf::Wide(mphf) => mphf.index(key),
+        }
+    }
+
+    /// Key hash the index is computed from, widened to 128 bits.
+    #[inline(always)]
+    pub(crate) fn hash(&self, key: &K) -> u128 {
+        match self {
+            Mphf::Narrow(mphf) => mphf.hash(key) as u128,
+            Mphf::Wide(mphf) => mphf.hash(key),
+        }
+    }
+
+    /// [`hash`](Self::hash) of a borrowed key, e.g. a `str` for `String` keys.
+    #[inline(always)]
+    pub(crate) fn hash_borrowed<Q: ?Sized>(&self, key: &Q) -> u128
+    where
+        H: KeyHasher<Q, H = u64>,
+        H::Wide: KeyHasher<Q, H = u128>,
+    {
+        match self {
+            Mphf::Narrow(mphf) => mphf.hash_borrowed(key) as u128,
+            Mphf::Wide(mphf) => mphf.hash_borrowed(key),
+        }
+    }
+
+    /// Index for a hash returned by [`hash`](Self::hash).
+    #[inline(always)]
+    pub(crate) fn index_from_hash(&self, hx: u128) -> usize {
+        match self {
+            Mphf::Narrow(mphf) => mphf.index_from_hash(hx as u64),
+            Mphf::Wide(mphf) => mphf.index_from_hash(hx),
+        }
+    }
+
+    /// The global seed PtrHash settled on.
+    pub(crate) fn global_seed(&self) -> u64 {
+        match self {
+            Mphf::Narrow(mphf) => mphf.global_seed(),
+            Mphf::Wide(mphf) => mphf.global_seed(),
+        }
+    }
+
+    /// The resolved hash width (never `Auto`).
+    pub(crate) fn width(&self) -> HashWidth {
+        match self {
+            Mphf::Narrow(_) => HashWidth::Bits64,
+            Mphf::Wide(_) => HashWidth::Bits128,
+        }
+    }
+}
+
+/// 32-bit tag of a key hash, for rejecting a slot's key without comparing it.
+///
+/// The tag is only a filter: a mismatch proves the key is not the slot's key,
+/// while a match still goes on to the key comparison, which alone decides
+/// whether the key is found. Tags of different keys may be equal, and tag
+/// bits may overlap the bits that pick the slot; either only costs a key
+/// comparison.
+#[inline(always)]
+pub(crate) fn hash_tag(hx: u128) -> u32 {
+    hx as u32
+}
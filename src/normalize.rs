@@ -0,0 +1,574 @@
+//! Case-insensitive and other fuzzy lookups through key normalization.
+//!
+//! A [`KeyNormalizer`] maps every key to a canonical form: `"Berlin"`,
+//! `"BERLIN"` and `" berlin "` can all become `"berlin"`. A
+//! [`NormalizedKvStore`] is built over the normalized keys and normalizes
+//! each query the same way, so any spelling the normalizer folds together
+//! finds the entry. The key as originally inserted is returned by
+//! [`get_normalized`](NormalizedKvStore::get_normalized); it is stored next
+//! to the value only when it differs from its normalized form.
+//!
+//! The normalizer's [name](KeyNormalizer::name) is recorded in saved files,
+//! and loading checks it: a file can't be queried with a different
+//! normalization than it was built with.
+//!
+//! Unicode normalization needs tables this crate doesn't ship; a normalizer
+//! for it is a few lines on top of the `unicode-normalization` crate:
+//!
+//! ```ignore
+//! use learned_kv::normalize::KeyNormalizer;
+//! use std::borrow::Cow;
+//! use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+//!
+//! #[derive(Debug, Clone, Copy, Default)]
+//! struct Nfc;
+//!
+//! impl KeyNormalizer for Nfc {
+//!     fn name(&self) -> Cow<'static, str> {
+//!         "nfc".into()
+//!     }
+//!
+//!     fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str> {
+//!         match is_nfc_quick(key.chars()) {
+//!             IsNormalized::Yes => Cow::Borrowed(key),
+//!             _ => Cow::Owned(key.nfc().collect()),
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::error::KvError;
+use crate::hasher::{DefaultHasherFor, HashWidth, StoreHasher, StrHasher};
+use crate::persistence::{PersistedData, StoreMetadata, WriteOptions};
+use crate::verified_kv_store::{VerifiedKvStore, VerifiedKvStoreBuilder};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Maps keys to the canonical form they are stored and looked up under.
+pub trait KeyNormalizer: Send + Sync {
+    /// Identifies the normalization in saved files.
+    ///
+    /// Must not be empty, and must change whenever
+    /// [`normalize`](Self::normalize) changes its output.
+    fn name(&self) -> Cow<'static, str>;
+
+    /// Canonical form of `key`, borrowed if `key` already is canonical.
+    fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str>;
+}
+
+/// Lowercases ASCII letters and leaves every other character alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AsciiCaseFold;
+
+impl KeyNormalizer for AsciiCaseFold {
+    fn name(&self) -> Cow<'static, str> {
+        "ascii-case-fold".into()
+    }
+
+    fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        if key.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(key.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+}
+
+/// Lowercases with the full Unicode mapping of [`str::to_lowercase`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Lowercase;
+
+impl KeyNormalizer for Lowercase {
+    fn name(&self) -> Cow<'static, str> {
+        "lowercase".into()
+    }
+
+    fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        if key.chars().all(|c| c.to_lowercase().eq([c])) {
+            Cow::Borrowed(key)
+        } else {
+            Cow::Owned(key.to_lowercase())
+        }
+    }
+}
+
+/// Strips leading and trailing whitespace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Trim;
+
+impl KeyNormalizer for Trim {
+    fn name(&self) -> Cow<'static, str> {
+        "trim".into()
+    }
+
+    fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        Cow::Borrowed(key.trim())
+    }
+}
+
+/// Applies `A`, then `B`; `(Trim, AsciiCaseFold)` is named `"trim+ascii-case-fold"`.
+impl<A: KeyNormalizer, B: KeyNormalizer> KeyNormalizer for (A, B) {
+    fn name(&self) -> Cow<'static, str> {
+        format!("{}+{}", self.0.name(), self.1.name()).into()
+    }
+
+    fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        match self.0.normalize(key) {
+            Cow::Borrowed(key) => self.1.normalize(key),
+            Cow::Owned(key) => Cow::Owned(self.1.normalize(&key).into_owned()),
+        }
+    }
+}
+
+/// Check that keys recorded as normalized by `recorded` can be looked up
+/// with normalizer `expected`. Empty names stand for no normalization.
+pub(crate) fn check_recorded(recorded: &str, expected: &str) -> Result<(), KvError> {
+    if recorded == expected {
+        return Ok(());
+    }
+    Err(KvError::IncompatibleNormalizer(if recorded.is_empty() {
+        format!(
+            "file keys are not normalized, but the store type uses {}",
+            expected
+        )
+    } else if expected.is_empty() {
+        format!(
+            "file keys were normalized with {}; load it as a NormalizedKvStore",
+            recorded
+        )
+    } else {
+        format!(
+            "file keys were normalized with {}, but the store type uses {}",
+            recorded, expected
+        )
+    }))
+}
+
+/// Verified string-keyed store that looks keys up by their normalized form.
+///
+/// Build one with [`NormalizedKvStoreBuilder`].
+///
+/// # Example
+/// ```
+/// use learned_kv::normalize::{AsciiCaseFold, NormalizedKvStore, NormalizedKvStoreBuilder, Trim};
+///
+/// let store: NormalizedKvStore<u32, _> = NormalizedKvStoreBuilder::new((Trim, AsciiCaseFold))
+///     .insert("Berlin".to_string(), 3_645_000)
+///     .insert("Paris".to_string(), 2_161_000)
+///     .build()?;
+///
+/// assert_eq!(*store.get("  BERLIN")?, 3_645_000);
+/// assert_eq!(store.get_normalized("paris")?, ("Paris", &2_161_000));
+/// # Ok::<(), learned_kv::KvError>(())
+/// ```
+#[derive(Clone)]
+pub struct NormalizedKvStore<V, N, H = DefaultHasherFor<String>>
+where
+    V: Clone,
+    H: StoreHasher<String>,
+{
+    index: VerifiedKvStore<String, (Option<String>, V), H>,
+    normalizer: N,
+}
+
+impl<V, N, H> NormalizedKvStore<V, N, H>
+where
+    V: Clone,
+    N: KeyNormalizer,
+    H: StrHasher,
+{
+    /// Value for the key that normalizes like `key`.
+    ///
+    /// Fails with `KvError::KeyNotFoundFast` if there is none.
+    #[inline]
+    pub fn get(&self, key: &str) -> Result<&V, KvError> {
+        self.get_normalized(key).map(|(_, value)| value)
+    }
+
+    /// The key as inserted and its value, for the key that normalizes like `key`.
+    #[inline]
+    pub fn get_normalized(&self, key: &str) -> Result<(&str, &V), KvError> {
+        let slot = self
+            .index
+            .slot_str(&self.normalizer.normalize(key))
+            .ok_or(KvError::KeyNotFoundFast)?;
+        let (normalized, (original, value)) = self.index.get_by_index(slot).unwrap();
+        Ok((original.as_deref().unwrap_or(normalized), value))
+    }
+
+    /// Whether some key normalizes like `key`.
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_str(&self.normalizer.normalize(key))
+    }
+
+    /// The normalizer applied to keys and queries.
+    pub fn normalizer(&self) -> &N {
+        &self.normalizer
+    }
+
+    /// The underlying store, keyed by normalized key, each value paired
+    /// with the key as inserted if that differs from the normalized key.
+    pub fn index(&self) -> &VerifiedKvStore<String, (Option<String>, V), H> {
+        &self.index
+    }
+
+    /// Returns the number of key-value pairs in the store.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Check if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns an iterator over the keys as inserted and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &V)> {
+        self.index.iter().map(|(normalized, (original, value))| {
+            (original.as_deref().unwrap_or(normalized), value)
+        })
+    }
+
+    /// Returns the approximate **stack-allocated** memory usage in bytes;
+    /// see [`VerifiedKvStore::memory_usage_bytes`].
+    pub fn memory_usage_bytes(&self) -> usize {
+        std::mem::size_of::<N>() + self.index.memory_usage_bytes()
+    }
+}
+
+// Persistence, in the VerifiedKvStore file format with the normalizer recorded
+impl<V, N, H> NormalizedKvStore<V, N, H>
+where
+    V: Clone + Serialize + for<'de> Deserialize<'de>,
+    N: KeyNormalizer,
+    H: StrHasher,
+{
+    /// Save the store; see [`VerifiedKvStore::save_to_file`].
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), KvError> {
+        self.save_to_file_with(path, &WriteOptions::default())
+    }
+
+    /// Save the store, compressing sections as set in `options`.
+    pub fn save_to_file_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<(), KvError> {
+        let (data, meta) = self.persisted();
+        crate::persistence::write_with_integrity(path, &data, &meta, options)
+    }
+
+    /// Write the store to `writer`; see [`VerifiedKvStore::to_writer`].
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), KvError> {
+        self.to_writer_with(writer, &WriteOptions::default())
+    }
+
+    /// [`to_writer`](Self::to_writer) with the given compression settings.
+    pub fn to_writer_with<W: Write>(
+        &self,
+        writer: W,
+        options: &WriteOptions,
+    ) -> Result<(), KvError> {
+        let (data, meta) = self.persisted();
+        crate::persistence::write_to(writer, &data, &meta, options)
+    }
+
+    /// Encode the store as the bytes [`save_to_file`](Self::save_to_file) would write.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, KvError> {
+        let mut bytes = Vec::new();
+        self.to_writer(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn persisted(&self) -> (PersistedData<String, (Option<String>, V)>, StoreMetadata) {
+        let data = PersistedData {
+            keys: self.index.keys().cloned().collect(),
+            values: self.index.values().cloned().collect(),
+            order: self.index.persisted_order(),
+        };
+        let mut meta = self.index.metadata();
+        meta.normalizer = self.normalizer.name().into_owned();
+        (data, meta)
+    }
+
+    /// Read a store written by [`to_writer`](Self::to_writer), normalizing
+    /// queries with `normalizer`.
+    ///
+    /// Besides the errors of [`VerifiedKvStore::from_reader`], fails with
+    /// `KvError::IncompatibleNormalizer` if the file was built with another
+    /// normalizer, or without one.
+    pub fn from_reader_with<R: Read>(reader: R, normalizer: N) -> Result<Self, KvError> {
+        let (data, meta) = crate::persistence::read_from(reader)?;
+        let index = VerifiedKvStore::from_persisted_normalized(data, &meta, &normalizer.name())?;
+        Ok(Self { index, normalizer })
+    }
+
+    /// Load a store file; see [`from_reader_with`](Self::from_reader_with).
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, KvError>
+    where
+        N: Default,
+    {
+        let (data, meta) = crate::persistence::read_with_validation(path)?;
+        let normalizer = N::default();
+        let index = VerifiedKvStore::from_persisted_normalized(data, &meta, &normalizer.name())?;
+        Ok(Self { index, normalizer })
+    }
+
+    /// [`from_reader_with`](Self::from_reader_with) with the default normalizer.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, KvError>
+    where
+        N: Default,
+    {
+        Self::from_reader_with(reader, N::default())
+    }
+
+    /// Decode a store from bytes produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KvError>
+    where
+        N: Default,
+    {
+        Self::from_reader(bytes)
+    }
+}
+
+/// Builder for [`NormalizedKvStore`].
+///
+/// Keys are normalized as they are added. Adding a key that normalizes like
+/// an earlier, different key makes [`build`](Self::build) fail with
+/// `KvError::NormalizedKeyCollision`; adding the same key again replaces its
+/// value. Settings behave as on [`VerifiedKvStoreBuilder`].
+pub struct NormalizedKvStoreBuilder<V, N, H = DefaultHasherFor<String>> {
+    entries: VerifiedKvStoreBuilder<String, (Option<String>, V), H>,
+    normalizer: N,
+    collision: Option<KvError>,
+}
+
+impl<V, N, H> NormalizedKvStoreBuilder<V, N, H>
+where
+    V: Clone,
+    N: KeyNormalizer,
+    H: StoreHasher<String>,
+{
+    /// Create an empty builder normalizing keys with `normalizer`.
+    pub fn new(normalizer: N) -> Self {
+        Self {
+            entries: VerifiedKvStoreBuilder::new(),
+            normalizer,
+            collision: None,
+        }
+    }
+
+    /// Add `key` under its normalized form, replacing the value of an
+    /// identical key added before.
+    pub fn insert(mut self, key: String, value: V) -> Self {
+        let normalized = self.normalizer.normalize(&key).into_owned();
+        if let Some((first, _)) = self.entries.get(&normalized) {
+            let first = first.as_deref().unwrap_or(&normalized);
+            if first != key && self.collision.is_none() {
+                self.collision = Some(KvError::NormalizedKeyCollision {
+                    normalized: normalized.clone(),
+                    first: first.to_string(),
+                    second: key.clone(),
+                });
+            }
+        }
+        let original = (key != normalized).then_some(key);
+        self.entries = self.entries.insert(normalized, (original, value));
+        self
+    }
+
+    /// Add every pair from `iter`, as [`insert`](Self::insert) does.
+    pub fn extend<I>(mut self, iter: I) -> Self
+    where
+        I: IntoIterator<Item = (String, V)>,
+    {
+        for (key, value) in iter {
+            self = self.insert(key, value);
+        }
+        self
+    }
+
+    /// Whether an entry for a key normalizing like `key` has been added.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries
+            .contains_key(&self.normalizer.normalize(key).into_owned())
+    }
+
+    /// Number of distinct normalized keys added so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no entries have been added.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// See [`VerifiedKvStoreBuilder::hash_width`].
+    pub fn hash_width(mut self, width: HashWidth) -> Self {
+        self.entries = self.entries.hash_width(width);
+        self
+    }
+
+    /// See [`VerifiedKvStoreBuilder::hash_seed`].
+    pub fn hash_seed(mut self, seed: u64) -> Self {
+        self.entries = self.entries.hash_seed(seed);
+        self
+    }
+
+    /// See [`VerifiedKvStoreBuilder::seed`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.entries = self.entries.seed(seed);
+        self
+    }
+
+    /// See [`VerifiedKvStoreBuilder::prefilter`].
+    pub fn prefilter(mut self, fp_rate: f64) -> Self {
+        self.entries = self.entries.prefilter(fp_rate);
+        self
+    }
+
+    /// See [`VerifiedKvStoreBuilder::hash_tags`].
+    pub fn hash_tags(mut self, enabled: bool) -> Self {
+        self.entries = self.entries.hash_tags(enabled);
+        self
+    }
+
+    /// Build the store. Fails with the first `KvError::NormalizedKeyCollision`,
+    /// or with the errors of [`VerifiedKvStoreBuilder::build`].
+    pub fn build(self) -> Result<NormalizedKvStore<V, N, H>, KvError>
+    where
+        H: StrHasher,
+    {
+        if let Some(err) = self.collision {
+            return Err(err);
+        }
+        Ok(NormalizedKvStore {
+            index: self.entries.build()?,
+            normalizer: self.normalizer,
+        })
+    }
+}
+
+impl<V, N, H> Default for NormalizedKvStoreBuilder<V, N, H>
+where
+    V: Clone,
+    N: KeyNormalizer + Default,
+    H: StoreHasher<String>,
+{
+    fn default() -> Self {
+        Self::new(N::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::PortableHash;
+
+    #[test]
+    fn test_builtin_normalizers() {
+        assert_eq!(AsciiCaseFold.normalize("MiXeD-Ünï"), "mixed-Ünï");
+        assert!(matches!(AsciiCaseFold.normalize("lower"), Cow::Borrowed(_)));
+        assert_eq!(Lowercase.normalize("ÄRGER"), "ärger");
+        assert!(matches!(Lowercase.normalize("ärger"), Cow::Borrowed(_)));
+        assert_eq!(Trim.normalize("\t key \n"), "key");
+
+        let chained = (Trim, Lowercase);
+        assert_eq!(chained.name(), "trim+lowercase");
+        assert_eq!(chained.normalize("  ÉCOLE "), "école");
+    }
+
+    #[test]
+    fn test_lookup_and_collisions() {
+        let store: NormalizedKvStore<u32, AsciiCaseFold> =
+            NormalizedKvStoreBuilder::new(AsciiCaseFold)
+                .extend((0..100).map(|i| (format!("User{}", i), i)))
+                .insert("User7".to_string(), 700)
+                .build()
+                .unwrap();
+
+        assert_eq!(store.len(), 100);
+        assert_eq!(*store.get("user7").unwrap(), 700);
+        assert_eq!(store.get_normalized("USER42").unwrap(), ("User42", &42));
+        assert!(store.contains_key("uSeR99"));
+
+        let canonical: NormalizedKvStore<u32, AsciiCaseFold> =
+            NormalizedKvStoreBuilder::new(AsciiCaseFold)
+                .insert("plain".to_string(), 1)
+                .insert("Mixed".to_string(), 2)
+                .build()
+                .unwrap();
+        let originals: Vec<_> = canonical
+            .index()
+            .iter()
+            .map(|(key, (original, _))| (key.as_str(), original.as_deref()))
+            .collect();
+        assert!(originals.contains(&("plain", None)));
+        assert!(originals.contains(&("mixed", Some("Mixed"))));
+        assert_eq!(canonical.get_normalized("PLAIN").unwrap(), ("plain", &1));
+        let mut iterated: Vec<_> = canonical.iter().collect();
+        iterated.sort();
+        assert_eq!(iterated, [("Mixed", &2), ("plain", &1)]);
+        assert!(matches!(
+            store.get("user100"),
+            Err(KvError::KeyNotFoundFast)
+        ));
+
+        let result = NormalizedKvStoreBuilder::<u32, _>::new(AsciiCaseFold)
+            .insert("Key".to_string(), 1)
+            .insert("KEY".to_string(), 2)
+            .build();
+        assert!(matches!(
+            result,
+            Err(KvError::NormalizedKeyCollision { normalized, first, second })
+                if normalized == "key" && first == "Key" && second == "KEY"
+        ));
+    }
+
+    #[test]
+    fn test_normalizer_recorded_in_file() {
+        let store: NormalizedKvStore<String, (Trim, AsciiCaseFold), PortableHash> =
+            NormalizedKvStoreBuilder::default()
+                .insert(" Alpha".to_string(), "a".to_string())
+                .insert("BETA".to_string(), "b".to_string())
+                .seed(3)
+                .build()
+                .unwrap();
+        let bytes = store.to_bytes().unwrap();
+
+        let loaded: NormalizedKvStore<String, (Trim, AsciiCaseFold), PortableHash> =
+            NormalizedKvStore::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            loaded.get_normalized("alpha").unwrap(),
+            (" Alpha", &"a".to_string())
+        );
+        assert_eq!(loaded.to_bytes().unwrap(), bytes);
+
+        let info = crate::inspect_reader(bytes.as_slice()).unwrap();
+        assert_eq!(info.meta.normalizer, "trim+ascii-case-fold");
+
+        let other = NormalizedKvStore::<String, _, PortableHash>::from_reader_with(
+            bytes.as_slice(),
+            AsciiCaseFold,
+        );
+        assert!(matches!(other, Err(KvError::IncompatibleNormalizer(_))));
+        let plain =
+            VerifiedKvStore::<String, (Option<String>, String), PortableHash>::from_bytes(&bytes);
+        assert!(matches!(plain, Err(KvError::IncompatibleNormalizer(_))));
+
+        let unnormalized =
+            VerifiedKvStoreBuilder::<String, (Option<String>, String), PortableHash>::new()
+                .insert("beta".to_string(), (None, "b".to_string()))
+                .build()
+                .unwrap()
+                .to_bytes()
+                .unwrap();
+        let result =
+            NormalizedKvStore::<String, AsciiCaseFold, PortableHash>::from_bytes(&unnormalized);
+        assert!(matches!(result, Err(KvError::IncompatibleNormalizer(_))));
+    }
+}
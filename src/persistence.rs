@@ -4,29 +4,241 @@
 //! - Format versioning for safe evolution
 //! - Checksum validation for data integrity
 //! - Atomic writes to prevent corruption
+//! - Works on any `Read`/`Write`; [`AtomicWriter`] is the sink for files
+//! - Optional LZ4 or zstd compression, chosen separately for keys and values
+//!   (`compression` feature)
+//!
+//! # Layout
+//!
+//! A file is a bincode header followed by the data section: the keys section,
+//! then the values section, both in slot order (see
+//! [`VerifiedKvStore::slot_order`](crate::VerifiedKvStore::slot_order)),
+//! then the order section: the sorted key index as positions into the keys
+//! section, empty without one. Each section is the bincode encoding of its
+//! entries, cut into blocks of up to [`BLOCK_SIZE`] bytes. Every block is
+//! compressed on its own and carries the CRC32 of its stored bytes, so a
+//! damaged block can be located without decompressing anything. The header
+//! records the keys and values compression (the order section is never
+//! compressed), the prefilter size, whether slot hash tags and a sorted index
+//! are kept, and a CRC32 of the whole data section.
+//!
+//! # Versions
+//!
+//! Version 2 is the layout above. Yes/no settings live in the header's flag
+//! bits, and readers reject bits they don't know, so a setting that only
+//! changes how the existing sections are read can be added as a new bit
+//! without a version bump.
+//!
+//! Version 1 files hold the keys and values as one bincode blob and record
+//! no seeds. They are still read; loading builds a new MPHF over them
+//! ([`PersistenceStrategy::BuildOnLoad`]), and saving the loaded store
+//! writes version 2.
 //!
 //! ⚠️ **LIMITATION: MPHF is always rebuilt on load**
 //! - MPHF serialization is not currently implemented
 //! - Load times scale with dataset size (see VerifiedKvStore docs)
 
 use crate::error::KvError;
+use crate::hasher::{DefaultHasherFor, HashPlatform, HashWidth, HasherKind, StoreHasher};
+use crate::prefilter::MAX_BITS_PER_KEY;
 use serde::{Deserialize, Serialize};
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 
 /// Current format version - increment when format changes
-const FORMAT_VERSION: u32 = 1;
+const FORMAT_VERSION: u32 = 2;
+
+/// Version written before seeds and block sections were recorded
+const LEGACY_VERSION: u32 = 1;
+
+/// Header flag: slot hash tags are kept
+const FLAG_HASH_TAGS: u8 = 1 << 0;
+/// Header flag: a sorted key index is in the order section
+const FLAG_SORTED_INDEX: u8 = 1 << 1;
 
 /// Magic number to identify our file format
 const MAGIC: &[u8; 8] = b"LEARNKV1";
 
-/// Persistence strategy - currently only RebuildOnLoad is supported
+/// Uncompressed size of a full block. The last block of a section may be shorter.
+pub const BLOCK_SIZE: usize = 256 * 1024;
+
+/// Block compression for one section of a store file.
+///
+/// LZ4 and zstd need the `compression` feature; without it, writing or
+/// reading such a section fails with an `Unsupported` I/O error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Blocks are stored as-is.
+    #[default]
+    None,
+    /// LZ4: fast to decompress, moderate ratio.
+    Lz4,
+    /// Zstandard at [`WriteOptions::zstd_level`]: slower, better ratio.
+    Zstd,
+}
+
+impl Compression {
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+            Compression::Zstd => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, KvError> {
+        match byte {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Lz4),
+            2 => Ok(Compression::Zstd),
+            _ => Err(KvError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unknown compression: {}", byte),
+            ))),
+        }
+    }
+
+    fn compress(self, raw: &[u8], zstd_level: i32) -> Result<Vec<u8>, KvError> {
+        Ok(match self {
+            Compression::None => raw.to_vec(),
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => lz4_flex::compress(raw),
+            #[cfg(feature = "compression")]
+            Compression::Zstd => zstd::bulk::compress(raw, zstd_level)?,
+            #[cfg(not(feature = "compression"))]
+            Compression::Lz4 | Compression::Zstd => {
+                let _ = zstd_level;
+                return Err(self.unavailable());
+            }
+        })
+    }
+
+    /// Decompress a block whose uncompressed size is `raw_len`.
+    fn decompress(self, stored: &[u8], raw_len: usize) -> Result<Vec<u8>, KvError> {
+        let raw = match self {
+            Compression::None => stored.to_vec(),
+            #[cfg(feature = "compression")]
+            Compression::Lz4 => lz4_flex::decompress(stored, raw_len)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+            #[cfg(feature = "compression")]
+            Compression::Zstd => zstd::bulk::decompress(stored, raw_len)?,
+            #[cfg(not(feature = "compression"))]
+            Compression::Lz4 | Compression::Zstd => return Err(self.unavailable()),
+        };
+        if raw.len() != raw_len {
+            return Err(KvError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Block decompressed to {} bytes, expected {}",
+                    raw.len(),
+                    raw_len
+                ),
+            )));
+        }
+        Ok(raw)
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn unavailable(self) -> KvError {
+        KvError::IoError(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "{:?} compression needs the `compression` feature of learned-kv",
+                self
+            ),
+        ))
+    }
+}
+
+/// How a store is written.
+///
+/// # Example
+///
+#[cfg_attr(feature = "compression", doc = "```")]
+#[cfg_attr(not(feature = "compression"), doc = "```ignore")]
+/// use learned_kv::{Compression, VerifiedKvStoreBuilder, WriteOptions};
+///
+/// let store = VerifiedKvStoreBuilder::<u64, String>::new()
+///     .extend((0..1000).map(|i| (i, format!("{{\"id\": {}, \"active\": true}}", i))))
+///     .build()?;
+///
+/// // Compress the JSON values, leave the keys alone
+/// let options = WriteOptions {
+///     values: Compression::Zstd,
+///     ..WriteOptions::default()
+/// };
+/// let mut compressed = Vec::new();
+/// store.to_writer_with(&mut compressed, &options)?;
+/// assert!(compressed.len() < store.to_bytes()?.len() / 2);
+/// # Ok::<(), learned_kv::KvError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Compression of the keys section (default none).
+    pub keys: Compression,
+    /// Compression of the values section (default none).
+    pub values: Compression,
+    /// Level used for [`Compression::Zstd`] (default 3).
+    pub zstd_level: i32,
+}
+
+impl WriteOptions {
+    /// Compress both sections with `compression`.
+    pub fn compressed(compression: Compression) -> Self {
+        Self {
+            keys: compression,
+            values: compression,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            keys: Compression::None,
+            values: Compression::None,
+            zstd_level: 3,
+        }
+    }
+}
+
+/// How the MPHF is restored when a file is loaded
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PersistenceStrategy {
-    /// Rebuild MPHF on load (only supported strategy)
-    /// MPHF is not saved - it's reconstructed from keys on every load
+    /// Rebuild MPHF on load (the strategy files are written with)
+    /// MPHF is not saved - it's reconstructed from keys and the recorded seeds
     RebuildOnLoad,
+    /// Version 1 files: no seeds were recorded, so loading builds a new MPHF
+    /// under a random seed, and slot indices differ from those of the store
+    /// that was saved
+    BuildOnLoad,
+}
+
+/// Store settings recorded in the file header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreMetadata {
+    /// How the MPHF is restored on load
+    pub strategy: PersistenceStrategy,
+    /// Resolved key hash width (never `Auto`)
+    pub hash_width: HashWidth,
+    /// Hash function family used for keys
+    pub hasher: HasherKind,
+    /// Platform family the key hashes were computed on
+    pub hash_platform: HashPlatform,
+    /// Secret seed mixed into every key hash
+    pub hash_seed: u64,
+    /// Global seed the MPHF settled on
+    pub global_seed: u64,
+    /// Prefilter bits per key, 0 without a prefilter
+    pub prefilter_bits_per_key: u8,
+    /// Whether slot hash tags are kept
+    pub hash_tags: bool,
+    /// Whether a sorted key index is kept
+    pub sorted_index: bool,
+    /// Name of the key normalizer applied to the keys, empty without one
+    pub normalizer: String,
 }
 
 /// File format header
@@ -44,72 +256,490 @@ struct FileHeader {
     key_count: usize,
     /// Strategy used for this file (always 1 = RebuildOnLoad)
     strategy: u8,
+    /// Key hash width (0 = 64-bit, 1 = 128-bit)
+    hash_width: u8,
+    /// Key hasher (0 = Gx, 1 = Xxh3, 2 = Portable, 3 = StrongerInt)
+    hasher: u8,
+    /// Hash platform (0 = any, 1 = x86, 2 = ARM)
+    hash_platform: u8,
+    /// Secret key hash seed
+    hash_seed: u64,
+    /// MPHF global seed, so that loading reproduces the same MPHF
+    global_seed: u64,
+    /// Keys section compression (0 = none, 1 = LZ4, 2 = zstd)
+    keys_compression: u8,
+    /// Values section compression (0 = none, 1 = LZ4, 2 = zstd)
+    values_compression: u8,
+    /// Prefilter bits per key (0 = no prefilter)
+    prefilter_bits_per_key: u8,
+    /// `FLAG_*` bits
+    flags: u8,
+    /// Key normalizer name (empty = keys stored as given)
+    normalizer: String,
 }
 
 impl FileHeader {
-    fn new(file_size: u64, checksum: u32, key_count: usize, strategy: PersistenceStrategy) -> Self {
+    fn new(
+        file_size: u64,
+        checksum: u32,
+        key_count: usize,
+        meta: &StoreMetadata,
+        options: &WriteOptions,
+    ) -> Self {
         Self {
             magic: *MAGIC,
             version: FORMAT_VERSION,
             file_size,
             checksum,
             key_count,
-            strategy: match strategy {
-                PersistenceStrategy::RebuildOnLoad => 1,
+            // The seeds are always recorded, whatever the store was loaded from
+            strategy: 1,
+            hash_width: match meta.hash_width {
+                HashWidth::Bits128 => 1,
+                _ => 0,
+            },
+            hasher: match meta.hasher {
+                HasherKind::Gx => 0,
+                HasherKind::Xxh3 => 1,
+                HasherKind::Portable => 2,
+                HasherKind::StrongerInt => 3,
             },
+            hash_platform: match meta.hash_platform {
+                HashPlatform::Any => 0,
+                HashPlatform::X86 => 1,
+                HashPlatform::Arm => 2,
+            },
+            hash_seed: meta.hash_seed,
+            global_seed: meta.global_seed,
+            keys_compression: options.keys.to_byte(),
+            values_compression: options.values.to_byte(),
+            prefilter_bits_per_key: meta.prefilter_bits_per_key,
+            flags: if meta.hash_tags { FLAG_HASH_TAGS } else { 0 }
+                | if meta.sorted_index {
+                    FLAG_SORTED_INDEX
+                } else {
+                    0
+                },
+            normalizer: meta.normalizer.clone(),
         }
     }
 
-    fn validate(&self) -> Result<PersistenceStrategy, KvError> {
-        // Check magic number
-        if &self.magic != MAGIC {
+    /// Compression of the keys and values sections.
+    fn compression(&self) -> Result<(Compression, Compression), KvError> {
+        Ok((
+            Compression::from_byte(self.keys_compression)?,
+            Compression::from_byte(self.values_compression)?,
+        ))
+    }
+
+    fn validate(&self) -> Result<StoreMetadata, KvError> {
+        let strategy = match self.strategy {
+            1 => PersistenceStrategy::RebuildOnLoad,
+            _ => return Err(unknown_strategy(self.strategy)),
+        };
+
+        let hash_width = match self.hash_width {
+            0 => HashWidth::Bits64,
+            1 => HashWidth::Bits128,
+            _ => {
+                return Err(KvError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Unknown hash width: {}", self.hash_width),
+                )))
+            }
+        };
+
+        let hasher = match self.hasher {
+            0 => HasherKind::Gx,
+            1 => HasherKind::Xxh3,
+            2 => HasherKind::Portable,
+            3 => HasherKind::StrongerInt,
+            _ => {
+                return Err(KvError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Unknown hasher: {}", self.hasher),
+                )))
+            }
+        };
+
+        let hash_platform = match self.hash_platform {
+            0 => HashPlatform::Any,
+            1 => HashPlatform::X86,
+            2 => HashPlatform::Arm,
+            _ => {
+                return Err(KvError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Unknown hash platform: {}", self.hash_platform),
+                )))
+            }
+        };
+
+        self.compression()?;
+
+        if self.prefilter_bits_per_key > MAX_BITS_PER_KEY {
             return Err(KvError::IoError(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 format!(
-                    "Invalid file format: expected magic {:?}, got {:?}",
-                    MAGIC, self.magic
+                    "Prefilter bits per key out of range: {}",
+                    self.prefilter_bits_per_key
                 ),
             )));
         }
 
-        // Check version compatibility
-        if self.version != FORMAT_VERSION {
+        if self.flags & !(FLAG_HASH_TAGS | FLAG_SORTED_INDEX) != 0 {
             return Err(KvError::IoError(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
-                format!(
-                    "Incompatible format version: expected {}, got {}",
-                    FORMAT_VERSION, self.version
-                ),
+                format!("Unknown header flags: {:#04x}", self.flags),
             )));
         }
+        let hash_tags = self.flags & FLAG_HASH_TAGS != 0;
+        let sorted_index = self.flags & FLAG_SORTED_INDEX != 0;
 
-        // Decode strategy (only RebuildOnLoad supported, but accept legacy value 0 for compatibility)
-        let strategy = match self.strategy {
-            0 | 1 => PersistenceStrategy::RebuildOnLoad,
-            _ => {
-                return Err(KvError::IoError(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    format!("Unknown persistence strategy: {}", self.strategy),
-                )))
-            }
-        };
+        Ok(StoreMetadata {
+            strategy,
+            hash_width,
+            hasher,
+            hash_platform,
+            hash_seed: self.hash_seed,
+            global_seed: self.global_seed,
+            prefilter_bits_per_key: self.prefilter_bits_per_key,
+            hash_tags,
+            sorted_index,
+            normalizer: self.normalizer.clone(),
+        })
+    }
+}
+
+fn unknown_strategy(strategy: u8) -> KvError {
+    KvError::IoError(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Unknown persistence strategy: {}", strategy),
+    ))
+}
+
+/// Leading fields shared by the headers of all versions
+#[derive(Serialize, Deserialize)]
+struct Preamble {
+    magic: [u8; 8],
+    version: u32,
+}
+
+/// Version 1 file header
+#[derive(Serialize, Deserialize)]
+struct LegacyHeader {
+    magic: [u8; 8],
+    version: u32,
+    /// Size the writer expected the file to have (not reliable)
+    _file_size: u64,
+    /// CRC32 checksum of data section
+    checksum: u32,
+    /// Number of keys in the store
+    key_count: usize,
+    /// Strategy used for this file (0 or 1, both rebuild on load)
+    strategy: u8,
+}
+
+impl LegacyHeader {
+    fn validate(&self) -> Result<StoreMetadata, KvError> {
+        if self.strategy > 1 {
+            return Err(unknown_strategy(self.strategy));
+        }
+        // Version 1 had String keys only, under a hasher whose seeds went
+        // unrecorded; the store is built anew, with any hasher
+        let hasher = <DefaultHasherFor<String> as StoreHasher<String>>::KIND;
+        Ok(StoreMetadata {
+            strategy: PersistenceStrategy::BuildOnLoad,
+            hash_width: HashWidth::Bits64,
+            hasher,
+            hash_platform: hasher.platform(),
+            hash_seed: 0,
+            global_seed: 0,
+            prefilter_bits_per_key: 0,
+            hash_tags: false,
+            sorted_index: false,
+            normalizer: String::new(),
+        })
+    }
+}
+
+/// Version 1 data section: keys and values in one bincode blob
+#[derive(Deserialize)]
+struct LegacyData<K, Values> {
+    keys: Vec<K>,
+    values: Values,
+    /// Never written by version 1 writers
+    _mphf_data: Option<Vec<u8>>,
+}
+
+/// Header of a file of either supported version
+enum Header {
+    Current(FileHeader),
+    Legacy(LegacyHeader),
+}
+
+/// Read and check the magic number and version, then the rest of the header.
+fn read_header<R: Read>(mut reader: R) -> Result<Header, KvError> {
+    let preamble: Preamble = bincode::deserialize_from(&mut reader)?;
+    if &preamble.magic != MAGIC {
+        return Err(KvError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Invalid file format: expected magic {:?}, got {:?}",
+                MAGIC, preamble.magic
+            ),
+        )));
+    }
+
+    // Decode the whole header, preamble included
+    let preamble_bytes = bincode::serialize(&preamble)?;
+    let mut reader = preamble_bytes.as_slice().chain(reader);
+    match preamble.version {
+        FORMAT_VERSION => Ok(Header::Current(bincode::deserialize_from(&mut reader)?)),
+        LEGACY_VERSION => Ok(Header::Legacy(bincode::deserialize_from(&mut reader)?)),
+        version => Err(KvError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Incompatible format version: expected {} or {}, got {}",
+                FORMAT_VERSION, LEGACY_VERSION, version
+            ),
+        ))),
+    }
+}
+
+/// Summary of a store file, read without deserializing its entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInfo {
+    /// Format version
+    pub version: u32,
+    /// Settings recorded in the header
+    pub meta: StoreMetadata,
+    /// Number of keys recorded in the header
+    pub key_count: usize,
+    /// Checksum recorded in the header
+    pub checksum: u32,
+    /// Checksum of the data section as found on disk
+    pub actual_checksum: u32,
+    /// Size of the encoded header in bytes
+    pub header_bytes: u64,
+    /// Size of the data section (keys, values and order) in bytes
+    pub data_bytes: u64,
+    /// Keys section, or `None` if its block framing is unreadable
+    pub keys: Option<SectionInfo>,
+    /// Values section, or `None` if its block framing is unreadable
+    pub values: Option<SectionInfo>,
+    /// Order (sorted index) section, or `None` if its block framing is unreadable
+    pub order: Option<SectionInfo>,
+}
+
+/// Block summary of one section of a store file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionInfo {
+    /// Compression recorded in the header
+    pub compression: Compression,
+    /// Number of blocks
+    pub blocks: usize,
+    /// Uncompressed size in bytes
+    pub raw_bytes: u64,
+    /// Size of the stored (possibly compressed) block payloads in bytes
+    pub stored_bytes: u64,
+    /// Indices of blocks whose stored bytes don't match their checksum
+    pub bad_blocks: Vec<usize>,
+}
+
+impl SectionInfo {
+    fn new(compression: Compression, blocks: &[Block]) -> Self {
+        Self {
+            compression,
+            blocks: blocks.len(),
+            raw_bytes: blocks.iter().map(|b| u64::from(b.raw_len)).sum(),
+            stored_bytes: blocks.iter().map(|b| b.payload.len() as u64).sum(),
+            bad_blocks: (0..blocks.len())
+                .filter(|&i| !blocks[i].checksum_ok())
+                .collect(),
+        }
+    }
 
-        Ok(strategy)
+    /// Uncompressed size divided by stored size
+    pub fn ratio(&self) -> f64 {
+        self.raw_bytes as f64 / self.stored_bytes.max(1) as f64
     }
 }
 
+impl FileInfo {
+    /// Whether the data section matches the recorded checksum
+    pub fn checksum_ok(&self) -> bool {
+        self.checksum == self.actual_checksum
+    }
+
+    /// Total file size in bytes
+    pub fn total_bytes(&self) -> u64 {
+        self.header_bytes + self.data_bytes
+    }
+}
+
+/// Read the header of a store file and checksum its data section.
+///
+/// Fails on an unreadable or unsupported header, but not on a checksum
+/// mismatch; check [`FileInfo::checksum_ok`] for that.
+pub fn inspect_file<P: AsRef<Path>>(path: P) -> Result<FileInfo, KvError> {
+    let file = File::open(path)?;
+    inspect_reader(BufReader::new(file))
+}
+
+/// Like [`inspect_file`], for a store read from `reader`. Everything after
+/// the header is taken to be the data section, so `reader` is consumed to
+/// the end.
+pub fn inspect_reader<R: Read>(mut reader: R) -> Result<FileInfo, KvError> {
+    let header = match read_header(&mut reader)? {
+        Header::Current(header) => header,
+        Header::Legacy(header) => return inspect_legacy(reader, header),
+    };
+    let meta = header.validate()?;
+
+    let (keys_compression, values_compression) = header.compression()?;
+
+    let mut data_bytes = Vec::new();
+    reader.read_to_end(&mut data_bytes)?;
+
+    // Damaged framing only hides the section summaries
+    let mut sections = data_bytes.as_slice();
+    let keys = bincode::deserialize_from::<_, Vec<Block>>(&mut sections)
+        .ok()
+        .map(|blocks| SectionInfo::new(keys_compression, &blocks));
+    let values = keys
+        .as_ref()
+        .and_then(|_| bincode::deserialize_from::<_, Vec<Block>>(&mut sections).ok())
+        .map(|blocks| SectionInfo::new(values_compression, &blocks));
+    let order = values
+        .as_ref()
+        .and_then(|_| bincode::deserialize_from::<_, Vec<Block>>(&mut sections).ok())
+        .map(|blocks| SectionInfo::new(Compression::None, &blocks));
+
+    Ok(FileInfo {
+        version: header.version,
+        meta,
+        key_count: header.key_count,
+        checksum: header.checksum,
+        actual_checksum: calculate_checksum(&data_bytes),
+        header_bytes: bincode::serialized_size(&header)?,
+        data_bytes: data_bytes.len() as u64,
+        keys,
+        values,
+        order,
+    })
+}
+
+/// [`inspect_reader`] for a version 1 file, which has no block sections.
+fn inspect_legacy<R: Read>(mut reader: R, header: LegacyHeader) -> Result<FileInfo, KvError> {
+    let meta = header.validate()?;
+    let mut data_bytes = Vec::new();
+    reader.read_to_end(&mut data_bytes)?;
+    Ok(FileInfo {
+        version: header.version,
+        meta,
+        key_count: header.key_count,
+        checksum: header.checksum,
+        actual_checksum: calculate_checksum(&data_bytes),
+        header_bytes: bincode::serialized_size(&header)?,
+        data_bytes: data_bytes.len() as u64,
+        keys: None,
+        values: None,
+        order: None,
+    })
+}
+
 /// Persisted data container
-#[derive(Serialize, Deserialize)]
-pub struct PersistedData<K, V> {
+pub type PersistedData<K, V> = PersistedSections<K, Vec<V>>;
+
+/// Persisted data, with the values section decoded as `Values`: a `Vec<V>`,
+/// or anything else that deserializes from a sequence of values
+pub struct PersistedSections<K, Values> {
     /// Keys in the store
     pub keys: Vec<K>,
-    /// Values in the store
-    pub values: Vec<V>,
-    /// Serialized MPHF (not currently used - MPHF is always rebuilt on load)
-    pub mphf_data: Option<Vec<u8>>,
+    /// Values in the store, in the order of `keys`
+    pub values: Values,
+    /// Sorted key index as positions into `keys`, empty without one
+    pub order: Vec<u32>,
+}
+
+/// One block of a section
+#[derive(Serialize, Deserialize)]
+struct Block {
+    /// Uncompressed size, at most `BLOCK_SIZE`
+    raw_len: u32,
+    /// CRC32 of `payload`
+    checksum: u32,
+    /// Stored (possibly compressed) bytes
+    payload: Vec<u8>,
+}
+
+impl Block {
+    fn checksum_ok(&self) -> bool {
+        calculate_checksum(&self.payload) == self.checksum
+    }
+}
+
+/// Encode `items` and cut the result into compressed blocks.
+fn encode_section<T: Serialize + ?Sized>(
+    items: &T,
+    compression: Compression,
+    zstd_level: i32,
+) -> Result<Vec<Block>, KvError> {
+    let raw = bincode::serialize(items)?;
+    raw.chunks(BLOCK_SIZE)
+        .map(|chunk| {
+            let payload = compression.compress(chunk, zstd_level)?;
+            Ok(Block {
+                raw_len: chunk.len() as u32,
+                checksum: calculate_checksum(&payload),
+                payload,
+            })
+        })
+        .collect()
+}
+
+/// Check, decompress and decode the blocks of one section.
+fn decode_section<T>(blocks: Vec<Block>, compression: Compression, name: &str) -> Result<T, KvError>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut raw = Vec::new();
+    for (index, block) in blocks.iter().enumerate() {
+        if !block.checksum_ok() {
+            return Err(KvError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Checksum mismatch in {} block {}", name, index),
+            )));
+        }
+        let raw_len = block.raw_len as usize;
+        if raw_len > BLOCK_SIZE {
+            return Err(KvError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} block {} claims {} bytes", name, index, raw_len),
+            )));
+        }
+        raw.extend_from_slice(&compression.decompress(&block.payload, raw_len)?);
+    }
+    Ok(bincode::deserialize(&raw)?)
 }
 
 /// Writer for atomic file operations
+///
+/// Writes go to a temporary file next to the target, which replaces the
+/// target on [`commit`](Self::commit). Dropping the writer without committing
+/// removes the temporary file.
+///
+/// # Example
+///
+/// ```no_run
+/// use learned_kv::{AtomicWriter, VerifiedKvStoreBuilder};
+///
+/// let store = VerifiedKvStoreBuilder::<u64, u64>::new().insert(1, 2).build()?;
+/// let mut writer = AtomicWriter::new("store.kv")?;
+/// store.to_writer(&mut writer)?;
+/// writer.commit()?;
+/// # Ok::<(), learned_kv::KvError>(())
+/// ```
 pub struct AtomicWriter {
     temp_path: std::path::PathBuf,
     final_path: std::path::PathBuf,
@@ -135,12 +765,6 @@ impl AtomicWriter {
         })
     }
 
-    /// Write data to temp file
-    pub fn write_all(&mut self, data: &[u8]) -> Result<(), KvError> {
-        self.writer.write_all(data)?;
-        Ok(())
-    }
-
     /// Commit the write atomically
     pub fn commit(mut self) -> Result<(), KvError> {
         // Flush buffer
@@ -156,6 +780,16 @@ impl AtomicWriter {
     }
 }
 
+impl Write for AtomicWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
 impl Drop for AtomicWriter {
     fn drop(&mut self) {
         // Clean up temp file if commit wasn't called
@@ -168,19 +802,23 @@ pub fn calculate_checksum(data: &[u8]) -> u32 {
     crc32fast::hash(data)
 }
 
-/// Write data with full integrity protection
-pub fn write_with_integrity<K, V, P>(
-    path: P,
+/// Serialize the header and the data section.
+fn encode<K, V>(
     data: &PersistedData<K, V>,
-    strategy: PersistenceStrategy,
-) -> Result<(), KvError>
+    meta: &StoreMetadata,
+    options: &WriteOptions,
+) -> Result<(Vec<u8>, Vec<u8>), KvError>
 where
     K: Serialize,
     V: Serialize,
-    P: AsRef<Path>,
 {
-    // Serialize the data section
-    let data_bytes = bincode::serialize(data)?;
+    // Serialize the data section: keys, values and order blocks
+    let keys = encode_section(&data.keys, options.keys, options.zstd_level)?;
+    let values = encode_section(&data.values, options.values, options.zstd_level)?;
+    let order = encode_section(&data.order, Compression::None, options.zstd_level)?;
+    let mut data_bytes = bincode::serialize(&keys)?;
+    bincode::serialize_into(&mut data_bytes, &values)?;
+    bincode::serialize_into(&mut data_bytes, &order)?;
 
     // Calculate checksum
     let checksum = calculate_checksum(&data_bytes);
@@ -190,49 +828,113 @@ where
         (std::mem::size_of::<FileHeader>() + data_bytes.len()) as u64,
         checksum,
         data.keys.len(),
-        strategy,
+        meta,
+        options,
     );
     let header_bytes = bincode::serialize(&header)?;
 
+    Ok((header_bytes, data_bytes))
+}
+
+/// Write data with full integrity protection
+pub fn write_with_integrity<K, V, P>(
+    path: P,
+    data: &PersistedData<K, V>,
+    meta: &StoreMetadata,
+    options: &WriteOptions,
+) -> Result<(), KvError>
+where
+    K: Serialize,
+    V: Serialize,
+    P: AsRef<Path>,
+{
     // Atomic write
     let mut writer = AtomicWriter::new(path)?;
-
-    // Write header
-    writer.write_all(&header_bytes)?;
-
-    // Write data
-    writer.write_all(&data_bytes)?;
+    write_to(&mut writer, data, meta, options)?;
 
     // Commit atomically
-    writer.commit()?;
+    writer.commit()
+}
 
+/// Write the same bytes as [`write_with_integrity`] to `writer`.
+///
+/// The data section is encoded in memory first, since the header carries its
+/// checksum.
+pub fn write_to<K, V, W>(
+    mut writer: W,
+    data: &PersistedData<K, V>,
+    meta: &StoreMetadata,
+    options: &WriteOptions,
+) -> Result<(), KvError>
+where
+    K: Serialize,
+    V: Serialize,
+    W: Write,
+{
+    let (header_bytes, data_bytes) = encode(data, meta, options)?;
+    writer.write_all(&header_bytes)?;
+    writer.write_all(&data_bytes)?;
+    writer.flush()?;
     Ok(())
 }
 
 /// Read data with full integrity validation
-pub fn read_with_validation<K, V, P>(
+pub fn read_with_validation<K, Values, P>(
     path: P,
-) -> Result<(PersistedData<K, V>, PersistenceStrategy), KvError>
+) -> Result<(PersistedSections<K, Values>, StoreMetadata), KvError>
 where
     K: for<'de> Deserialize<'de>,
-    V: for<'de> Deserialize<'de>,
+    Values: for<'de> Deserialize<'de>,
     P: AsRef<Path>,
 {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
+    let result = read_from(&mut reader)?;
 
+    // A file holds exactly one store
+    if reader.read(&mut [0])? != 0 {
+        return Err(KvError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Trailing data after the store",
+        )));
+    }
+
+    Ok(result)
+}
+
+/// Read and validate one store from `reader`.
+///
+/// Reads exactly the bytes [`write_to`] wrote, so a stream may carry several
+/// stores back to back, or other data after the store. The checksum is
+/// verified once the data section has been decoded, so corruption that breaks
+/// decoding is reported as a `SerializationError` instead.
+pub fn read_from<K, Values, R>(
+    mut reader: R,
+) -> Result<(PersistedSections<K, Values>, StoreMetadata), KvError>
+where
+    K: for<'de> Deserialize<'de>,
+    Values: for<'de> Deserialize<'de>,
+    R: Read,
+{
     // Read and deserialize header
-    let header: FileHeader = bincode::deserialize_from(&mut reader)?;
+    let header = match read_header(&mut reader)? {
+        Header::Current(header) => header,
+        Header::Legacy(header) => return read_legacy(reader, header),
+    };
 
     // Validate header
-    let strategy = header.validate()?;
+    let meta = header.validate()?;
 
-    // Read remaining data
-    let mut data_bytes = Vec::new();
-    reader.read_to_end(&mut data_bytes)?;
+    let (keys_compression, values_compression) = header.compression()?;
+
+    // Read all sections, checksumming the bytes as they are consumed
+    let mut checked = ChecksumReader::new(reader);
+    let keys: Vec<Block> = bincode::deserialize_from(&mut checked)?;
+    let values: Vec<Block> = bincode::deserialize_from(&mut checked)?;
+    let order: Vec<Block> = bincode::deserialize_from(&mut checked)?;
 
     // Validate checksum
-    let actual_checksum = calculate_checksum(&data_bytes);
+    let actual_checksum = checked.finish();
     if actual_checksum != header.checksum {
         return Err(KvError::IoError(std::io::Error::new(
             std::io::ErrorKind::InvalidData,
@@ -243,8 +945,11 @@ where
         )));
     }
 
-    // Deserialize data
-    let data: PersistedData<K, V> = bincode::deserialize(&data_bytes)?;
+    let data = PersistedSections {
+        keys: decode_section(keys, keys_compression, "keys")?,
+        values: decode_section(values, values_compression, "values")?,
+        order: decode_section(order, Compression::None, "order")?,
+    };
 
     // Validate key count
     if data.keys.len() != header.key_count {
@@ -257,8 +962,94 @@ where
             ),
         )));
     }
+    let order_len = if meta.sorted_index {
+        header.key_count
+    } else {
+        0
+    };
+    if data.order.len() != order_len {
+        return Err(KvError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Sorted index has {} entries, expected {}",
+                data.order.len(),
+                order_len
+            ),
+        )));
+    }
+
+    Ok((data, meta))
+}
+
+/// [`read_from`] for a version 1 file.
+fn read_legacy<K, Values, R>(
+    reader: R,
+    header: LegacyHeader,
+) -> Result<(PersistedSections<K, Values>, StoreMetadata), KvError>
+where
+    K: for<'de> Deserialize<'de>,
+    Values: for<'de> Deserialize<'de>,
+    R: Read,
+{
+    let meta = header.validate()?;
+
+    let mut checked = ChecksumReader::new(reader);
+    let data: LegacyData<K, Values> = bincode::deserialize_from(&mut checked)?;
+    let actual_checksum = checked.finish();
+    if actual_checksum != header.checksum {
+        return Err(KvError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Checksum mismatch: expected {}, got {}",
+                header.checksum, actual_checksum
+            ),
+        )));
+    }
+    if data.keys.len() != header.key_count {
+        return Err(KvError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Key count mismatch: header says {}, got {}",
+                header.key_count,
+                data.keys.len()
+            ),
+        )));
+    }
 
-    Ok((data, strategy))
+    let data = PersistedSections {
+        keys: data.keys,
+        values: data.values,
+        order: Vec::new(),
+    };
+    Ok((data, meta))
+}
+
+/// Passes reads through while computing their CRC32.
+struct ChecksumReader<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    /// Checksum of everything read so far.
+    fn finish(self) -> u32 {
+        self.hasher.finalize()
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
 }
 
 #[cfg(test)]
@@ -266,6 +1057,19 @@ mod tests {
     use super::*;
     use std::fs;
 
+    const META: StoreMetadata = StoreMetadata {
+        strategy: PersistenceStrategy::RebuildOnLoad,
+        hash_width: HashWidth::Bits64,
+        hasher: HasherKind::Portable,
+        hash_platform: HashPlatform::Any,
+        hash_seed: 0x5eed,
+        global_seed: 0x91083a1,
+        prefilter_bits_per_key: 0,
+        hash_tags: false,
+        sorted_index: false,
+        normalizer: String::new(),
+    };
+
     #[test]
     fn test_checksum_calculation() {
         let data1 = b"hello world";
@@ -317,21 +1121,67 @@ mod tests {
         let original_data = PersistedData {
             keys: vec!["key1".to_string(), "key2".to_string()],
             values: vec![100, 200],
-            mphf_data: None,
+            order: Vec::new(),
         };
 
-        write_with_integrity(path, &original_data, PersistenceStrategy::RebuildOnLoad).unwrap();
+        write_with_integrity(path, &original_data, &META, &WriteOptions::default()).unwrap();
 
-        let (loaded_data, strategy): (PersistedData<String, i32>, _) =
+        let (loaded_data, meta): (PersistedData<String, i32>, _) =
             read_with_validation(path).unwrap();
 
-        assert_eq!(strategy, PersistenceStrategy::RebuildOnLoad);
+        assert_eq!(meta, META);
         assert_eq!(loaded_data.keys, original_data.keys);
         assert_eq!(loaded_data.values, original_data.values);
 
         fs::remove_file(path).unwrap();
     }
 
+    #[test]
+    fn test_stores_back_to_back() {
+        let first = PersistedData {
+            keys: vec!["a".to_string()],
+            values: vec![1],
+            order: Vec::new(),
+        };
+        let second = PersistedData {
+            keys: vec!["b".to_string(), "c".to_string()],
+            values: vec![2, 3],
+            order: Vec::new(),
+        };
+
+        let mut stream = Vec::new();
+        write_to(&mut stream, &first, &META, &WriteOptions::default()).unwrap();
+        write_to(&mut stream, &second, &META, &WriteOptions::default()).unwrap();
+        stream.extend_from_slice(b"tail");
+
+        let mut reader = stream.as_slice();
+        let (data, _): (PersistedData<String, i32>, _) = read_from(&mut reader).unwrap();
+        assert_eq!(data.keys, first.keys);
+        let (data, _): (PersistedData<String, i32>, _) = read_from(&mut reader).unwrap();
+        assert_eq!(data.values, second.values);
+        assert_eq!(reader, b"tail");
+    }
+
+    #[test]
+    fn test_trailing_data_in_file_rejected() {
+        let path = "/tmp/test_trailing_data.bin";
+        let data = PersistedData {
+            keys: vec!["key1".to_string()],
+            values: vec![100],
+            order: Vec::new(),
+        };
+        write_with_integrity(path, &data, &META, &WriteOptions::default()).unwrap();
+
+        let mut content = fs::read(path).unwrap();
+        content.push(0);
+        fs::write(path, content).unwrap();
+
+        let result: Result<(PersistedData<String, i32>, _), _> = read_with_validation(path);
+        assert!(result.is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn test_corruption_detection() {
         let path = "/tmp/test_corruption.bin";
@@ -340,10 +1190,10 @@ mod tests {
         let data = PersistedData {
             keys: vec!["key1".to_string()],
             values: vec![100],
-            mphf_data: None,
+            order: Vec::new(),
         };
 
-        write_with_integrity(path, &data, PersistenceStrategy::RebuildOnLoad).unwrap();
+        write_with_integrity(path, &data, &META, &WriteOptions::default()).unwrap();
 
         // Corrupt the file
         let mut file_content = fs::read(path).unwrap();
@@ -359,4 +1209,155 @@ mod tests {
 
         fs::remove_file(path).unwrap();
     }
+
+    #[test]
+    fn test_unknown_header_fields_rejected() {
+        let data = PersistedData {
+            keys: vec!["key1".to_string()],
+            values: vec![100],
+            order: Vec::new(),
+        };
+        let (header_bytes, data_bytes) = encode(&data, &META, &WriteOptions::default()).unwrap();
+        let with = |change: fn(&mut FileHeader)| {
+            let mut header: FileHeader = bincode::deserialize(&header_bytes).unwrap();
+            change(&mut header);
+            let mut bytes = bincode::serialize(&header).unwrap();
+            bytes.extend_from_slice(&data_bytes);
+            read_from::<String, Vec<i32>, _>(bytes.as_slice()).map(|_| ())
+        };
+        assert!(with(|_| {}).is_ok());
+        assert!(with(|h| h.flags |= 0x80).is_err());
+        assert!(with(|h| h.version = 3).is_err());
+        assert!(with(|h| h.magic[0] ^= 1).is_err());
+    }
+
+    #[test]
+    fn test_inspect_file() {
+        let path = "/tmp/test_inspect_file.bin";
+        let _ = fs::remove_file(path);
+
+        let data = PersistedData {
+            keys: vec!["key1".to_string(), "key2".to_string()],
+            values: vec![100, 200],
+            order: Vec::new(),
+        };
+        write_with_integrity(path, &data, &META, &WriteOptions::default()).unwrap();
+
+        let info = inspect_file(path).unwrap();
+        assert_eq!(info.version, FORMAT_VERSION);
+        assert_eq!(info.meta, META);
+        assert_eq!(info.key_count, 2);
+        assert!(info.checksum_ok());
+        assert_eq!(info.total_bytes(), fs::metadata(path).unwrap().len());
+
+        // Corrupt the data section: still inspectable, but flagged
+        let mut content = fs::read(path).unwrap();
+        let last = content.len() - 1;
+        content[last] ^= 0xFF;
+        fs::write(path, content).unwrap();
+        assert!(!inspect_file(path).unwrap().checksum_ok());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(not(feature = "compression"))]
+    fn test_compression_unavailable() {
+        let data = PersistedData {
+            keys: vec![1u64],
+            values: vec![1u64],
+            order: Vec::new(),
+        };
+        let options = WriteOptions {
+            values: Compression::Zstd,
+            ..WriteOptions::default()
+        };
+        let result = write_to(Vec::new(), &data, &META, &options);
+        assert!(matches!(
+            result,
+            Err(KvError::IoError(e)) if e.kind() == std::io::ErrorKind::Unsupported
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_compressed_sections() {
+        let path = "/tmp/test_compressed_sections.bin";
+        let _ = fs::remove_file(path);
+
+        // ~1.3 MB of repetitive values spans several blocks
+        let data = PersistedData {
+            keys: (0..20_000u64).collect::<Vec<_>>(),
+            values: (0..20_000u64)
+                .map(|i| format!("{{\"id\":{},\"status\":\"active\",\"tags\":[]}}", i))
+                .collect::<Vec<_>>(),
+            order: Vec::new(),
+        };
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let options = WriteOptions {
+                keys: Compression::Lz4,
+                values: compression,
+                ..WriteOptions::default()
+            };
+            write_with_integrity(path, &data, &META, &options).unwrap();
+
+            let (loaded, _): (PersistedData<u64, String>, _) = read_with_validation(path).unwrap();
+            assert_eq!(loaded.keys, data.keys);
+            assert_eq!(loaded.values, data.values);
+
+            let info = inspect_file(path).unwrap();
+            let keys = info.keys.unwrap();
+            let values = info.values.unwrap();
+            assert_eq!(keys.compression, Compression::Lz4);
+            assert_eq!(values.compression, compression);
+            assert!(values.blocks > 1);
+            assert!(values.bad_blocks.is_empty());
+            if compression == Compression::None {
+                assert_eq!(values.raw_bytes, values.stored_bytes);
+            } else {
+                assert!(values.ratio() > 3.0, "{:?}", values);
+            }
+        }
+
+        // Damage the last values block, just before the empty order section:
+        // located by inspect, rejected on load
+        let empty_order = encode_section(&Vec::<u32>::new(), Compression::None, 0).unwrap();
+        let order_bytes = bincode::serialized_size(&empty_order).unwrap() as usize;
+        let mut content = fs::read(path).unwrap();
+        let last = content.len() - order_bytes - 1;
+        content[last] ^= 0xFF;
+        fs::write(path, content).unwrap();
+
+        let info = inspect_file(path).unwrap();
+        let values = info.values.unwrap();
+        assert_eq!(values.bad_blocks, vec![values.blocks - 1]);
+        assert!(info.keys.unwrap().bad_blocks.is_empty());
+        let result: Result<(PersistedData<u64, String>, _), _> = read_with_validation(path);
+        assert!(result.is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_hash_width_recorded() {
+        let path = "/tmp/test_hash_width_recorded.bin";
+        let _ = fs::remove_file(path);
+
+        let data = PersistedData {
+            keys: vec![1u64, 2],
+            values: vec![10, 20],
+            order: Vec::new(),
+        };
+        let meta = StoreMetadata {
+            hash_width: HashWidth::Bits128,
+            ..META
+        };
+
+        write_with_integrity(path, &data, &meta, &WriteOptions::default()).unwrap();
+        let (_, loaded): (PersistedData<u64, i32>, _) = read_with_validation(path).unwrap();
+
+        assert_eq!(loaded.hash_width, HashWidth::Bits128);
+
+        fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,196 @@
+//! Blocked Bloom filter in front of the MPHF, for fast negative lookups.
+//!
+//! Every key sets a few bits inside one 512-bit (cache line sized) block.
+//! Bit positions come from the key hash the MPHF computes anyway, so a
+//! lookup that misses the filter costs one hash and one cache line, and
+//! never touches the keys array.
+//!
+//! The filter is described by a single number, its bits per key; the hash
+//! count follows from it. That number is recorded in the file header and
+//! the filter is rebuilt on load, like the MPHF.
+
+use std::f64::consts::LN_2;
+
+/// Bits per block; one 64-byte cache line.
+const BLOCK_BITS: usize = 512;
+
+/// Most bits per key a prefilter can use (false-positive rate around 5e-6).
+pub(crate) const MAX_BITS_PER_KEY: u8 = 32;
+
+#[derive(Clone, Copy, Default)]
+#[repr(align(64))]
+struct Block([u64; BLOCK_BITS / 64]);
+
+/// Blocked Bloom filter over key hashes.
+#[derive(Clone)]
+pub(crate) struct Prefilter {
+    blocks: Vec<Block>,
+    bits_per_key: u8,
+    hashes: u32,
+}
+
+/// Size and accuracy of a store's prefilter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrefilterStats {
+    /// Filter bits per key.
+    pub bits_per_key: u8,
+    /// Bits set per key.
+    pub hashes: u32,
+    /// Memory held by the filter.
+    pub bytes: usize,
+    /// Chance that a key outside the store passes the filter, estimated
+    /// from how full each block actually is.
+    pub false_positive_rate: f64,
+}
+
+impl Prefilter {
+    /// Fewest bits per key expected to reach `fp_rate`, capped at
+    /// [`MAX_BITS_PER_KEY`].
+    pub(crate) fn bits_for_rate(fp_rate: f64) -> u8 {
+        (1..=MAX_BITS_PER_KEY)
+            .find(|&bits| expected_fp_rate(bits) <= fp_rate)
+            .unwrap_or(MAX_BITS_PER_KEY)
+    }
+
+    /// Build a filter holding the given key hashes (`n` of them).
+    pub(crate) fn build(hashes: impl Iterator<Item = u128>, n: usize, bits_per_key: u8) -> Self {
+        let bits_per_key = bits_per_key.clamp(1, MAX_BITS_PER_KEY);
+        let blocks = (n * bits_per_key as usize).div_ceil(BLOCK_BITS).max(1);
+        let mut filter = Self {
+            blocks: vec![Block::default(); blocks],
+            bits_per_key,
+            hashes: hash_count(bits_per_key),
+        };
+        for hx in hashes {
+            let (block, mut state) = filter.locate(hx);
+            let words = &mut filter.blocks[block].0;
+            for _ in 0..filter.hashes {
+                let bit = next_bit(&mut state);
+                words[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        filter
+    }
+
+    /// False if the key with hash `hx` is certainly not in the store.
+    #[inline(always)]
+    pub(crate) fn may_contain(&self, hx: u128) -> bool {
+        let (block, mut state) = self.locate(hx);
+        let words = &self.blocks[block].0;
+        (0..self.hashes).all(|_| {
+            let bit = next_bit(&mut state);
+            words[bit / 64] & (1 << (bit % 64)) != 0
+        })
+    }
+
+    /// Block index for `hx`, and the state its bit positions are drawn from.
+    #[inline(always)]
+    fn locate(&self, hx: u128) -> (usize, u64) {
+        // Fold both halves so 64-bit and 128-bit hashes are treated alike
+        let fp = (hx as u64) ^ ((hx >> 64) as u64);
+        let block = ((fp as u128 * self.blocks.len() as u128) >> 64) as usize;
+        (block, fp)
+    }
+
+    pub(crate) fn bits_per_key(&self) -> u8 {
+        self.bits_per_key
+    }
+
+    pub(crate) fn stats(&self) -> PrefilterStats {
+        let k = self.hashes as i32;
+        let fp_sum: f64 = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let set: u32 = block.0.iter().map(|word| word.count_ones()).sum();
+                (set as f64 / BLOCK_BITS as f64).powi(k)
+            })
+            .sum();
+        PrefilterStats {
+            bits_per_key: self.bits_per_key,
+            hashes: self.hashes,
+            bytes: self.blocks.len() * std::mem::size_of::<Block>(),
+            false_positive_rate: fp_sum / self.blocks.len() as f64,
+        }
+    }
+}
+
+/// Next bit position within a block. Multiplying by an odd constant
+/// permutes the state; its top bits are well mixed.
+#[inline(always)]
+fn next_bit(state: &mut u64) -> usize {
+    *state = state.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    (*state >> (64 - BLOCK_BITS.trailing_zeros())) as usize
+}
+
+/// Hashes per key for a filter with `bits_per_key` bits per key.
+fn hash_count(bits_per_key: u8) -> u32 {
+    ((bits_per_key as f64 * LN_2).round() as u32).clamp(1, 16)
+}
+
+/// Expected false-positive rate of a blocked filter with `bits_per_key`,
+/// with block loads following a Poisson distribution.
+fn expected_fp_rate(bits_per_key: u8) -> f64 {
+    let k = hash_count(bits_per_key) as i32;
+    let mean = BLOCK_BITS as f64 / bits_per_key as f64;
+    let limit = (mean + 12.0 * mean.sqrt() + 20.0) as usize;
+
+    let mut rate = 0.0;
+    let mut p = (-mean).exp();
+    for keys in 0..limit {
+        let unset = (1.0 - 1.0 / BLOCK_BITS as f64).powi((k as usize * keys) as i32);
+        rate += p * (1.0 - unset).powi(k);
+        p *= mean / (keys + 1) as f64;
+    }
+    rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mix(i: u64) -> u128 {
+        let x = i.wrapping_mul(0xd6e8_feb8_6659_fd93);
+        (x ^ (x >> 32)).wrapping_mul(0xd6e8_feb8_6659_fd93) as u128
+    }
+
+    #[test]
+    fn test_no_false_negatives_and_rate_close_to_target() {
+        for target in [0.1, 0.01, 0.001] {
+            let bits = Prefilter::bits_for_rate(target);
+            let filter = Prefilter::build((0..50_000).map(mix), 50_000, bits);
+            assert!((0..50_000).all(|i| filter.may_contain(mix(i))));
+
+            let probes = 200_000;
+            let passed = (1_000_000..1_000_000 + probes)
+                .filter(|&i| filter.may_contain(mix(i)))
+                .count();
+            let measured = passed as f64 / probes as f64;
+            let estimated = filter.stats().false_positive_rate;
+            assert!(
+                measured < target * 1.5,
+                "target {} measured {}",
+                target,
+                measured
+            );
+            assert!(
+                (measured - estimated).abs() < target * 0.3,
+                "measured {} estimated {}",
+                measured,
+                estimated
+            );
+        }
+    }
+
+    #[test]
+    fn test_bits_for_rate_is_monotonic() {
+        assert_eq!(Prefilter::bits_for_rate(1.0), 1);
+        assert_eq!(Prefilter::bits_for_rate(0.0), MAX_BITS_PER_KEY);
+        let mut last = 0;
+        for exp in 1..6 {
+            let bits = Prefilter::bits_for_rate(10f64.powi(-exp));
+            assert!(bits > last);
+            last = bits;
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! JSON over HTTP/1.1 frontend.
+//!
+//! | Endpoint             | Response                                                |
+//! |----------------------|---------------------------------------------------------|
+//! | `GET /v1/keys/{key}` | `{"key": .., "value": ..}`, or 404                      |
+//! | `POST /v1/batch`     | body `["k1", "k2"]`; `{"results": [{"key", "found", "value"}, ..]}` |
+//! | `GET /v1/stats`      | key count and hasher; file path and reloads when served from disk |
+//! | `GET /v1/health`     | `{"status": "ok"}`                                      |
+//!
+//! Keys in the path are percent-decoded. Errors are JSON objects with an
+//! `error` field. Connections are kept alive unless the client asks otherwise;
+//! chunked request bodies are not supported.
+//!
+//! Anything implementing [`JsonSource`] can be served: a [`StoreHandle`] (byte
+//! values, reloaded from disk) or a [`VerifiedKvStore`] whose values implement
+//! `Serialize`, for embedding in an application.
+//!
+//! ```no_run
+//! use learned_kv::server::http;
+//! use learned_kv::VerifiedKvStoreBuilder;
+//! use std::net::TcpListener;
+//! use std::sync::Arc;
+//!
+//! let store = VerifiedKvStoreBuilder::<u64, Vec<String>>::new()
+//!     .insert(1, vec!["admin".to_string()])
+//!     .build()
+//!     .unwrap();
+//! let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+//! http::serve(listener, Arc::new(store)).unwrap();
+//! // curl localhost:8080/v1/keys/1  =>  {"key":"1","value":["admin"]}
+//! ```
+
+use super::{invalid, read_line, serve as serve_with, StoreHandle};
+use crate::error::KvError;
+use crate::hasher::{KvKey, StoreHasher};
+use crate::verified_kv_store::VerifiedKvStore;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Longest accepted request or header line.
+const MAX_LINE_LEN: usize = 8 * 1024;
+
+/// Most headers accepted in one request.
+const MAX_HEADERS: usize = 100;
+
+/// Largest accepted request body.
+const MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// A store the HTTP API can query.
+pub trait JsonSource: Send + Sync {
+    /// Look up a key given as text. `Ok(None)` on a miss.
+    fn lookup_json(&self, key: &str) -> Result<Option<Value>, KvError>;
+
+    /// Fields reported by `GET /v1/stats`.
+    fn stats(&self) -> Map<String, Value>;
+}
+
+/// Keys are parsed with `FromStr`; text that doesn't parse is a miss.
+impl<K, V, H> JsonSource for VerifiedKvStore<K, V, H>
+where
+    K: KvKey + FromStr + Sync,
+    V: Clone + Serialize + Send + Sync,
+    H: StoreHasher<K> + Sync,
+{
+    fn lookup_json(&self, key: &str) -> Result<Option<Value>, KvError> {
+        let Ok(key) = key.parse::<K>() else {
+            return Ok(None);
+        };
+        match self.get(&key) {
+            Ok(value) => Ok(Some(serde_json::to_value(value).map_err(io::Error::from)?)),
+            Err(KvError::KeyNotFoundFast) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn stats(&self) -> Map<String, Value> {
+        let mut stats = Map::new();
+        stats.insert("keys".into(), self.len().into());
+        stats.insert("hasher".into(), self.hasher_kind().to_string().into());
+        stats
+    }
+}
+
+/// Values that are valid UTF-8 are returned as strings (so files built with
+/// `String` values look the same as in the original store), others as byte
+/// arrays.
+impl JsonSource for StoreHandle {
+    fn lookup_json(&self, key: &str) -> Result<Option<Value>, KvError> {
+        match self.store().get(key.as_bytes()) {
+            Ok(value) => Ok(Some(match std::str::from_utf8(value) {
+                Ok(text) => Value::from(text),
+                Err(_) => Value::from(value),
+            })),
+            Err(KvError::KeyNotFoundFast) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn stats(&self) -> Map<String, Value> {
+        let store = self.store();
+        let mut stats = Map::new();
+        stats.insert("keys".into(), store.len().into());
+        stats.insert("hasher".into(), store.hasher_kind().to_string().into());
+        stats.insert("file".into(), self.path().display().to_string().into());
+        stats.insert("reloads".into(), self.reloads().into());
+        stats
+    }
+}
+
+/// Serve HTTP clients on `listener` forever.
+pub fn serve<S>(listener: TcpListener, source: Arc<S>) -> io::Result<()>
+where
+    S: JsonSource + ?Sized + 'static,
+{
+    serve_with(listener, source, handle_connection::<S>)
+}
+
+/// Answer requests on one connection until the client disconnects or asks
+/// to close it.
+pub fn handle_connection<S>(stream: TcpStream, source: &S) -> io::Result<()>
+where
+    S: JsonSource + ?Sized,
+{
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    loop {
+        let request = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => return writer.flush(),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                write_response(&mut writer, 400, &error_body(e), true)?;
+                return writer.flush();
+            }
+            Err(e) => return Err(e),
+        };
+
+        let (status, body) = route(&request, source);
+        write_response(&mut writer, status, &body, request.close)?;
+        writer.flush()?;
+        if request.close {
+            return Ok(());
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    target: String,
+    body: Vec<u8>,
+    /// Close the connection after responding.
+    close: bool,
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+    // Tolerate blank lines between requests
+    let line = loop {
+        match read_line(reader, MAX_LINE_LEN)? {
+            None => return Ok(None),
+            Some(line) if line.is_empty() => continue,
+            Some(line) => break line,
+        }
+    };
+    let line = String::from_utf8(line).map_err(|_| invalid("malformed request line"))?;
+    let mut parts = line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("malformed request line"));
+    };
+    let mut close = version != "HTTP/1.1";
+
+    let mut content_length = 0;
+    for count in 0.. {
+        let line = read_line(reader, MAX_LINE_LEN)?.ok_or_else(|| invalid("truncated headers"))?;
+        if line.is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Err(invalid("too many headers"));
+        }
+        let line = String::from_utf8_lossy(&line);
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid("malformed header"))?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .parse()
+                .ok()
+                .filter(|&len| len <= MAX_BODY_LEN)
+                .ok_or_else(|| invalid("invalid content length"))?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(invalid("chunked request bodies are not supported"));
+        } else if name.eq_ignore_ascii_case("connection") {
+            close = !value.eq_ignore_ascii_case("keep-alive");
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        body,
+        close,
+    }))
+}
+
+/// Handle one request, returning the status code and JSON body.
+fn route<S: JsonSource + ?Sized>(request: &Request, source: &S) -> (u16, Value) {
+    let path = request.target.split('?').next().unwrap_or_default();
+    let method = request.method.as_str();
+
+    match path {
+        "/v1/health" if method == "GET" => (200, json!({ "status": "ok" })),
+        "/v1/stats" if method == "GET" => (200, Value::Object(source.stats())),
+        "/v1/batch" if method == "POST" => batch(&request.body, source),
+        "/v1/health" | "/v1/stats" | "/v1/batch" => method_not_allowed(),
+        _ => match path.strip_prefix("/v1/keys/") {
+            Some(key) if !key.is_empty() && method == "GET" => {
+                let Some(key) = percent_decode(key) else {
+                    return (400, json!({ "error": "invalid percent-encoding in key" }));
+                };
+                match source.lookup_json(&key) {
+                    Ok(Some(value)) => (200, json!({ "key": key, "value": value })),
+                    Ok(None) => (404, json!({ "error": "key not found", "key": key })),
+                    Err(e) => (500, error_body(e)),
+                }
+            }
+            Some(key) if !key.is_empty() => method_not_allowed(),
+            _ => (404, json!({ "error": "not found" })),
+        },
+    }
+}
+
+/// `POST /v1/batch`: the body is a JSON array of string or integer keys.
+fn batch<S: JsonSource + ?Sized>(body: &[u8], source: &S) -> (u16, Value) {
+    let keys: Vec<Value> = match serde_json::from_slice(body) {
+        Ok(keys) => keys,
+        Err(e) => {
+            return (
+                400,
+                json!({ "error": format!("invalid batch body: {}", e) }),
+            )
+        }
+    };
+
+    let mut results = Vec::with_capacity(keys.len());
+    for key in keys {
+        let key = match key {
+            Value::String(key) => key,
+            Value::Number(n) => n.to_string(),
+            _ => return (400, json!({ "error": "keys must be strings or numbers" })),
+        };
+        results.push(match source.lookup_json(&key) {
+            Ok(Some(value)) => json!({ "key": key, "found": true, "value": value }),
+            Ok(None) => json!({ "key": key, "found": false }),
+            Err(e) => return (500, error_body(e)),
+        });
+    }
+    (200, json!({ "results": results }))
+}
+
+fn method_not_allowed() -> (u16, Value) {
+    (405, json!({ "error": "method not allowed" }))
+}
+
+fn error_body(err: impl std::fmt::Display) -> Value {
+    json!({ "error": err.to_string() })
+}
+
+/// Decode `%XX` escapes. `None` if an escape is malformed or the result
+/// isn't UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            // from_str_radix alone would also take a sign: "%+f"
+            let hex = bytes.get(i + 1..i + 3)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn write_response<W: Write>(out: &mut W, status: u16, body: &Value, close: bool) -> io::Result<()> {
+    let body = body.to_string();
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    write!(
+        out,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+        status,
+        reason,
+        body.len()
+    )?;
+    if close {
+        out.write_all(b"Connection: close\r\n")?;
+    }
+    out.write_all(b"\r\n")?;
+    out.write_all(body.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::tests::roundtrip;
+    use crate::test_util::write_store;
+    use crate::VerifiedKvStoreBuilder;
+
+    fn get(target: &str) -> Request {
+        Request {
+            method: "GET".to_string(),
+            target: target.to_string(),
+            body: Vec::new(),
+            close: false,
+        }
+    }
+
+    fn post(target: &str, body: &str) -> Request {
+        Request {
+            method: "POST".to_string(),
+            body: body.as_bytes().to_vec(),
+            ..get(target)
+        }
+    }
+
+    fn response(status: &str, body: &str, close: bool) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n{}",
+            status,
+            body.len(),
+            if close { "Connection: close\r\n" } else { "" },
+            body
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_routes_on_typed_store() {
+        let store = VerifiedKvStoreBuilder::<u64, Vec<u32>>::new()
+            .insert(1, vec![10, 11])
+            .insert(2, vec![])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            route(&get("/v1/keys/1"), &store),
+            (200, json!({ "key": "1", "value": [10, 11] }))
+        );
+        assert_eq!(route(&get("/v1/keys/3"), &store).0, 404);
+        // Keys that don't parse as the key type are misses
+        assert_eq!(route(&get("/v1/keys/abc"), &store).0, 404);
+        assert_eq!(
+            route(&post("/v1/batch", "[2, \"1\", 9]"), &store),
+            (
+                200,
+                json!({ "results": [
+                    { "key": "2", "found": true, "value": [] },
+                    { "key": "1", "found": true, "value": [10, 11] },
+                    { "key": "9", "found": false },
+                ] })
+            )
+        );
+        assert_eq!(route(&get("/v1/stats"), &store).1["keys"], json!(2));
+        assert_eq!(
+            route(&get("/v1/health?verbose=1"), &store),
+            (200, json!({ "status": "ok" }))
+        );
+
+        assert_eq!(route(&post("/v1/batch", "{\"keys\": 1}"), &store).0, 400);
+        assert_eq!(route(&post("/v1/batch", "[[1]]"), &store).0, 400);
+        assert_eq!(route(&get("/v1/batch"), &store).0, 405);
+        assert_eq!(route(&post("/v1/keys/1", ""), &store).0, 405);
+        assert_eq!(route(&get("/v1/keys/"), &store).0, 404);
+        assert_eq!(route(&get("/"), &store).0, 404);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("user%3A1").as_deref(), Some("user:1"));
+        assert_eq!(percent_decode("a%20b%2Fc").as_deref(), Some("a b/c"));
+        assert_eq!(percent_decode("caf%C3%A9").as_deref(), Some("café"));
+        assert_eq!(percent_decode("bad%2"), None);
+        assert_eq!(percent_decode("bad%zz"), None);
+        assert_eq!(percent_decode("%ff"), None);
+        assert_eq!(percent_decode("%+f"), None);
+        assert_eq!(percent_decode("%-0"), None);
+    }
+
+    #[test]
+    fn test_serve_store_file() {
+        let path = "/tmp/test_http_serve.bin";
+        write_store(path, &[("user:1", "alice")]);
+        let mut stream = crate::server::tests::connect(path, serve::<StoreHandle>);
+
+        roundtrip(
+            &mut stream,
+            b"GET /v1/keys/user%3A1 HTTP/1.1\r\nHost: x\r\n\r\n",
+            &response("200 OK", r#"{"key":"user:1","value":"alice"}"#, false),
+        );
+        let body = r#"["user:1","nope"]"#;
+        roundtrip(
+            &mut stream,
+            format!(
+                "POST /v1/batch HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .as_bytes(),
+            &response(
+                "200 OK",
+                r#"{"results":[{"found":true,"key":"user:1","value":"alice"},{"found":false,"key":"nope"}]}"#,
+                false,
+            ),
+        );
+        roundtrip(
+            &mut stream,
+            b"GET /v1/health HTTP/1.1\r\nConnection: close\r\n\r\n",
+            &response("200 OK", r#"{"status":"ok"}"#, true),
+        );
+
+        std::fs::remove_file(path).ok();
+    }
+}
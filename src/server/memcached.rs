@@ -0,0 +1,363 @@
+//! Memcached text and meta protocol frontend.
+//!
+//! Supports `get`/`gets` from the text protocol and `mg`/`mn` from the meta
+//! protocol, plus `version`, `stats`, `verbosity` and `quit`. A key is a miss
+//! exactly when the store reports [`KvError::KeyNotFoundFast`]; any other
+//! lookup error is answered with `SERVER_ERROR`.
+//!
+//! Storage commands (`set`, `cas`, `ms`, ...) have their data block read and
+//! discarded, then get a `SERVER_ERROR`, so a client that tries to write stays
+//! in sync with the stream. Client flags are always 0 and items never expire.
+//! The CAS value is derived from the reload count, so it changes whenever a
+//! new store file is swapped in.
+
+use super::{read_line, serve as serve_with, StoreHandle};
+use crate::byte_store::ByteStore;
+use crate::error::KvError;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+/// Longest key memcached accepts.
+const MAX_KEY_LEN: usize = 250;
+
+/// Longest accepted command line. Enough for a `get` with a few hundred keys.
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// Text protocol storage commands, with the position of their `<bytes>` token.
+const STORAGE_COMMANDS: &[(&[u8], usize)] = &[
+    (b"set", 4),
+    (b"add", 4),
+    (b"replace", 4),
+    (b"append", 4),
+    (b"prepend", 4),
+    (b"cas", 4),
+    (b"ms", 2),
+];
+
+/// Commands that would modify data but carry no data block.
+const WRITE_COMMANDS: &[&[u8]] = &[
+    b"delete",
+    b"incr",
+    b"decr",
+    b"touch",
+    b"gat",
+    b"gats",
+    b"flush_all",
+    b"md",
+    b"ma",
+];
+
+/// Serve memcached clients on `listener` forever.
+pub fn serve(listener: TcpListener, handle: Arc<StoreHandle>) -> io::Result<()> {
+    serve_with(listener, handle, handle_connection)
+}
+
+/// Answer commands on one connection until the client disconnects or sends `quit`.
+pub fn handle_connection(stream: TcpStream, handle: &StoreHandle) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    loop {
+        let line = match read_line(&mut reader, MAX_LINE_LEN) {
+            Ok(Some(line)) => line,
+            Ok(None) => return writer.flush(),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                write!(writer, "CLIENT_ERROR {}\r\n", e)?;
+                return writer.flush();
+            }
+            Err(e) => return Err(e),
+        };
+        let tokens: Vec<&[u8]> = line
+            .split(|&b| b == b' ')
+            .filter(|token| !token.is_empty())
+            .collect();
+
+        let quit = execute(&tokens, handle, &mut reader, &mut writer)?;
+        // Flush once the client has no more pipelined commands waiting
+        if quit || reader.buffer().is_empty() {
+            writer.flush()?;
+        }
+        if quit {
+            return Ok(());
+        }
+    }
+}
+
+/// Run one command. Returns `true` if the connection should be closed.
+fn execute<R: BufRead, W: Write>(
+    tokens: &[&[u8]],
+    handle: &StoreHandle,
+    reader: &mut R,
+    out: &mut W,
+) -> io::Result<bool> {
+    let Some((&command, args)) = tokens.split_first() else {
+        out.write_all(b"ERROR\r\n")?;
+        return Ok(false);
+    };
+
+    match command {
+        b"get" | b"gets" => {
+            if args.is_empty() || args.iter().any(|key| key.len() > MAX_KEY_LEN) {
+                return client_error(out, "bad command line format");
+            }
+            let store = handle.store();
+            let cas = cas_unique(handle);
+            for &key in args {
+                let value = match lookup(&*store, key) {
+                    Ok(Some(value)) => value,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        server_error(out, e)?;
+                        return Ok(false);
+                    }
+                };
+                out.write_all(b"VALUE ")?;
+                out.write_all(key)?;
+                if command == b"gets" {
+                    write!(out, " 0 {} {}\r\n", value.len(), cas)?;
+                } else {
+                    write!(out, " 0 {}\r\n", value.len())?;
+                }
+                out.write_all(value)?;
+                out.write_all(b"\r\n")?;
+            }
+            out.write_all(b"END\r\n")?;
+        }
+        b"mg" => meta_get(args, handle, out)?,
+        b"mn" => out.write_all(b"MN\r\n")?,
+        b"version" => write!(out, "VERSION learned-kv {}\r\n", env!("CARGO_PKG_VERSION"))?,
+        b"verbosity" => {
+            if args.last() != Some(&&b"noreply"[..]) {
+                out.write_all(b"OK\r\n")?;
+            }
+        }
+        b"stats" => stats(handle, out)?,
+        b"quit" => return Ok(true),
+        _ => {
+            if let Some(&(_, pos)) = STORAGE_COMMANDS.iter().find(|(name, _)| *name == command) {
+                let len = tokens
+                    .get(pos)
+                    .and_then(|len| std::str::from_utf8(len).ok())
+                    .and_then(|len| len.parse::<u64>().ok())
+                    .and_then(|len| len.checked_add(2));
+                let Some(len) = len else {
+                    return client_error(out, "bad data chunk");
+                };
+                // Skip the data block and its CRLF
+                let skipped = io::copy(&mut Read::take(&mut *reader, len), &mut io::sink())?;
+                if skipped < len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            } else if !WRITE_COMMANDS.contains(&command) {
+                out.write_all(b"ERROR\r\n")?;
+                return Ok(false);
+            }
+
+            // Meta commands always reply; text commands honour `noreply`
+            if command.starts_with(b"m") || args.last() != Some(&&b"noreply"[..]) {
+                out.write_all(b"SERVER_ERROR store is read-only\r\n")?;
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Look up `key`, mapping `KeyNotFoundFast` to `None`.
+fn lookup<'a>(store: &'a dyn ByteStore, key: &[u8]) -> Result<Option<&'a [u8]>, KvError> {
+    match store.get(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(KvError::KeyNotFoundFast) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn server_error<W: Write>(out: &mut W, err: KvError) -> io::Result<()> {
+    write!(out, "SERVER_ERROR {}\r\n", err)
+}
+
+/// `mg <key> <flags>*`: answers `VA` with a value, `HD` without, `EN` on a miss.
+fn meta_get<W: Write>(args: &[&[u8]], handle: &StoreHandle, out: &mut W) -> io::Result<()> {
+    let Some((&key, flags)) = args.split_first() else {
+        return client_error(out, "bad command line format").map(drop);
+    };
+    if key.len() > MAX_KEY_LEN {
+        return client_error(out, "bad command line format").map(drop);
+    }
+
+    let mut want_value = false;
+    let mut quiet = false;
+    for flag in flags {
+        match flag[0] {
+            b'v' => want_value = true,
+            b'q' => quiet = true,
+            b'k' | b's' | b'c' | b'f' | b't' | b'O' => {}
+            _ => return client_error(out, "invalid flag").map(drop),
+        }
+    }
+
+    let store = handle.store();
+    let value = match lookup(&*store, key) {
+        Ok(Some(value)) => value,
+        Ok(None) if quiet => return Ok(()),
+        Ok(None) => return out.write_all(b"EN\r\n"),
+        Err(e) => return server_error(out, e),
+    };
+
+    if want_value {
+        write!(out, "VA {}", value.len())?;
+    } else {
+        out.write_all(b"HD")?;
+    }
+    for flag in flags {
+        match flag[0] {
+            b'k' => {
+                out.write_all(b" k")?;
+                out.write_all(key)?;
+            }
+            b's' => write!(out, " s{}", value.len())?,
+            b'c' => write!(out, " c{}", cas_unique(handle))?,
+            b'f' => out.write_all(b" f0")?,
+            b't' => out.write_all(b" t-1")?,
+            b'O' => {
+                out.write_all(b" ")?;
+                out.write_all(flag)?;
+            }
+            _ => {}
+        }
+    }
+    out.write_all(b"\r\n")?;
+    if want_value {
+        out.write_all(value)?;
+        out.write_all(b"\r\n")?;
+    }
+    Ok(())
+}
+
+fn stats<W: Write>(handle: &StoreHandle, out: &mut W) -> io::Result<()> {
+    let store = handle.store();
+    write!(out, "STAT pid {}\r\n", std::process::id())?;
+    write!(
+        out,
+        "STAT version learned-kv {}\r\n",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    write!(out, "STAT curr_items {}\r\n", store.len())?;
+    write!(out, "STAT hasher {}\r\n", store.hasher_kind())?;
+    write!(out, "STAT reloads {}\r\n", handle.reloads())?;
+    out.write_all(b"END\r\n")
+}
+
+/// CAS value shared by every item of the current store.
+fn cas_unique(handle: &StoreHandle) -> u64 {
+    handle.reloads() + 1
+}
+
+fn client_error<W: Write>(out: &mut W, message: &str) -> io::Result<bool> {
+    write!(out, "CLIENT_ERROR {}\r\n", message)?;
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::tests::roundtrip;
+    use crate::test_util::write_store;
+
+    fn connect(path: &str) -> TcpStream {
+        crate::server::tests::connect(path, serve)
+    }
+
+    #[test]
+    fn test_text_get() {
+        let path = "/tmp/test_memcached_text_get.bin";
+        write_store(path, &[("user:1", "alice"), ("user:2", "bob")]);
+        let mut stream = connect(path);
+
+        roundtrip(
+            &mut stream,
+            b"get user:1\r\n",
+            b"VALUE user:1 0 5\r\nalice\r\nEND\r\n",
+        );
+        roundtrip(&mut stream, b"get nope\r\n", b"END\r\n");
+        roundtrip(
+            &mut stream,
+            b"gets user:2 nope user:1\r\n",
+            b"VALUE user:2 0 3 1\r\nbob\r\nVALUE user:1 0 5 1\r\nalice\r\nEND\r\n",
+        );
+        roundtrip(
+            &mut stream,
+            b"get\r\n",
+            b"CLIENT_ERROR bad command line format\r\n",
+        );
+        roundtrip(&mut stream, b"bogus\r\n", b"ERROR\r\n");
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_meta_get() {
+        let path = "/tmp/test_memcached_meta_get.bin";
+        write_store(path, &[("k", "value")]);
+        let mut stream = connect(path);
+
+        roundtrip(&mut stream, b"mg k v\r\n", b"VA 5\r\nvalue\r\n");
+        roundtrip(&mut stream, b"mg k s k Oab\r\n", b"HD s5 kk Oab\r\n");
+        roundtrip(
+            &mut stream,
+            b"mg k v c f t\r\n",
+            b"VA 5 c1 f0 t-1\r\nvalue\r\n",
+        );
+        roundtrip(&mut stream, b"mg x v\r\n", b"EN\r\n");
+        // Quiet misses are suppressed; `mn` marks the end of the batch
+        roundtrip(
+            &mut stream,
+            b"mg x v q\r\nmg k q\r\nmn\r\n",
+            b"HD\r\nMN\r\n",
+        );
+        roundtrip(
+            &mut stream,
+            b"mg k N30\r\n",
+            b"CLIENT_ERROR invalid flag\r\n",
+        );
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_writes_are_rejected_in_sync() {
+        let path = "/tmp/test_memcached_writes.bin";
+        write_store(path, &[("k", "v")]);
+        let mut stream = connect(path);
+
+        roundtrip(
+            &mut stream,
+            b"set k 0 0 5\r\nhello\r\n",
+            b"SERVER_ERROR store is read-only\r\n",
+        );
+        roundtrip(
+            &mut stream,
+            b"ms k 2 T0\r\nhi\r\n",
+            b"SERVER_ERROR store is read-only\r\n",
+        );
+        roundtrip(
+            &mut stream,
+            b"delete k\r\n",
+            b"SERVER_ERROR store is read-only\r\n",
+        );
+        // `noreply` writes are dropped silently; the data block is still skipped
+        roundtrip(
+            &mut stream,
+            b"set k 0 0 3 noreply\r\nget\r\nget k\r\n",
+            b"VALUE k 0 1\r\nv\r\nEND\r\n",
+        );
+        roundtrip(
+            &mut stream,
+            b"set k 0 0 18446744073709551615\r\n",
+            b"CLIENT_ERROR bad data chunk\r\n",
+        );
+        roundtrip(&mut stream, b"get k\r\n", b"VALUE k 0 1\r\nv\r\nEND\r\n");
+
+        std::fs::remove_file(path).ok();
+    }
+}
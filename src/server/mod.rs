@@ -0,0 +1,307 @@
+//! Network frontends that serve a store file read-only.
+//!
+//! All frontends share a [`StoreHandle`], which owns the loaded store and
+//! swaps in a new one when the file changes on disk. Stores are loaded with
+//! [`load_byte_store`], so any file with `String` keys and `String` or
+//! `Vec<u8>` values can be served. Files are read into memory; the format
+//! rebuilds the MPHF on load, so there is nothing to mmap.
+//!
+//! Each connection runs on its own thread, up to [`MAX_CONNECTIONS`] at a
+//! time. Lookups take a snapshot of the current store, so a reload never
+//! disturbs a request in flight.
+//!
+//! Reloads, refused connections and accept errors are reported through the
+//! [`log`] crate; install a logger to see them.
+
+pub mod http;
+pub mod memcached;
+pub mod resp;
+
+use crate::byte_store::{load_byte_store, ByteStore};
+use crate::error::KvError;
+use std::io::{self, BufRead, Read};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// Modification time and size, used to detect a replaced file.
+type FileStamp = (SystemTime, u64);
+
+fn file_stamp(path: &Path) -> io::Result<FileStamp> {
+    let meta = std::fs::metadata(path)?;
+    Ok((meta.modified()?, meta.len()))
+}
+
+/// A store file loaded for serving, reloaded when the file changes.
+pub struct StoreHandle {
+    path: PathBuf,
+    current: RwLock<Arc<dyn ByteStore>>,
+    stamp: Mutex<FileStamp>,
+    reloads: AtomicU64,
+}
+
+impl StoreHandle {
+    /// Load the store at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, KvError> {
+        let path = path.as_ref().to_path_buf();
+        let stamp = file_stamp(&path)?;
+        let store = load_byte_store(&path)?;
+        Ok(Self {
+            path,
+            current: RwLock::new(Arc::from(store)),
+            stamp: Mutex::new(stamp),
+            reloads: AtomicU64::new(0),
+        })
+    }
+
+    /// The currently loaded store.
+    pub fn store(&self) -> Arc<dyn ByteStore> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Path of the served file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of successful reloads since opening.
+    pub fn reloads(&self) -> u64 {
+        self.reloads.load(Ordering::Relaxed)
+    }
+
+    /// Reload the store if the file's modification time or size changed.
+    ///
+    /// Returns whether a new store was swapped in. On error the current store
+    /// stays in place.
+    pub fn reload_if_changed(&self) -> Result<bool, KvError> {
+        let mut stamp = self.stamp.lock().unwrap();
+        let new_stamp = file_stamp(&self.path)?;
+        if new_stamp == *stamp {
+            return Ok(false);
+        }
+
+        // Record the stamp first, so a bad file is reported once, not on every check
+        *stamp = new_stamp;
+        let store = load_byte_store(&self.path)?;
+        *self.current.write().unwrap() = Arc::from(store);
+        self.reloads.fetch_add(1, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    /// Check for changes every `interval` on a background thread.
+    ///
+    /// Reloads are logged at info level, failed ones at error level; they
+    /// are retried on the next change.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let handle = Arc::clone(self);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            match handle.reload_if_changed() {
+                Ok(true) => log::info!(
+                    "reloaded {} ({} keys)",
+                    handle.path.display(),
+                    handle.store().len()
+                ),
+                Ok(false) => {}
+                Err(e) => log::error!("reload of {} failed: {}", handle.path.display(), e),
+            }
+        })
+    }
+}
+
+/// Most connections served at once by [`serve`]. Connections beyond this
+/// are closed right after they are accepted.
+pub const MAX_CONNECTIONS: usize = 1024;
+
+/// Pause after a failed accept, so that running out of file descriptors
+/// doesn't turn the accept loop into a busy loop.
+const ACCEPT_ERROR_PAUSE: Duration = Duration::from_millis(50);
+
+/// Accept connections forever, running `handler` for each on its own thread.
+///
+/// `state` is usually a [`StoreHandle`], but any shared state works. At most
+/// [`MAX_CONNECTIONS`] connections are served at once. Failed accepts are
+/// logged and don't end the loop.
+pub fn serve<T, F>(listener: TcpListener, state: Arc<T>, handler: F) -> io::Result<()>
+where
+    T: ?Sized + Send + Sync + 'static,
+    F: Fn(TcpStream, &T) -> io::Result<()> + Copy + Send + 'static,
+{
+    serve_limited(listener, state, handler, MAX_CONNECTIONS)
+}
+
+/// [`serve`] with at most `max_connections` connections at once.
+fn serve_limited<T, F>(
+    listener: TcpListener,
+    state: Arc<T>,
+    handler: F,
+    max_connections: usize,
+) -> io::Result<()>
+where
+    T: ?Sized + Send + Sync + 'static,
+    F: Fn(TcpStream, &T) -> io::Result<()> + Copy + Send + 'static,
+{
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("accept failed: {}", e);
+                thread::sleep(ACCEPT_ERROR_PAUSE);
+                continue;
+            }
+        };
+        if open.fetch_add(1, Ordering::AcqRel) >= max_connections {
+            open.fetch_sub(1, Ordering::AcqRel);
+            log::warn!(
+                "refused connection from {:?}: {} connections open",
+                stream.peer_addr().ok(),
+                max_connections
+            );
+            continue;
+        }
+
+        let state = Arc::clone(&state);
+        let slot = ConnectionSlot(Arc::clone(&open));
+        thread::spawn(move || {
+            let _slot = slot;
+            // Client I/O errors only end that connection
+            let _ = handler(stream, &state);
+        });
+    }
+    Ok(())
+}
+
+/// One of the connections counted by [`serve_limited`], released on drop.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Read a CRLF (or LF) terminated line of at most `limit` bytes, without the
+/// terminator. `Ok(None)` at end of stream.
+fn read_line<R: BufRead>(reader: &mut R, limit: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let limit = limit as u64 + 2;
+    if Read::take(&mut *reader, limit).read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    } else if line.len() as u64 == limit {
+        return Err(invalid("line too long"));
+    }
+    Ok(Some(line))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_util::write_store;
+    use std::io::Write;
+
+    /// Start a server for `path` on an ephemeral port and connect to it.
+    pub(crate) fn connect(
+        path: &str,
+        serve: fn(TcpListener, Arc<StoreHandle>) -> io::Result<()>,
+    ) -> TcpStream {
+        let handle = Arc::new(StoreHandle::open(path).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, handle));
+        TcpStream::connect(addr).unwrap()
+    }
+
+    /// Send `request` and read until `expected.len()` bytes arrived.
+    pub(crate) fn roundtrip(stream: &mut TcpStream, request: &[u8], expected: &[u8]) {
+        stream.write_all(request).unwrap();
+        let mut reply = vec![0; expected.len()];
+        stream.read_exact(&mut reply).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&reply),
+            String::from_utf8_lossy(expected)
+        );
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let path = "/tmp/test_store_handle_reload.bin";
+        write_store(path, &[("a", "1")]);
+
+        let handle = StoreHandle::open(path).unwrap();
+        assert_eq!(handle.store().get(b"a").unwrap(), b"1");
+        assert!(!handle.reload_if_changed().unwrap());
+
+        let old = handle.store();
+        write_store(path, &[("a", "2"), ("b", "3")]);
+        assert!(handle.reload_if_changed().unwrap());
+        assert_eq!(handle.reloads(), 1);
+        assert_eq!(handle.store().get(b"a").unwrap(), b"2");
+        assert_eq!(handle.store().len(), 2);
+        // Snapshots taken before the reload keep working
+        assert_eq!(old.get(b"a").unwrap(), b"1");
+
+        // A broken file leaves the old store in place
+        std::fs::write(path, b"garbage").unwrap();
+        assert!(handle.reload_if_changed().is_err());
+        assert!(!handle.reload_if_changed().unwrap());
+        assert_eq!(handle.store().get(b"b").unwrap(), b"3");
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Echo one byte back, then hold the connection until the client closes it
+        let echo = |mut stream: TcpStream, _: &()| {
+            let mut byte = [0];
+            while stream.read(&mut byte)? == 1 {
+                stream.write_all(&byte)?;
+            }
+            Ok(())
+        };
+        thread::spawn(move || serve_limited(listener, Arc::new(()), echo, 1));
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        roundtrip(&mut first, b"a", b"a");
+
+        // Over the limit: closed without being served
+        let mut second = TcpStream::connect(addr).unwrap();
+        second.write_all(b"b").unwrap();
+        let mut reply = Vec::new();
+        assert!(matches!(second.read_to_end(&mut reply), Ok(0) | Err(_)));
+
+        // Closing the first connection frees its slot
+        drop(first);
+        let mut third = (0..100)
+            .find_map(|_| {
+                let mut stream = TcpStream::connect(addr).unwrap();
+                stream.write_all(b"c").unwrap();
+                let mut byte = [0];
+                match stream.read(&mut byte) {
+                    Ok(1) => Some(stream),
+                    _ => {
+                        thread::sleep(Duration::from_millis(10));
+                        None
+                    }
+                }
+            })
+            .expect("slot was not released");
+        roundtrip(&mut third, b"d", b"d");
+    }
+}
@@ -0,0 +1,383 @@
+//! Redis protocol (RESP2) frontend.
+//!
+//! Supports the read commands `GET`, `MGET`, `EXISTS`, `DBSIZE` and `INFO`,
+//! plus `PING`, `ECHO`, `SELECT 0`, `COMMAND` and `QUIT` so that `redis-cli`
+//! and common client libraries work unchanged. Write commands are answered
+//! with a `READONLY` error. Both multibulk and inline commands are accepted,
+//! and pipelined commands are answered in order.
+
+use super::{invalid, read_line, serve as serve_with, StoreHandle};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+/// Largest accepted bulk string. Arguments are keys, so this and the limits
+/// below only bound what a misbehaving client can make us allocate.
+const MAX_BULK_LEN: usize = 1024 * 1024;
+
+/// Most arguments accepted in one command, the command name included.
+const MAX_ARGS: usize = 1024;
+
+/// Most bytes accepted in one command, all arguments together.
+const MAX_COMMAND_BYTES: usize = 4 * MAX_BULK_LEN;
+
+/// Commands that would modify data in Redis.
+const WRITE_COMMANDS: &[&str] = &[
+    "APPEND",
+    "DECR",
+    "DECRBY",
+    "DEL",
+    "EXPIRE",
+    "EXPIREAT",
+    "FLUSHALL",
+    "FLUSHDB",
+    "GETDEL",
+    "GETEX",
+    "GETSET",
+    "HDEL",
+    "HSET",
+    "INCR",
+    "INCRBY",
+    "INCRBYFLOAT",
+    "LPUSH",
+    "MSET",
+    "MSETNX",
+    "PERSIST",
+    "PEXPIRE",
+    "PSETEX",
+    "RENAME",
+    "RPUSH",
+    "SADD",
+    "SET",
+    "SETEX",
+    "SETNX",
+    "SETRANGE",
+    "UNLINK",
+    "ZADD",
+];
+
+/// Serve RESP clients on `listener` forever.
+pub fn serve(listener: TcpListener, handle: Arc<StoreHandle>) -> io::Result<()> {
+    serve_with(listener, handle, handle_connection)
+}
+
+/// Answer commands on one connection until the client disconnects or sends `QUIT`.
+pub fn handle_connection(stream: TcpStream, handle: &StoreHandle) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    loop {
+        let args = match read_command(&mut reader) {
+            Ok(Some(args)) => args,
+            Ok(None) => return writer.flush(),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                write_error(&mut writer, &format!("ERR Protocol error: {}", e))?;
+                return writer.flush();
+            }
+            Err(e) => return Err(e),
+        };
+        if args.is_empty() {
+            continue;
+        }
+
+        let quit = execute(&args, handle, &mut writer)?;
+        // Flush once the client has no more pipelined commands waiting
+        if quit || reader.buffer().is_empty() {
+            writer.flush()?;
+        }
+        if quit {
+            return Ok(());
+        }
+    }
+}
+
+/// Run one command. Returns `true` if the connection should be closed.
+fn execute<W: Write>(args: &[Vec<u8>], handle: &StoreHandle, out: &mut W) -> io::Result<bool> {
+    let name = String::from_utf8_lossy(&args[0]).to_ascii_uppercase();
+    let params = &args[1..];
+
+    match name.as_str() {
+        "GET" => {
+            let [key] = params else {
+                return wrong_arity(out, "get");
+            };
+            match handle.store().get(key) {
+                Ok(value) => write_bulk(out, value)?,
+                Err(_) => write_null(out)?,
+            }
+        }
+        "MGET" => {
+            if params.is_empty() {
+                return wrong_arity(out, "mget");
+            }
+            let store = handle.store();
+            write!(out, "*{}\r\n", params.len())?;
+            for key in params {
+                match store.get(key) {
+                    Ok(value) => write_bulk(out, value)?,
+                    Err(_) => write_null(out)?,
+                }
+            }
+        }
+        "EXISTS" => {
+            if params.is_empty() {
+                return wrong_arity(out, "exists");
+            }
+            let store = handle.store();
+            let count = params.iter().filter(|key| store.contains_key(key)).count();
+            write!(out, ":{}\r\n", count)?;
+        }
+        "DBSIZE" => write!(out, ":{}\r\n", handle.store().len())?,
+        "INFO" => write_bulk(out, info(handle).as_bytes())?,
+        "PING" => match params {
+            [] => out.write_all(b"+PONG\r\n")?,
+            [message] => write_bulk(out, message)?,
+            _ => return wrong_arity(out, "ping"),
+        },
+        "ECHO" => {
+            let [message] = params else {
+                return wrong_arity(out, "echo");
+            };
+            write_bulk(out, message)?;
+        }
+        "SELECT" => match params {
+            [db] if db.as_slice() == b"0" => out.write_all(b"+OK\r\n")?,
+            [_] => write_error(out, "ERR DB index is out of range")?,
+            _ => return wrong_arity(out, "select"),
+        },
+        // Command introspection: report nothing rather than fail
+        "COMMAND" => out.write_all(b"*0\r\n")?,
+        "QUIT" => {
+            out.write_all(b"+OK\r\n")?;
+            return Ok(true);
+        }
+        _ if WRITE_COMMANDS.contains(&name.as_str()) => {
+            write_error(out, "READONLY this store is read-only")?
+        }
+        _ => write_error(
+            out,
+            &format!(
+                "ERR unknown command '{}'",
+                String::from_utf8_lossy(&args[0])
+            ),
+        )?,
+    }
+    Ok(false)
+}
+
+fn info(handle: &StoreHandle) -> String {
+    let store = handle.store();
+    format!(
+        "# Server\r\n\
+         learned_kv_version:{}\r\n\
+         store_file:{}\r\n\
+         hasher:{}\r\n\
+         reloads:{}\r\n\
+         \r\n\
+         # Keyspace\r\n\
+         db0:keys={},expires=0,avg_ttl=0\r\n",
+        env!("CARGO_PKG_VERSION"),
+        handle.path().display(),
+        store.hasher_kind(),
+        handle.reloads(),
+        store.len()
+    )
+}
+
+/// Read one command as a list of arguments. `Ok(None)` at end of stream.
+fn read_command<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<Vec<u8>>>> {
+    let Some(line) = read_line(reader, MAX_BULK_LEN)? else {
+        return Ok(None);
+    };
+
+    let Some(count) = line.strip_prefix(b"*") else {
+        // Inline command: space-separated words on one line
+        let args = line
+            .split(|b| b.is_ascii_whitespace())
+            .filter(|word| !word.is_empty())
+            .map(<[u8]>::to_vec)
+            .collect();
+        return Ok(Some(args));
+    };
+
+    let count = parse_len(count, MAX_ARGS)?;
+    let mut args = Vec::with_capacity(count);
+    let mut budget = MAX_COMMAND_BYTES;
+    for _ in 0..count {
+        let line =
+            read_line(reader, MAX_BULK_LEN)?.ok_or_else(|| invalid("unexpected end of stream"))?;
+        let len = line
+            .strip_prefix(b"$")
+            .ok_or_else(|| invalid("expected '$'"))?;
+        let len = parse_len(len, MAX_BULK_LEN)?;
+        budget = budget
+            .checked_sub(len)
+            .ok_or_else(|| invalid("command too large"))?;
+
+        let mut arg = vec![0; len + 2];
+        reader.read_exact(&mut arg)?;
+        if !arg.ends_with(b"\r\n") {
+            return Err(invalid("bulk string not terminated by CRLF"));
+        }
+        arg.truncate(len);
+        args.push(arg);
+    }
+    Ok(Some(args))
+}
+
+/// Parse a length of at most `max`.
+fn parse_len(digits: &[u8], max: usize) -> io::Result<usize> {
+    std::str::from_utf8(digits)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|&len| len <= max)
+        .ok_or_else(|| invalid("invalid length"))
+}
+
+fn write_bulk<W: Write>(out: &mut W, data: &[u8]) -> io::Result<()> {
+    write!(out, "${}\r\n", data.len())?;
+    out.write_all(data)?;
+    out.write_all(b"\r\n")
+}
+
+fn write_null<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(b"$-1\r\n")
+}
+
+fn write_error<W: Write>(out: &mut W, message: &str) -> io::Result<()> {
+    write!(out, "-{}\r\n", message)
+}
+
+fn wrong_arity<W: Write>(out: &mut W, command: &str) -> io::Result<bool> {
+    write_error(
+        out,
+        &format!("ERR wrong number of arguments for '{}' command", command),
+    )?;
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::tests::roundtrip;
+    use crate::test_util::write_store;
+    use std::io::Read;
+
+    fn connect(path: &str) -> TcpStream {
+        crate::server::tests::connect(path, serve)
+    }
+
+    #[test]
+    fn test_read_commands() {
+        let path = "/tmp/test_resp_read_commands.bin";
+        write_store(path, &[("user:1", "alice"), ("user:2", "bob")]);
+        let mut stream = connect(path);
+
+        roundtrip(
+            &mut stream,
+            b"*2\r\n$3\r\nGET\r\n$6\r\nuser:1\r\n",
+            b"$5\r\nalice\r\n",
+        );
+        roundtrip(
+            &mut stream,
+            b"*2\r\n$3\r\nget\r\n$4\r\nnope\r\n",
+            b"$-1\r\n",
+        );
+        roundtrip(
+            &mut stream,
+            b"*4\r\n$4\r\nMGET\r\n$6\r\nuser:2\r\n$1\r\nx\r\n$6\r\nuser:1\r\n",
+            b"*3\r\n$3\r\nbob\r\n$-1\r\n$5\r\nalice\r\n",
+        );
+        roundtrip(
+            &mut stream,
+            b"*4\r\n$6\r\nEXISTS\r\n$6\r\nuser:1\r\n$1\r\nx\r\n$6\r\nuser:1\r\n",
+            b":2\r\n",
+        );
+        roundtrip(&mut stream, b"DBSIZE\r\n", b":2\r\n");
+        roundtrip(&mut stream, b"PING\r\n", b"+PONG\r\n");
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_writes_and_unknown_commands_fail() {
+        let path = "/tmp/test_resp_writes.bin";
+        write_store(path, &[("k", "v")]);
+        let mut stream = connect(path);
+
+        roundtrip(
+            &mut stream,
+            b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nw\r\n",
+            b"-READONLY this store is read-only\r\n",
+        );
+        roundtrip(&mut stream, b"FOO\r\n", b"-ERR unknown command 'FOO'\r\n");
+        roundtrip(
+            &mut stream,
+            b"GET\r\n",
+            b"-ERR wrong number of arguments for 'get' command\r\n",
+        );
+        // Store unchanged
+        roundtrip(&mut stream, b"GET k\r\n", b"$1\r\nv\r\n");
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_pipelining_and_info() {
+        let path = "/tmp/test_resp_pipelining.bin";
+        write_store(path, &[("a", "1"), ("b", "2")]);
+        let mut stream = connect(path);
+
+        roundtrip(
+            &mut stream,
+            b"GET a\r\nGET b\r\nEXISTS c\r\n",
+            b"$1\r\n1\r\n$1\r\n2\r\n:0\r\n",
+        );
+
+        stream.write_all(b"INFO\r\nQUIT\r\n").unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        assert!(reply.contains("db0:keys=2"), "{}", reply);
+        assert!(reply.ends_with("+OK\r\n"), "{}", reply);
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_read_command_formats() {
+        let mut input: &[u8] = b"*2\r\n$3\r\nGET\r\n$3\r\na b\r\n  MGET  x y \r\n";
+        assert_eq!(
+            read_command(&mut input).unwrap(),
+            Some(vec![b"GET".to_vec(), b"a b".to_vec()])
+        );
+        assert_eq!(
+            read_command(&mut input).unwrap(),
+            Some(vec![b"MGET".to_vec(), b"x".to_vec(), b"y".to_vec()])
+        );
+        assert_eq!(read_command(&mut input).unwrap(), None);
+
+        let mut bad: &[u8] = b"*1\r\n+GET\r\n";
+        assert_eq!(
+            read_command(&mut bad).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_read_command_limits() {
+        let too_many = format!("*{}\r\n", MAX_ARGS + 1);
+        let err = read_command(&mut too_many.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "invalid length");
+
+        // Each argument is within MAX_BULK_LEN, together they are not
+        let mut command = b"*6\r\n$4\r\nMGET\r\n".to_vec();
+        let key = vec![b'k'; MAX_BULK_LEN];
+        for _ in 0..5 {
+            command.extend_from_slice(format!("${}\r\n", key.len()).as_bytes());
+            command.extend_from_slice(&key);
+            command.extend_from_slice(b"\r\n");
+        }
+        let err = read_command(&mut command.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "command too large");
+    }
+}
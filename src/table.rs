@@ -0,0 +1,381 @@
+//! Tables of typed value columns over one verified key set.
+//!
+//! Several attributes of the same keys (price, stock, category, ...) as
+//! separate [`VerifiedKvStore`]s each pay for an MPHF and a copy of the keys.
+//! A [`KvTable`] keeps one MPHF and one key array, and any number of named
+//! columns with a value per key. Columns can be added and removed at any
+//! time without touching the index.
+
+use crate::error::KvError;
+use crate::hasher::{DefaultHasherFor, KvKey, StoreHasher};
+use crate::verified_kv_store::{HashedKey, VerifiedKvStore, VerifiedKvStoreBuilder};
+use std::any::{type_name, Any};
+
+/// A named column, values in slot order.
+struct StoredColumn {
+    name: String,
+    values: Box<dyn Any + Send + Sync>,
+    type_name: &'static str,
+    value_size: usize,
+}
+
+impl StoredColumn {
+    fn values<T: 'static>(&self) -> Result<&[T], KvError> {
+        match self.values.downcast_ref::<Vec<T>>() {
+            Some(values) => Ok(values),
+            None => Err(KvError::ColumnType {
+                column: self.name.clone(),
+                expected: type_name::<T>(),
+                actual: self.type_name,
+            }),
+        }
+    }
+}
+
+/// One verified key set with named, typed value columns.
+///
+/// # Example
+/// ```
+/// use learned_kv::table::KvTable;
+///
+/// let mut table = KvTable::<u64>::from_keys(0..1000)?;
+/// table.add_column("price", |&id| id as f64 * 1.5)?;
+/// table.add_column("name", |id| format!("item-{id}"))?;
+///
+/// assert_eq!(*table.get_column::<f64>("price", &10)?, 15.0);
+/// assert_eq!(table.get_column::<String>("name", &10)?, "item-10");
+///
+/// // Look several columns up for one hash
+/// let row = table.row(&20).unwrap();
+/// assert_eq!(row.get::<String>("name")?, "item-20");
+/// # Ok::<(), learned_kv::KvError>(())
+/// ```
+pub struct KvTable<K, H = DefaultHasherFor<K>>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    index: VerifiedKvStore<K, (), H>,
+    columns: Vec<StoredColumn>,
+}
+
+impl<K, H> KvTable<K, H>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    /// Build a table without columns over `keys`. Repeated keys are kept once.
+    ///
+    /// Uses random seeds; build the index yourself and use
+    /// [`from_index`](Self::from_index) to choose them.
+    pub fn from_keys<I>(keys: I) -> Result<Self, KvError>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let index = VerifiedKvStoreBuilder::new()
+            .extend(keys.into_iter().map(|key| (key, ())))
+            .build()?;
+        Ok(Self::from_index(index))
+    }
+
+    /// Use the keys, seeds, prefilter and hash tags of `index` as they are.
+    pub fn from_index(index: VerifiedKvStore<K, (), H>) -> Self {
+        Self {
+            index,
+            columns: Vec::new(),
+        }
+    }
+
+    /// Turn `store` into a table with its values as the column `column`.
+    pub fn from_store<V>(store: VerifiedKvStore<K, V, H>, column: &str) -> Self
+    where
+        V: Send + Sync + 'static,
+    {
+        let (index, values) = store.split_values();
+        let mut table = Self::from_index(index);
+        table.push_column(column.to_string(), values);
+        table
+    }
+
+    /// Add the column `name`, computing each key's value with `value`.
+    ///
+    /// Only fills the new column; the MPHF and keys are left alone. Fails
+    /// with [`KvError::ColumnExists`] if the table already has `name`.
+    pub fn add_column<T, F>(&mut self, name: &str, value: F) -> Result<(), KvError>
+    where
+        T: Send + Sync + 'static,
+        F: FnMut(&K) -> T,
+    {
+        if self.find(name).is_some() {
+            return Err(KvError::ColumnExists(name.to_string()));
+        }
+        let values = self.index.keys().map(value).collect();
+        self.push_column(name.to_string(), values);
+        Ok(())
+    }
+
+    /// Remove the column `name`. Returns whether it existed.
+    pub fn remove_column(&mut self, name: &str) -> bool {
+        let before = self.columns.len();
+        self.columns.retain(|column| column.name != name);
+        self.columns.len() != before
+    }
+
+    /// Column names, in the order they were added.
+    pub fn column_names(&self) -> impl Iterator<Item = &str> {
+        self.columns.iter().map(|column| column.name.as_str())
+    }
+
+    /// Typed handle on the column `name`, for repeated lookups.
+    pub fn column<T: 'static>(&self, name: &str) -> Result<Column<'_, K, T, H>, KvError> {
+        let column = self
+            .find(name)
+            .ok_or_else(|| KvError::ColumnNotFound(name.to_string()))?;
+        Ok(Column {
+            index: &self.index,
+            values: column.values()?,
+        })
+    }
+
+    /// Value of `key` in the column `name`.
+    ///
+    /// Fails with [`KvError::ColumnNotFound`] or [`KvError::ColumnType`] for
+    /// a bad column, and like [`VerifiedKvStore::get`] for a missing key.
+    pub fn get_column<T: 'static>(&self, name: &str, key: &K) -> Result<&T, KvError> {
+        self.column(name)?.get(key)
+    }
+
+    /// Row of `key`, to read several columns for one lookup.
+    pub fn row(&self, key: &K) -> Option<Row<'_, K, H>> {
+        self.index.slot(key).map(|slot| Row { table: self, slot })
+    }
+
+    /// [`row`](Self::row) with a precomputed hash.
+    pub fn row_hashed(&self, key: &HashedKey<'_, K, H>) -> Option<Row<'_, K, H>> {
+        self.index
+            .slot_hashed(key)
+            .map(|slot| Row { table: self, slot })
+    }
+
+    /// Hash `key` once; see [`VerifiedKvStore::hash_key`].
+    pub fn hash_key<'k>(&self, key: &'k K) -> HashedKey<'k, K, H> {
+        self.index.hash_key(key)
+    }
+
+    /// Check if a key is in the table (accurate, no false positives).
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns true if the table holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Iterate over keys, in slot order like the columns.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.index.keys()
+    }
+
+    /// The key index shared by all columns, e.g. to save the keys.
+    pub fn index(&self) -> &VerifiedKvStore<K, (), H> {
+        &self.index
+    }
+
+    /// Approximate memory usage in bytes: the index plus each column's
+    /// values, not counting heap data the values own.
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.index.memory_usage_bytes()
+            + self
+                .columns
+                .iter()
+                .map(|column| column.value_size * self.len())
+                .sum::<usize>()
+    }
+
+    fn find(&self, name: &str) -> Option<&StoredColumn> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    fn push_column<T: Send + Sync + 'static>(&mut self, name: String, values: Vec<T>) {
+        self.columns.push(StoredColumn {
+            name,
+            values: Box::new(values),
+            type_name: type_name::<T>(),
+            value_size: std::mem::size_of::<T>(),
+        });
+    }
+}
+
+/// Typed view of one [`KvTable`] column.
+pub struct Column<'a, K, T, H = DefaultHasherFor<K>>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    index: &'a VerifiedKvStore<K, (), H>,
+    values: &'a [T],
+}
+
+impl<'a, K, T, H> Column<'a, K, T, H>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    /// Fast lookup with key verification; same contract as [`VerifiedKvStore::get`].
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Result<&'a T, KvError> {
+        match self.index.slot(key) {
+            Some(slot) => Ok(&self.values[slot]),
+            None => Err(KvError::KeyNotFoundFast),
+        }
+    }
+
+    /// Lookup with a precomputed hash; see [`VerifiedKvStore::get_hashed`].
+    #[inline(always)]
+    pub fn get_hashed(&self, key: &HashedKey<'_, K, H>) -> Result<&'a T, KvError> {
+        match self.index.slot_hashed(key) {
+            Some(slot) => Ok(&self.values[slot]),
+            None => Err(KvError::KeyNotFoundFast),
+        }
+    }
+
+    /// Iterate over (key, value) pairs, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a K, &'a T)> {
+        self.index.keys().zip(self.values)
+    }
+}
+
+/// The columns of one key in a [`KvTable`].
+pub struct Row<'a, K, H = DefaultHasherFor<K>>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    table: &'a KvTable<K, H>,
+    slot: usize,
+}
+
+impl<'a, K, H> Row<'a, K, H>
+where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    /// Value in the column `name`.
+    pub fn get<T: 'static>(&self, name: &str) -> Result<&'a T, KvError> {
+        let column = self
+            .table
+            .find(name)
+            .ok_or_else(|| KvError::ColumnNotFound(name.to_string()))?;
+        Ok(&column.values()?[self.slot])
+    }
+
+    /// The key of this row.
+    pub fn key(&self) -> &'a K {
+        self.table.index.key_at(self.slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::PortableHash;
+
+    #[test]
+    fn test_columns_share_one_index() {
+        let mut table = KvTable::<u64, PortableHash>::from_keys(0..10_000).unwrap();
+        table.add_column("double", |&k| k * 2).unwrap();
+        table.add_column("label", |k| format!("#{k}")).unwrap();
+        table.add_column("odd", |&k| k % 2 == 1).unwrap();
+        assert_eq!(
+            table.column_names().collect::<Vec<_>>(),
+            ["double", "label", "odd"]
+        );
+
+        let double = table.column::<u64>("double").unwrap();
+        for k in (0..10_000).step_by(37) {
+            assert_eq!(*double.get(&k).unwrap(), k * 2);
+            assert_eq!(
+                table.get_column::<String>("label", &k).unwrap(),
+                &format!("#{k}")
+            );
+            let row = table.row(&k).unwrap();
+            assert_eq!(*row.key(), k);
+            assert_eq!(*row.get::<bool>("odd").unwrap(), k % 2 == 1);
+        }
+        assert!(matches!(double.get(&10_000), Err(KvError::KeyNotFoundFast)));
+        assert!(table.row(&10_000).is_none());
+        assert_eq!(double.iter().count(), 10_000);
+        assert!(double.iter().all(|(k, v)| *v == k * 2));
+
+        // One index, and each column costs only its values
+        let index_bytes = table.index().memory_usage_bytes();
+        assert_eq!(
+            table.memory_usage_bytes(),
+            index_bytes
+                + 10_000
+                    * (std::mem::size_of::<u64>()
+                        + std::mem::size_of::<String>()
+                        + std::mem::size_of::<bool>())
+        );
+    }
+
+    #[test]
+    fn test_column_errors() {
+        let mut table = KvTable::<u64, PortableHash>::from_keys(0..100).unwrap();
+        table.add_column("n", |&k| k as u32).unwrap();
+
+        assert!(matches!(
+            table.add_column("n", |_| 0u8),
+            Err(KvError::ColumnExists(name)) if name == "n"
+        ));
+        assert!(matches!(
+            table.get_column::<u32>("missing", &1),
+            Err(KvError::ColumnNotFound(name)) if name == "missing"
+        ));
+        assert!(matches!(
+            table.get_column::<u64>("n", &1),
+            Err(KvError::ColumnType {
+                actual: "u32",
+                expected: "u64",
+                ..
+            })
+        ));
+
+        assert!(table.remove_column("n"));
+        assert!(!table.remove_column("n"));
+        table.add_column("n", |&k| k).unwrap();
+        assert_eq!(*table.get_column::<u64>("n", &99).unwrap(), 99);
+    }
+
+    #[test]
+    fn test_from_store_and_hashed_rows() {
+        let store: VerifiedKvStore<String, u32, PortableHash> = VerifiedKvStoreBuilder::new()
+            .extend((0..500).map(|i| (format!("key-{i}"), i)))
+            .hash_seed(3)
+            .seed(3)
+            .build()
+            .unwrap();
+        let mut table = KvTable::from_store(store, "id");
+        table.add_column("len", |k: &String| k.len()).unwrap();
+
+        let key = "key-123".to_string();
+        let hashed = table.hash_key(&key);
+        let row = table.row_hashed(&hashed).unwrap();
+        assert_eq!(*row.get::<u32>("id").unwrap(), 123);
+        assert_eq!(*row.get::<usize>("len").unwrap(), 7);
+        assert_eq!(
+            *table
+                .column::<u32>("id")
+                .unwrap()
+                .get_hashed(&hashed)
+                .unwrap(),
+            123
+        );
+    }
+}
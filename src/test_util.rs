@@ -0,0 +1,13 @@
+//! Fixtures shared by the unit tests of the FFI and server modules.
+
+use crate::hasher::PortableHash;
+use crate::{VerifiedKvStore, VerifiedKvStoreBuilder};
+
+/// Write a `String` → `String` store with `entries` to `path`.
+pub(crate) fn write_store(path: &str, entries: &[(&str, &str)]) {
+    let store: VerifiedKvStore<String, String, PortableHash> = VerifiedKvStoreBuilder::new()
+        .extend(entries.iter().map(|(k, v)| (k.to_string(), v.to_string())))
+        .build()
+        .unwrap();
+    store.save_to_file(path).unwrap();
+}
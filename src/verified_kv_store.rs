@@ -4,45 +4,76 @@
 //! Use this when you need strong guarantees that lookups won't return incorrect values.
 
 use crate::error::KvError;
-use ptr_hash::bucket_fn::Linear;
-use ptr_hash::hash::StringHash;
-use ptr_hash::{PtrHash, PtrHashParams};
-use serde::{Deserialize, Serialize};
+use crate::hasher::{
+    random_hash_seed, DefaultHasherFor, HashPlatform, HashWidth, HasherKind, KvKey, StoreHasher,
+    StrHasher,
+};
+use crate::mphf::{hash_tag, Mphf};
+use crate::persistence::{PersistedData, PersistenceStrategy, StoreMetadata, WriteOptions};
+use crate::prefilter::{Prefilter, PrefilterStats};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::{Borrow, Cow};
 use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 use std::path::Path;
 
 /// Safe key-value store that verifies every lookup.
 ///
-/// Designed for String → Value mappings with:
+/// Designed for Key → Value mappings with:
 /// - O(1) lookups using Minimal Perfect Hash Functions (MPHF)
 /// - Key verification (returns errors for non-existent keys, never wrong values)
 /// - Full serialization and persistence support
 /// - Complete API: iter(), keys(), values(), contains_key()
 ///
-/// Uses GxHash (AES-NI accelerated) for optimal string key distribution.
+/// String keys use GxHash (AES-NI accelerated); integer keys use a cheap
+/// multiplicative hash. See [`crate::hasher`] for the defaults.
 ///
-/// # Type Parameter
+/// # Type Parameters
+/// - `K`: Key type (`String`, integers, `(u32, u32)`, ...)
 /// - `V`: Value type (must be cloneable and serializable)
+/// - `H`: Hasher used by the MPHF, defaults to [`DefaultHasherFor<K>`]
+///
+/// # Reproducible builds
+///
+/// [`save_to_file`](Self::save_to_file) output is byte-identical for the same
+/// entries (in any insertion order) built with the same
+/// [`VerifiedKvStoreBuilder::seed`], provided that:
+/// - `H` is platform independent, i.e. not [`GxHash`](crate::hasher::GxHash)
+/// - the file format version is unchanged
+///
+/// Thread count, platform and the order in which entries were added make no
+/// difference. Loading and re-saving a file also reproduces it exactly.
 #[derive(Clone)]
-pub struct VerifiedKvStore<V>
+pub struct VerifiedKvStore<K, V, H = DefaultHasherFor<K>>
 where
-    V: Clone,
+    K: KvKey,
+    H: StoreHasher<K>,
 {
-    mphf: PtrHash<String, Linear, Vec<u32>, StringHash, Vec<u8>>,
+    mphf: Mphf<K, H>,
+    hash_seed: u64,
     values: Vec<V>,
-    keys: Vec<String>, // Keep keys for verification
+    keys: Vec<K>, // Keep keys for verification
     len: usize,
+    prefilter: Option<Prefilter>,
+    /// 32 bits of each slot's key hash, checked before comparing keys.
+    tags: Option<Vec<u32>>,
+    /// Slots in ascending key order, for ordered queries.
+    order: Option<Vec<u32>>,
 }
 
-// Main implementation
-impl<V> VerifiedKvStore<V>
+// Constructors using the default hasher for `K`
+impl<K, V> VerifiedKvStore<K, V>
 where
+    K: KvKey,
     V: Clone,
 {
-    /// Create a new VerifiedKvStore from a HashMap with String keys.
+    /// Create a new VerifiedKvStore from a HashMap.
     ///
-    /// Uses GxHash (AES-NI accelerated) which provides excellent hash distribution
-    /// for all string patterns - sequential, random, UUID-style, etc.
+    /// For String keys this uses GxHash (AES-NI accelerated) which provides excellent
+    /// hash distribution for all string patterns - sequential, random, UUID-style, etc.
     ///
     /// # Example
     /// ```
@@ -56,16 +87,94 @@ where
     ///
     /// assert_eq!(store.get(&"key1".to_string()).unwrap(), "value1");
     /// ```
-    pub fn new(data: HashMap<String, V>) -> Result<Self, KvError> {
+    pub fn new(data: HashMap<K, V>) -> Result<Self, KvError> {
+        Self::with_hasher(data)
+    }
+}
+
+// Main implementation
+impl<K, V, H> VerifiedKvStore<K, V, H>
+where
+    K: KvKey,
+    V: Clone,
+    H: StoreHasher<K>,
+{
+    /// Create a new VerifiedKvStore from a HashMap, hashing keys with `H`.
+    ///
+    /// # Example
+    /// ```
+    /// use learned_kv::hasher::PortableHash;
+    /// use learned_kv::VerifiedKvStore;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("key1".to_string(), 1);
+    /// let store = VerifiedKvStore::<_, _, PortableHash>::with_hasher(data).unwrap();
+    ///
+    /// assert_eq!(store.get(&"key1".to_string()).unwrap(), &1);
+    /// ```
+    pub fn with_hasher(data: HashMap<K, V>) -> Result<Self, KvError> {
+        Self::with_hash_width(data, HashWidth::Auto)
+    }
+
+    /// Create a new VerifiedKvStore using the given key hash width.
+    ///
+    /// `HashWidth::Auto` switches to 128-bit hashes above
+    /// [`WIDE_HASH_THRESHOLD`](crate::hasher::WIDE_HASH_THRESHOLD) keys.
+    pub fn with_hash_width(data: HashMap<K, V>, width: HashWidth) -> Result<Self, KvError> {
+        let seed = random_hash_seed()?;
+        Self::build_seeded(data, width, retry_hash_seeds(seed, false), seed)
+    }
+
+    /// Build with an explicit MPHF seed, trying the secret hash seeds from
+    /// `hash_seeds` in turn until the key hashes are distinct.
+    ///
+    /// # Errors
+    ///
+    /// - `KvError::EmptyKeySet` if `data` is empty
+    /// - `KvError::IncompatibleHasher` if this CPU can't run `H`
+    /// - `KvError::DuplicateHashes` if keys collide under every hash seed tried
+    /// - `KvError::PilotSearchFailed` if the MPHF search fails for another reason
+    fn build_seeded<I>(
+        data: HashMap<K, V>,
+        width: HashWidth,
+        hash_seeds: I,
+        seed: u64,
+    ) -> Result<Self, KvError>
+    where
+        I: IntoIterator<Item = Result<u64, KvError>>,
+    {
         if data.is_empty() {
             return Err(KvError::EmptyKeySet);
         }
 
-        let keys: Vec<String> = data.keys().cloned().collect();
+        if !H::KIND.is_available() {
+            return Err(H::KIND.unavailable());
+        }
+
+        let keys: Vec<K> = data.keys().cloned().collect();
         let n = keys.len();
 
-        let mphf = PtrHash::new(&keys, PtrHashParams::default());
+        let mut result = Err(KvError::DuplicateHashes);
+        for hash_seed in hash_seeds {
+            let hash_seed = hash_seed?;
+            result = Mphf::build(&keys, width, hash_seed, seed).map(|mphf| (mphf, hash_seed));
+            if !matches!(result, Err(KvError::DuplicateHashes)) {
+                break;
+            }
+        }
+        let (mphf, hash_seed) = result?;
 
+        Ok(Self::from_entries(mphf, hash_seed, data, n))
+    }
+
+    /// Place every entry at its MPHF index.
+    ///
+    /// `entries` must contain exactly the `n` keys `mphf` was built from.
+    fn from_entries<I>(mphf: Mphf<K, H>, hash_seed: u64, entries: I, n: usize) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
         // Allocate values vector
         let mut values: Vec<V> = Vec::with_capacity(n);
         // SAFETY: We're about to initialize all n elements via ptr::write
@@ -76,7 +185,7 @@ where
         }
 
         // Allocate keys vector for verification
-        let mut key_array: Vec<String> = Vec::with_capacity(n);
+        let mut key_array: Vec<K> = Vec::with_capacity(n);
         // SAFETY: We're about to initialize all n elements via ptr::write
         // Clippy false positive: we DO initialize all elements via ptr::write below
         #[allow(clippy::uninit_vec)]
@@ -89,7 +198,7 @@ where
         let mut written = vec![false; n];
 
         // Fill both arrays
-        for (key, value) in data {
+        for (key, value) in entries {
             let index = mphf.index(&key);
 
             // Verify MPHF guarantees
@@ -128,12 +237,16 @@ where
                 .collect::<Vec<_>>()
         );
 
-        Ok(Self {
+        Self {
             mphf,
+            hash_seed,
             values,
             keys: key_array,
             len: n,
-        })
+            prefilter: None,
+            tags: None,
+            order: None,
+        }
     }
 
     /// Fast lookup with key verification.
@@ -143,37 +256,191 @@ where
     /// - Returns errors for non-existent keys
     /// - Uses MPHF for O(1) lookup time
     #[inline(always)]
-    pub fn get(&self, key: &String) -> Result<&V, KvError> {
-        let index = self.mphf.index(key);
+    pub fn get(&self, key: &K) -> Result<&V, KvError> {
+        match self.slot(key) {
+            Some(index) => Ok(&self.values[index]),
+            None => Err(KvError::KeyNotFoundFast),
+        }
+    }
+
+    /// Lookup with detailed error messages.
+    pub fn get_detailed(&self, key: &K) -> Result<&V, KvError>
+    where
+        K: Debug,
+    {
+        match self.slot(key) {
+            Some(index) => Ok(&self.values[index]),
+            None => Err(KvError::KeyNotFound {
+                key: format!("{:?}", key),
+            }),
+        }
+    }
+
+    /// Check if a key is in the store (accurate, no false positives).
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.slot(key).is_some()
+    }
+
+    /// Hash `key` once for lookups with [`get_hashed`](Self::get_hashed),
+    /// here or in any store with the same hasher, hash seed and hash width.
+    #[inline(always)]
+    pub fn hash_key<'k>(&self, key: &'k K) -> HashedKey<'k, K, H> {
+        HashedKey {
+            key,
+            hx: self.mphf.hash(key),
+            hash_seed: self.hash_seed,
+            width: self.mphf.width(),
+            _hasher: PhantomData,
+        }
+    }
+
+    /// Lookup with a precomputed hash; same contract as [`get`](Self::get).
+    ///
+    /// The hash is reused if `key` was hashed for a store with the same hash
+    /// seed and hash width, and recomputed otherwise.
+    #[inline(always)]
+    pub fn get_hashed(&self, key: &HashedKey<'_, K, H>) -> Result<&V, KvError> {
+        match self.slot_hashed(key) {
+            Some(index) => Ok(&self.values[index]),
+            None => Err(KvError::KeyNotFoundFast),
+        }
+    }
+
+    /// [`contains_key`](Self::contains_key) with a precomputed hash.
+    #[inline(always)]
+    pub fn contains_hashed(&self, key: &HashedKey<'_, K, H>) -> bool {
+        self.slot_hashed(key).is_some()
+    }
+
+    /// Verified slot index of `key`, for stores that keep values elsewhere.
+    #[inline(always)]
+    pub(crate) fn slot(&self, key: &K) -> Option<usize> {
+        self.slot_with_hash(key, self.mphf.hash(key))
+    }
+
+    /// Key in `slot`, which must be below [`len`](Self::len).
+    pub(crate) fn key_at(&self, slot: usize) -> &K {
+        &self.keys[slot]
+    }
+
+    /// [`slot`](Self::slot) with a precomputed hash, if it is valid here.
+    #[inline(always)]
+    pub(crate) fn slot_hashed(&self, key: &HashedKey<'_, K, H>) -> Option<usize> {
+        if key.hash_seed == self.hash_seed && key.width == self.mphf.width() {
+            self.slot_with_hash(key.key, key.hx)
+        } else {
+            self.slot(key.key)
+        }
+    }
+
+    #[inline(always)]
+    fn slot_with_hash<Q: ?Sized>(&self, key: &Q, hx: u128) -> Option<usize>
+    where
+        K: PartialEq<Q>,
+    {
+        if let Some(prefilter) = &self.prefilter {
+            if !prefilter.may_contain(hx) {
+                return None;
+            }
+        }
+        let index = self.mphf.index_from_hash(hx);
 
         // Must use safe indexing because we don't know if this is the right key
         // until AFTER we check. For non-existent keys, MPHF returns *some* index
         // which might be < len but point to a different key.
-        if index < self.len && self.keys[index] == *key {
-            Ok(&self.values[index])
-        } else {
-            Err(KvError::KeyNotFoundFast)
+        if index >= self.len {
+            return None;
         }
+        if let Some(tags) = &self.tags {
+            if tags[index] != hash_tag(hx) {
+                return None;
+            }
+        }
+        (self.keys[index] == *key).then_some(index)
     }
 
-    /// Lookup with detailed error messages.
-    pub fn get_detailed(&self, key: &String) -> Result<&V, KvError> {
-        let index = self.mphf.index(key);
+    /// Keep a 32-bit tag of each slot's key hash, so that most lookups of
+    /// absent keys are rejected without comparing key bytes.
+    ///
+    /// Costs 4 bytes per key and pays off for long keys, especially ones
+    /// sharing long prefixes. The tags come from the hash the MPHF computes
+    /// anyway. Saved with the store like the prefilter.
+    pub fn with_hash_tags(mut self) -> Self {
+        self.tags = Some(
+            self.keys
+                .iter()
+                .map(|key| hash_tag(self.mphf.hash(key)))
+                .collect(),
+        );
+        self
+    }
+
+    /// Drop the slot hash tags, if any.
+    pub fn without_hash_tags(mut self) -> Self {
+        self.tags = None;
+        self
+    }
+
+    /// Whether the store keeps slot hash tags.
+    pub fn has_hash_tags(&self) -> bool {
+        self.tags.is_some()
+    }
+
+    /// Put a Bloom filter in front of the MPHF, sized for `fp_rate`.
+    ///
+    /// Lookups of absent keys that the filter rejects return
+    /// `KvError::KeyNotFoundFast` without touching the keys. The filter
+    /// reuses the MPHF's key hash and costs about `1.44 * log2(1 / fp_rate)`
+    /// bits per key; rates below about 5e-6 are not reachable. The setting is
+    /// saved with the store, and the filter rebuilt on load.
+    pub fn with_prefilter(self, fp_rate: f64) -> Self {
+        self.with_prefilter_bits(Prefilter::bits_for_rate(fp_rate))
+    }
 
-        if index < self.len && self.keys[index] == *key {
-            Ok(&self.values[index])
+    /// Drop the prefilter, if any.
+    pub fn without_prefilter(mut self) -> Self {
+        self.prefilter = None;
+        self
+    }
+
+    /// Size and estimated false-positive rate of the prefilter, if there is one.
+    pub fn prefilter_stats(&self) -> Option<PrefilterStats> {
+        self.prefilter.as_ref().map(Prefilter::stats)
+    }
+
+    fn with_hash_tags_if(self, enabled: bool) -> Self {
+        if enabled {
+            self.with_hash_tags()
         } else {
-            Err(KvError::KeyNotFound {
-                key: format!("{:?}", key),
-            })
+            self
         }
     }
 
-    /// Check if a key is in the store (accurate, no false positives).
-    #[inline(always)]
-    pub fn contains_key(&self, key: &String) -> bool {
-        let index = self.mphf.index(key);
-        index < self.len && self.keys[index] == *key
+    /// Build a prefilter with `bits_per_key`, or none for 0.
+    fn with_prefilter_bits(mut self, bits_per_key: u8) -> Self {
+        self.prefilter = (bits_per_key > 0).then(|| {
+            let hashes = self.keys.iter().map(|key| self.mphf.hash(key));
+            Prefilter::build(hashes, self.len, bits_per_key)
+        });
+        self
+    }
+
+    /// Whether the store keeps a sorted key index; see
+    /// [`with_sorted_index`](Self::with_sorted_index).
+    pub fn has_sorted_index(&self) -> bool {
+        self.order.is_some()
+    }
+
+    /// Drop the sorted key index, if any.
+    pub fn without_sorted_index(mut self) -> Self {
+        self.order = None;
+        self
+    }
+
+    /// The sorted key index as saved: empty without one.
+    pub(crate) fn persisted_order(&self) -> Vec<u32> {
+        self.order.clone().unwrap_or_default()
     }
 
     /// Returns the number of key-value pairs in the store.
@@ -186,8 +453,30 @@ where
         self.len == 0
     }
 
+    /// Returns the width of the key hashes used by the MPHF.
+    pub fn hash_width(&self) -> HashWidth {
+        self.mphf.width()
+    }
+
+    /// Returns the secret seed of every key hash.
+    ///
+    /// Treat it like a key: anyone who knows it can craft colliding key sets.
+    pub fn hash_seed(&self) -> u64 {
+        self.hash_seed
+    }
+
+    /// Returns the global seed the MPHF settled on. Recorded in the file header.
+    pub fn global_seed(&self) -> u64 {
+        self.mphf.global_seed()
+    }
+
+    /// Returns the hash function family used for keys.
+    pub fn hasher_kind(&self) -> HasherKind {
+        H::KIND
+    }
+
     /// Returns an iterator over all keys in the store.
-    pub fn keys(&self) -> impl Iterator<Item = &String> {
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
         self.keys.iter()
     }
 
@@ -197,10 +486,35 @@ where
     }
 
     /// Returns an iterator over all key-value pairs.
-    pub fn iter(&self) -> impl Iterator<Item = (&String, &V)> {
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
         self.keys.iter().zip(self.values.iter())
     }
 
+    /// Verified slot index of `key` in `[0, len)`, for side data kept in
+    /// arrays aligned to [`slot_order`](Self::slot_order).
+    ///
+    /// Returns `KvError::KeyNotFoundFast` for keys not in the store.
+    #[inline(always)]
+    pub fn index_of(&self, key: &K) -> Result<usize, KvError> {
+        self.slot(key).ok_or(KvError::KeyNotFoundFast)
+    }
+
+    /// Key and value in slot `index`, or `None` past the end.
+    #[inline(always)]
+    pub fn get_by_index(&self, index: usize) -> Option<(&K, &V)> {
+        Some((self.keys.get(index)?, &self.values[index]))
+    }
+
+    /// Keys by slot: `slot_order()[i]` is the key at index `i`.
+    ///
+    /// Stable for the life of the store's file: the keys section is written in
+    /// this order, and loading rebuilds the same MPHF, so the indices survive
+    /// a save and load. Building again, even from the same entries, gives
+    /// another order unless the build is [reproducible](#reproducible-builds).
+    pub fn slot_order(&self) -> &[K] {
+        &self.keys
+    }
+
     /// Returns the approximate **stack-allocated** memory usage in bytes.
     ///
     /// ⚠️ **IMPORTANT - INCOMPLETE MEASUREMENT:**
@@ -216,15 +530,99 @@ where
     pub fn memory_usage_bytes(&self) -> usize {
         std::mem::size_of::<Self>()
             + self.values.capacity() * std::mem::size_of::<V>()
-            + self.keys.capacity() * std::mem::size_of::<String>()
+            + self.keys.capacity() * std::mem::size_of::<K>()
+            + self.prefilter_stats().map_or(0, |stats| stats.bytes)
+            + self.tags.as_ref().map_or(0, |tags| tags.capacity() * 4)
+            + self.order.as_ref().map_or(0, |order| order.capacity() * 4)
         // Note: MPHF memory not included (requires mem_dbg feature)
     }
 }
 
-// Serialization support
-impl<V> VerifiedKvStore<V>
+// Taking the store apart works for any value type
+impl<K, V, H> VerifiedKvStore<K, V, H>
 where
+    K: KvKey,
+    H: StoreHasher<K>,
+{
+    /// Detach the values (in slot order) and keep the verified key index.
+    pub(crate) fn split_values(self) -> (VerifiedKvStore<K, (), H>, Vec<V>) {
+        let index = VerifiedKvStore {
+            mphf: self.mphf,
+            hash_seed: self.hash_seed,
+            values: vec![(); self.len],
+            keys: self.keys,
+            len: self.len,
+            prefilter: self.prefilter,
+            tags: self.tags,
+            order: self.order,
+        };
+        (index, self.values)
+    }
+
+    /// Take the store apart.
+    pub(crate) fn into_parts(self) -> StoreParts<K, V, H> {
+        StoreParts {
+            mphf: self.mphf,
+            hash_seed: self.hash_seed,
+            prefilter: self.prefilter,
+            tags: self.tags,
+            keys: self.keys,
+            values: self.values,
+        }
+    }
+}
+
+/// Lookups by `&str`, which hash and compare the key in place instead of
+/// allocating a `String` for it.
+impl<V, H> VerifiedKvStore<String, V, H>
+where
+    V: Clone,
+    H: StrHasher,
+{
+    /// [`get`](Self::get) by `&str`.
+    ///
+    /// # Example
+    /// ```
+    /// use learned_kv::VerifiedKvStoreBuilder;
+    ///
+    /// let store = VerifiedKvStoreBuilder::<String, u32>::new()
+    ///     .insert("apple".to_string(), 3)
+    ///     .build()?;
+    /// assert_eq!(store.get_str("apple")?, &3);
+    /// # Ok::<(), learned_kv::KvError>(())
+    /// ```
+    #[inline(always)]
+    pub fn get_str(&self, key: &str) -> Result<&V, KvError> {
+        match self.slot_str(key) {
+            Some(index) => Ok(&self.values[index]),
+            None => Err(KvError::KeyNotFoundFast),
+        }
+    }
+
+    /// [`contains_key`](Self::contains_key) by `&str`.
+    #[inline(always)]
+    pub fn contains_str(&self, key: &str) -> bool {
+        self.slot_str(key).is_some()
+    }
+
+    /// [`index_of`](Self::index_of) by `&str`.
+    #[inline(always)]
+    pub fn index_of_str(&self, key: &str) -> Result<usize, KvError> {
+        self.slot_str(key).ok_or(KvError::KeyNotFoundFast)
+    }
+
+    /// [`slot`](Self::slot) by `&str`.
+    #[inline(always)]
+    pub(crate) fn slot_str(&self, key: &str) -> Option<usize> {
+        self.slot_with_hash(key, self.mphf.hash_borrowed(key))
+    }
+}
+
+impl<K, V, H> VerifiedKvStore<K, V, H>
+where
+    K: KvKey + Serialize + for<'de> Deserialize<'de>,
     V: Clone + Serialize + for<'de> Deserialize<'de>,
+    H: StoreHasher<K>,
 {
     /// Save the store to a file with integrity protection.
     ///
@@ -259,15 +657,73 @@ where
     /// # Ok::<(), learned_kv::KvError>(())
     /// ```
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), KvError> {
-        use crate::persistence::{write_with_integrity, PersistedData, PersistenceStrategy};
+        self.save_to_file_with(path, &WriteOptions::default())
+    }
 
-        let data = PersistedData {
+    /// Save the store to a file, compressing sections as set in `options`.
+    ///
+    /// Loading needs no options: the compression is recorded in the header.
+    pub fn save_to_file_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<(), KvError> {
+        crate::persistence::write_with_integrity(
+            path,
+            &self.persisted_data(),
+            &self.metadata(),
+            options,
+        )
+    }
+
+    /// Write the store to `writer` in the same checksummed format as
+    /// [`save_to_file`](Self::save_to_file).
+    ///
+    /// Use this to send stores over pipes or sockets, or to embed them in
+    /// archives and blobs; [`AtomicWriter`](crate::AtomicWriter) gives the crash-safe file behaviour
+    /// of `save_to_file`.
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), KvError> {
+        self.to_writer_with(writer, &WriteOptions::default())
+    }
+
+    /// [`to_writer`](Self::to_writer) with the given compression settings.
+    pub fn to_writer_with<W: Write>(
+        &self,
+        writer: W,
+        options: &WriteOptions,
+    ) -> Result<(), KvError> {
+        crate::persistence::write_to(writer, &self.persisted_data(), &self.metadata(), options)
+    }
+
+    /// Encode the store as the bytes [`save_to_file`](Self::save_to_file) would write.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use learned_kv::VerifiedKvStoreBuilder;
+    /// use learned_kv::VerifiedKvStore;
+    ///
+    /// let store = VerifiedKvStoreBuilder::<u64, String>::new()
+    ///     .insert(7, "seven".to_string())
+    ///     .build()?;
+    /// let bytes = store.to_bytes()?;
+    ///
+    /// let copy: VerifiedKvStore<u64, String> = VerifiedKvStore::from_bytes(&bytes)?;
+    /// assert_eq!(copy.get(&7)?, "seven");
+    /// # Ok::<(), learned_kv::KvError>(())
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>, KvError> {
+        let mut bytes = Vec::new();
+        self.to_writer(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn persisted_data(&self) -> PersistedData<K, V> {
+        PersistedData {
             keys: self.keys.clone(),
             values: self.values.clone(),
-            mphf_data: None,
-        };
-
-        write_with_integrity(path, &data, PersistenceStrategy::RebuildOnLoad)
+            order: self.persisted_order(),
+        }
     }
 
     /// Load the store from a file with integrity validation.
@@ -292,77 +748,558 @@ where
     /// - Version is incompatible
     /// - Checksum doesn't match
     /// - Data is structurally invalid
-    /// - **MPHF construction fails** (can happen with certain key patterns)
+    /// - The file was hashed with a different hasher than `H`, or with GxHash on
+    ///   another CPU family (`KvError::IncompatibleHasher`)
+    /// - The keys were normalized; such files load as a
+    ///   [`NormalizedKvStore`](crate::normalize::NormalizedKvStore)
+    ///   (`KvError::IncompatibleNormalizer`)
+    /// - **MPHF construction fails** (`KvError::DuplicateHashes` or
+    ///   `KvError::PilotSearchFailed`)
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, KvError> {
-        use crate::persistence::read_with_validation;
+        let (data, meta) = crate::persistence::read_with_validation(path)?;
+        Self::from_persisted(data, &meta)
+    }
+
+    /// Read a store written by [`to_writer`](Self::to_writer) or
+    /// [`save_to_file`](Self::save_to_file).
+    ///
+    /// Reads exactly the bytes of one store, so `reader` can carry several
+    /// stores back to back or stay open afterwards. Unbuffered readers such
+    /// as sockets are best wrapped in a `BufReader`.
+    ///
+    /// Validation and errors are the same as for [`load_from_file`](Self::load_from_file).
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, KvError> {
+        let (data, meta) = crate::persistence::read_from(reader)?;
+        Self::from_persisted(data, &meta)
+    }
+
+    /// Decode a store from bytes produced by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KvError> {
+        Self::from_reader(bytes)
+    }
+}
+
+impl<K, V, H> VerifiedKvStore<K, V, H>
+where
+    K: KvKey,
+    V: Clone,
+    H: StoreHasher<K>,
+{
+    /// Settings recorded alongside the entries when saving.
+    pub(crate) fn metadata(&self) -> StoreMetadata {
+        StoreMetadata {
+            strategy: PersistenceStrategy::RebuildOnLoad,
+            hash_width: self.hash_width(),
+            hasher: H::KIND,
+            hash_platform: H::KIND.platform(),
+            hash_seed: self.hash_seed,
+            global_seed: self.global_seed(),
+            prefilter_bits_per_key: self.prefilter.as_ref().map_or(0, Prefilter::bits_per_key),
+            hash_tags: self.has_hash_tags(),
+            sorted_index: self.has_sorted_index(),
+            normalizer: String::new(),
+        }
+    }
+
+    /// Rebuild a saved store: same hasher, seeds and therefore slot layout.
+    pub(crate) fn from_persisted(
+        data: PersistedData<K, V>,
+        meta: &StoreMetadata,
+    ) -> Result<Self, KvError> {
+        Self::from_persisted_normalized(data, meta, "")
+    }
 
-        let (data, _strategy) = read_with_validation(path)?;
+    /// [`from_persisted`](Self::from_persisted) for keys normalized by the
+    /// normalizer named `normalizer`, empty for keys stored as given.
+    pub(crate) fn from_persisted_normalized(
+        data: PersistedData<K, V>,
+        meta: &StoreMetadata,
+        normalizer: &str,
+    ) -> Result<Self, KvError> {
+        crate::normalize::check_recorded(&meta.normalizer, normalizer)?;
 
         // Calculate length before moving keys
         let n = data.keys.len();
+        if n == 0 {
+            return Err(KvError::EmptyKeySet);
+        }
+        if data.values.len() != n {
+            return Err(KvError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} keys but {} values", n, data.values.len()),
+            )));
+        }
 
-        // Reconstruct MPHF from keys
+        if meta.strategy == PersistenceStrategy::BuildOnLoad {
+            // No seeds recorded: build as from scratch, under a fresh random
+            // seed, so that the store and any re-save of it keep a private
+            // hash seed
+            let entries: HashMap<K, V> = data.keys.into_iter().zip(data.values).collect();
+            if entries.len() != n {
+                return Err(KvError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Duplicate keys in the data section",
+                )));
+            }
+            let seed = random_hash_seed()?;
+            return Self::build_seeded(
+                entries,
+                HashWidth::Auto,
+                retry_hash_seeds(seed, false),
+                seed,
+            );
+        }
+        meta.hasher
+            .check_reproducible(meta.hash_platform, H::KIND)?;
+
+        // Reconstruct MPHF from keys with the recorded hash width and seeds
         // NOTE: MPHF serialization not implemented - always rebuild on load
-        let mphf = PtrHash::new(&data.keys, PtrHashParams::default());
+        let mphf = Mphf::rebuild(
+            &data.keys,
+            meta.hash_width,
+            meta.hash_seed,
+            meta.global_seed,
+        )?;
+
+        // The sorted index refers to positions in the file; map them to slots
+        let order = if meta.sorted_index {
+            Some(file_order_to_slots(&data.order, &data.keys, &mphf)?)
+        } else {
+            None
+        };
 
         // CRITICAL: New MPHF assigns different indices! Must reorder values to match.
-        // Build mapping: key → old_value, then use new MPHF to place values correctly
-        let mut reordered_values: Vec<V> = Vec::with_capacity(n);
-        // SAFETY: We're about to initialize all n elements
-        #[allow(clippy::uninit_vec)]
-        unsafe {
-            reordered_values.set_len(n);
-        }
+        let mut store = Self::from_entries(
+            mphf,
+            meta.hash_seed,
+            data.keys.into_iter().zip(data.values),
+            n,
+        )
+        .with_prefilter_bits(meta.prefilter_bits_per_key)
+        .with_hash_tags_if(meta.hash_tags);
+        store.order = order;
+        Ok(store)
+    }
+}
 
-        let mut reordered_keys: Vec<String> = Vec::with_capacity(n);
-        #[allow(clippy::uninit_vec)]
-        unsafe {
-            reordered_keys.set_len(n);
+/// Ordered queries. They use the sorted key index if the store has one, and
+/// otherwise sort the slots first, which costs O(n log n) per call.
+impl<K, V, H> VerifiedKvStore<K, V, H>
+where
+    K: KvKey + Ord,
+    V: Clone,
+    H: StoreHasher<K>,
+{
+    /// Keep the slots in key order, so that [`sorted_iter`](Self::sorted_iter),
+    /// [`range`](Self::range) and [`prefix_iter`](Self::prefix_iter) don't
+    /// have to sort.
+    ///
+    /// Costs 4 bytes per key. Saved with the store and checked on load.
+    ///
+    /// # Panics
+    ///
+    /// If the store has 2^32 keys or more.
+    pub fn with_sorted_index(mut self) -> Self {
+        self.order = Some(sort_slots(&self.keys));
+        self
+    }
+
+    /// Iterate over all entries in ascending key order.
+    pub fn sorted_iter(&self) -> SortedIter<'_, K, V> {
+        SortedIter::new(self, self.sorted_order(), 0..self.len)
+    }
+
+    /// Iterate over the entries whose keys fall in `range`, in ascending
+    /// key order.
+    ///
+    /// # Example
+    /// ```
+    /// use learned_kv::VerifiedKvStoreBuilder;
+    ///
+    /// let store = VerifiedKvStoreBuilder::<u64, u64>::new()
+    ///     .extend((0..100).map(|k| (k, k * k)))
+    ///     .sorted_index()
+    ///     .build()?;
+    /// let squares: Vec<u64> = store.range(10..13).map(|(_, v)| *v).collect();
+    /// assert_eq!(squares, [100, 121, 144]);
+    /// # Ok::<(), learned_kv::KvError>(())
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> SortedIter<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let order = self.sorted_order();
+        let key = |slot: &u32| self.keys[*slot as usize].borrow();
+        let start = match range.start_bound() {
+            Bound::Included(bound) => order.partition_point(|slot| key(slot) < bound),
+            Bound::Excluded(bound) => order.partition_point(|slot| key(slot) <= bound),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(bound) => order.partition_point(|slot| key(slot) <= bound),
+            Bound::Excluded(bound) => order.partition_point(|slot| key(slot) < bound),
+            Bound::Unbounded => order.len(),
+        };
+        SortedIter::new(self, order, start..end.max(start))
+    }
+
+    /// Iterate over the entries whose keys start with `prefix`, in ascending
+    /// key order.
+    ///
+    /// The key order must be the byte order of `as_ref()`, as it is for
+    /// `String` and `Vec<u8>`.
+    ///
+    /// # Example
+    /// ```
+    /// use learned_kv::VerifiedKvStoreBuilder;
+    ///
+    /// let store = VerifiedKvStoreBuilder::<String, u32>::new()
+    ///     .insert("user:123:name".to_string(), 1)
+    ///     .insert("user:123:mail".to_string(), 2)
+    ///     .insert("user:124:name".to_string(), 3)
+    ///     .sorted_index()
+    ///     .build()?;
+    /// let keys: Vec<&String> = store.prefix_iter("user:123:").map(|(k, _)| k).collect();
+    /// assert_eq!(keys, ["user:123:mail", "user:123:name"]);
+    /// # Ok::<(), learned_kv::KvError>(())
+    /// ```
+    pub fn prefix_iter<P>(&self, prefix: &P) -> SortedIter<'_, K, V>
+    where
+        K: AsRef<[u8]>,
+        P: AsRef<[u8]> + ?Sized,
+    {
+        let prefix = prefix.as_ref();
+        let order = self.sorted_order();
+        let key = |slot: &u32| self.keys[*slot as usize].as_ref();
+        let start = order.partition_point(|slot| key(slot) < prefix);
+        let end = start + order[start..].partition_point(|slot| key(slot).starts_with(prefix));
+        SortedIter::new(self, order, start..end)
+    }
+
+    fn sorted_order(&self) -> Cow<'_, [u32]> {
+        match &self.order {
+            Some(order) => Cow::Borrowed(order),
+            None => Cow::Owned(sort_slots(&self.keys)),
         }
+    }
+}
 
-        // Reorder both keys and values according to new MPHF indices
-        for (old_key, old_value) in data.keys.into_iter().zip(data.values.into_iter()) {
-            let new_index = mphf.index(&old_key);
+/// Slots of `keys` in ascending key order.
+fn sort_slots<K: Ord>(keys: &[K]) -> Vec<u32> {
+    let n = u32::try_from(keys.len()).expect("sorted index supports fewer than 2^32 keys");
+    let mut order: Vec<u32> = (0..n).collect();
+    order.sort_unstable_by(|&a, &b| keys[a as usize].cmp(&keys[b as usize]));
+    order
+}
 
-            // SAFETY: new_index guaranteed < n by MPHF, each index used exactly once
-            unsafe {
-                std::ptr::write(reordered_values.as_mut_ptr().add(new_index), old_value);
-                std::ptr::write(reordered_keys.as_mut_ptr().add(new_index), old_key);
+/// Turn a saved sorted index over file positions into one over the slots
+/// `mphf` puts those keys in, checking that it is a permutation.
+fn file_order_to_slots<K: KvKey, H: StoreHasher<K>>(
+    order: &[u32],
+    keys: &[K],
+    mphf: &Mphf<K, H>,
+) -> Result<Vec<u32>, KvError> {
+    let invalid = |message: String| {
+        KvError::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            message,
+        ))
+    };
+    if order.len() != keys.len() {
+        return Err(invalid(format!(
+            "sorted index has {} entries for {} keys",
+            order.len(),
+            keys.len()
+        )));
+    }
+    let mut seen = vec![false; keys.len()];
+    order
+        .iter()
+        .map(|&position| {
+            let position = position as usize;
+            if position >= keys.len() || std::mem::replace(&mut seen[position], true) {
+                return Err(invalid(format!(
+                    "sorted index repeats or exceeds {}",
+                    position
+                )));
             }
+            Ok(mphf.index(&keys[position]) as u32)
+        })
+        .collect()
+}
+
+/// Entries of a [`VerifiedKvStore`] in ascending key order, from
+/// [`sorted_iter`](VerifiedKvStore::sorted_iter),
+/// [`range`](VerifiedKvStore::range) or
+/// [`prefix_iter`](VerifiedKvStore::prefix_iter).
+pub struct SortedIter<'a, K, V> {
+    keys: &'a [K],
+    values: &'a [V],
+    order: Cow<'a, [u32]>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, K, V> SortedIter<'a, K, V> {
+    fn new<H>(
+        store: &'a VerifiedKvStore<K, V, H>,
+        order: Cow<'a, [u32]>,
+        positions: std::ops::Range<usize>,
+    ) -> Self
+    where
+        K: KvKey,
+        V: Clone,
+        H: StoreHasher<K>,
+    {
+        Self {
+            keys: &store.keys,
+            values: &store.values,
+            order,
+            front: positions.start,
+            back: positions.end,
         }
+    }
 
-        Ok(Self {
-            mphf,
-            values: reordered_values,
-            keys: reordered_keys,
-            len: n,
-        })
+    fn entry(&self, position: usize) -> (&'a K, &'a V) {
+        let slot = self.order[position] as usize;
+        (&self.keys[slot], &self.values[slot])
+    }
+}
+
+impl<'a, K, V> Iterator for SortedIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.entry(self.front - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for SortedIter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.entry(self.back))
+    }
+}
+
+impl<K, V> ExactSizeIterator for SortedIter<'_, K, V> {}
+
+/// A key with its hash computed once, from [`VerifiedKvStore::hash_key`].
+///
+/// Stores built with the same hasher and
+/// [`hash_seed`](VerifiedKvStoreBuilder::hash_seed) hash every key the same
+/// way, e.g. per-day stores over one key space. Looking a key up in all of
+/// them with [`VerifiedKvStore::get_hashed`] pays for hashing once.
+#[derive(Debug)]
+pub struct HashedKey<'k, K, H = DefaultHasherFor<K>> {
+    key: &'k K,
+    hx: u128,
+    hash_seed: u64,
+    width: HashWidth,
+    _hasher: PhantomData<H>,
+}
+
+impl<'k, K, H> HashedKey<'k, K, H> {
+    /// The key that was hashed.
+    pub fn key(&self) -> &'k K {
+        self.key
+    }
+}
+
+impl<K, H> Clone for HashedKey<'_, K, H> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, H> Copy for HashedKey<'_, K, H> {}
+
+/// A store taken apart by [`VerifiedKvStore::into_parts`].
+pub(crate) struct StoreParts<K: KvKey, V, H: StoreHasher<K>> {
+    pub(crate) mphf: Mphf<K, H>,
+    pub(crate) hash_seed: u64,
+    pub(crate) prefilter: Option<Prefilter>,
+    pub(crate) tags: Option<Vec<u32>>,
+    pub(crate) keys: Vec<K>,
+    pub(crate) values: Vec<V>,
+}
+
+/// Serde form of a store: its settings, then keys and values in slot order.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "VerifiedKvStore")]
+struct StoreRepr<K, V> {
+    hasher: HasherKind,
+    hash_platform: HashPlatform,
+    hash_width: HashWidth,
+    hash_seed: u64,
+    global_seed: u64,
+    #[serde(default)]
+    prefilter_bits_per_key: u8,
+    #[serde(default)]
+    hash_tags: bool,
+    keys: Vec<K>,
+    values: Vec<V>,
+    /// Sorted key index over `keys`, empty without one
+    #[serde(default)]
+    order: Vec<u32>,
+}
+
+/// Borrowing twin of [`StoreRepr`], so serializing doesn't clone entries.
+#[derive(Serialize)]
+#[serde(rename = "VerifiedKvStore")]
+struct StoreReprRef<'a, K, V> {
+    hasher: HasherKind,
+    hash_platform: HashPlatform,
+    hash_width: HashWidth,
+    hash_seed: u64,
+    global_seed: u64,
+    prefilter_bits_per_key: u8,
+    hash_tags: bool,
+    keys: &'a [K],
+    values: &'a [V],
+    order: &'a [u32],
+}
+
+/// Serializes the store's settings, keys and values.
+///
+/// PtrHash has no serde support, so the MPHF itself is not written; the
+/// recorded seeds let deserialization rebuild it with the same slot layout.
+/// The output includes the secret [`hash_seed`](VerifiedKvStore::hash_seed).
+impl<K, V, H> Serialize for VerifiedKvStore<K, V, H>
+where
+    K: KvKey + Serialize,
+    V: Clone + Serialize,
+    H: StoreHasher<K>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        StoreReprRef {
+            hasher: H::KIND,
+            hash_platform: H::KIND.platform(),
+            hash_width: self.hash_width(),
+            hash_seed: self.hash_seed,
+            global_seed: self.global_seed(),
+            prefilter_bits_per_key: self.prefilter.as_ref().map_or(0, Prefilter::bits_per_key),
+            hash_tags: self.has_hash_tags(),
+            keys: &self.keys,
+            values: &self.values,
+            order: self.order.as_deref().unwrap_or_default(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Rebuilds the MPHF from the serialized keys and seeds. Fails with the same
+/// conditions as [`VerifiedKvStore::load_from_file`], reported as `D::Error`.
+impl<'de, K, V, H> Deserialize<'de> for VerifiedKvStore<K, V, H>
+where
+    K: KvKey + Deserialize<'de>,
+    V: Clone + Deserialize<'de>,
+    H: StoreHasher<K>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = StoreRepr::<K, V>::deserialize(deserializer)?;
+        let meta = StoreMetadata {
+            strategy: PersistenceStrategy::RebuildOnLoad,
+            hash_width: repr.hash_width,
+            hasher: repr.hasher,
+            hash_platform: repr.hash_platform,
+            hash_seed: repr.hash_seed,
+            global_seed: repr.global_seed,
+            prefilter_bits_per_key: repr.prefilter_bits_per_key,
+            hash_tags: repr.hash_tags,
+            sorted_index: !repr.order.is_empty(),
+            normalizer: String::new(),
+        };
+        let data = PersistedData {
+            keys: repr.keys,
+            values: repr.values,
+            order: repr.order,
+        };
+        Self::from_persisted(data, &meta).map_err(de::Error::custom)
     }
 }
 
+/// Hash seeds tried by builds whose hash seed isn't pinned.
+const HASH_SEED_ATTEMPTS: usize = 4;
+
+/// `first`, then further hash seeds for when keys collide under it. They
+/// are derived from `first` for `reproducible` builds, and random otherwise.
+fn retry_hash_seeds(first: u64, reproducible: bool) -> impl Iterator<Item = Result<u64, KvError>> {
+    let mut hash_seed = first;
+    (0..HASH_SEED_ATTEMPTS).map(move |attempt| {
+        if attempt > 0 {
+            hash_seed = if reproducible {
+                // SplitMix64 step
+                let mut z = hash_seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+            } else {
+                random_hash_seed()?
+            };
+        }
+        Ok(hash_seed)
+    })
+}
+
+/// Key sorter, captured where `K: Ord` so that `build` needs no such bound.
+type SortSlots<K> = fn(&[K]) -> Vec<u32>;
+
 /// Builder for constructing VerifiedKvStore instances.
-pub struct VerifiedKvStoreBuilder<V> {
-    data: HashMap<String, V>,
+pub struct VerifiedKvStoreBuilder<K, V, H = DefaultHasherFor<K>>
+where
+    K: KvKey,
+{
+    data: HashMap<K, V>,
+    hash_width: HashWidth,
+    hash_seed: Option<u64>,
+    seed: Option<u64>,
+    prefilter_bits_per_key: u8,
+    hash_tags: bool,
+    sort_slots: Option<SortSlots<K>>,
+    _hasher: PhantomData<H>,
 }
 
-impl<V> VerifiedKvStoreBuilder<V>
+impl<K, V, H> VerifiedKvStoreBuilder<K, V, H>
 where
+    K: KvKey,
     V: Clone,
+    H: StoreHasher<K>,
 {
     pub fn new() -> Self {
         Self {
             data: HashMap::new(),
+            hash_width: HashWidth::Auto,
+            hash_seed: None,
+            seed: None,
+            prefilter_bits_per_key: 0,
+            hash_tags: false,
+            sort_slots: None,
+            _hasher: PhantomData,
         }
     }
 
-    pub fn insert(mut self, key: String, value: V) -> Self {
+    pub fn insert(mut self, key: K, value: V) -> Self {
         self.data.insert(key, value);
         self
     }
 
     pub fn extend<I>(mut self, iter: I) -> Self
     where
-        I: IntoIterator<Item = (String, V)>,
+        I: IntoIterator<Item = (K, V)>,
     {
         self.data.extend(iter);
         self
@@ -370,21 +1307,119 @@ where
 
     pub fn with_entries<I>(iter: I) -> Self
     where
-        I: IntoIterator<Item = (String, V)>,
+        I: IntoIterator<Item = (K, V)>,
     {
         Self {
             data: HashMap::from_iter(iter),
+            hash_width: HashWidth::Auto,
+            hash_seed: None,
+            seed: None,
+            prefilter_bits_per_key: 0,
+            hash_tags: false,
+            sort_slots: None,
+            _hasher: PhantomData,
         }
     }
 
-    pub fn build(self) -> Result<VerifiedKvStore<V>, KvError> {
-        VerifiedKvStore::new(self.data)
+    /// Value added for `key`, if any.
+    pub(crate) fn get(&self, key: &K) -> Option<&V> {
+        self.data.get(key)
+    }
+
+    /// Whether an entry for `key` has been added.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.data.contains_key(key)
+    }
+
+    /// Number of entries added so far.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether no entries have been added.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Choose the key hash width (default `HashWidth::Auto`).
+    pub fn hash_width(mut self, width: HashWidth) -> Self {
+        self.hash_width = width;
+        self
+    }
+
+    /// Use a fixed secret hash seed instead of drawing one from OS randomness.
+    ///
+    /// Stores sharing a hasher and hash seed can share key hashes; see
+    /// [`HashedKey`]. Keep the seed private if the keys come from untrusted input.
+    ///
+    /// Unpinned builds retry under another hash seed when two keys hash
+    /// alike; with a pinned seed [`build`](Self::build) fails with
+    /// `KvError::DuplicateHashes` instead.
+    pub fn hash_seed(mut self, seed: u64) -> Self {
+        self.hash_seed = Some(seed);
+        self
+    }
+
+    /// Seed the MPHF construction instead of drawing a seed from OS randomness.
+    ///
+    /// With a fixed seed the build is reproducible; see
+    /// [Reproducible builds](VerifiedKvStore#reproducible-builds). Unless
+    /// [`hash_seed`](Self::hash_seed) is also set, the hash seed is the same
+    /// value, so keep it private if the keys come from untrusted input.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Put a Bloom filter sized for `fp_rate` in front of the MPHF; see
+    /// [`VerifiedKvStore::with_prefilter`].
+    pub fn prefilter(mut self, fp_rate: f64) -> Self {
+        self.prefilter_bits_per_key = Prefilter::bits_for_rate(fp_rate);
+        self
+    }
+
+    /// Keep slot hash tags; see [`VerifiedKvStore::with_hash_tags`].
+    pub fn hash_tags(mut self, enabled: bool) -> Self {
+        self.hash_tags = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<VerifiedKvStore<K, V, H>, KvError> {
+        let seed = match self.seed {
+            Some(seed) => seed,
+            None => random_hash_seed()?,
+        };
+        // A pinned hash seed is used as is; otherwise keys that happen to
+        // collide are retried under another hash seed
+        let hash_seeds: Box<dyn Iterator<Item = Result<u64, KvError>>> = match self.hash_seed {
+            Some(hash_seed) => Box::new(std::iter::once(Ok(hash_seed))),
+            None => Box::new(retry_hash_seeds(seed, self.seed.is_some())),
+        };
+        let mut store =
+            VerifiedKvStore::build_seeded(self.data, self.hash_width, hash_seeds, seed)?;
+        store.order = self.sort_slots.map(|sort| sort(&store.keys));
+        Ok(store
+            .with_prefilter_bits(self.prefilter_bits_per_key)
+            .with_hash_tags_if(self.hash_tags))
+    }
+}
+
+impl<K, V, H> VerifiedKvStoreBuilder<K, V, H>
+where
+    K: KvKey + Ord,
+{
+    /// Keep a sorted key index; see [`VerifiedKvStore::with_sorted_index`].
+    pub fn sorted_index(mut self) -> Self {
+        self.sort_slots = Some(sort_slots::<K>);
+        self
     }
 }
 
-impl<V> Default for VerifiedKvStoreBuilder<V>
+impl<K, V, H> Default for VerifiedKvStoreBuilder<K, V, H>
 where
+    K: KvKey,
     V: Clone,
+    H: StoreHasher<K>,
 {
     fn default() -> Self {
         Self::new()